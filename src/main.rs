@@ -1,14 +1,24 @@
+use std::process::ExitCode;
+
 use bevy::{asset::load_internal_binary_asset, log::LogPlugin, prelude::*, window::PrimaryWindow};
 use bevy_egui::EguiPlugin;
 use bevyon::BevyonPlugin;
-use schematic::SchematicPlugin;
+use schematic::{batch, SchematicPlugin};
 
 mod bevyon;
 mod input;
 mod schematic;
 pub use bevyon::{FillOptions, StrokeOptions};
 
-fn main() {
+fn main() -> ExitCode {
+    match batch::BatchArgs::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => return batch::run(args),
+        Ok(None) => {}
+        Err(usage) => {
+            eprintln!("{}", usage);
+            return ExitCode::from(2);
+        }
+    }
     let mut app = App::new();
     app.add_plugins(
         DefaultPlugins
@@ -36,6 +46,7 @@ fn main() {
         .add_systems(Startup, hide_cursor)
         .add_plugins(EguiPlugin)
         .run();
+    ExitCode::SUCCESS
 }
 
 fn hide_cursor(mut primary_window: Query<&mut Window, With<PrimaryWindow>>) {
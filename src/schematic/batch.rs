@@ -0,0 +1,176 @@
+/*
+headless batch mode: `scirke batch <schematic> [-o <dir>]`
+loads a saved schematic, assigns nets as the editor would, netlists it and runs every analysis
+directive on it, writing the results as csv. exits with 1 on simulator errors, 2 on usage or
+load errors. without libngspice the native solver is used, and says so on stderr.
+*/
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    sprite::Material2dPlugin,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_save::prelude::*;
+
+use crate::bevyon::BevyonPlugin;
+
+use super::{
+    add_save_load,
+    electrical::{run_batch, schematic_netlist, ElementsPlugin, SPRes},
+    infotext::InfoRes,
+    material::SchematicMaterial,
+    tools::{NewPickingCollider, SelectEvt},
+    ui::console::PrintConsoleLine,
+    LoadEvent, SavePipeline, SchematicChanged,
+};
+
+const USAGE: &str = "usage: scirke batch <schematic> [-o <output dir>]";
+
+pub struct BatchArgs {
+    /// saved schematic
+    schematic: PathBuf,
+    /// directory for netlists and results
+    out: PathBuf,
+}
+
+impl BatchArgs {
+    /// batch arguments from the command line, without the program name
+    /// None if not started in batch mode
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        match args.next().as_deref() {
+            Some("batch") => {}
+            _ => return Ok(None),
+        }
+        let mut schematic = None;
+        let mut out = PathBuf::from("out");
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--out" => out = args.next().ok_or(USAGE)?.into(),
+                _ if schematic.is_none() => schematic = Some(PathBuf::from(arg)),
+                _ => return Err(USAGE.to_owned()),
+            }
+        }
+        let schematic = schematic.ok_or(USAGE)?;
+        Ok(Some(Self { schematic, out }))
+    }
+}
+
+/// loads a schematic saved through [`SavePipeline`] from any path
+struct BatchPipeline {
+    /// path without extension, which the backend appends
+    key: String,
+}
+
+impl Pipeline for BatchPipeline {
+    type Backend = DefaultDebugBackend;
+    type Format = DefaultDebugFormat;
+
+    type Key<'a> = &'a str;
+
+    fn key(&self) -> Self::Key<'_> {
+        &self.key
+    }
+
+    fn capture(builder: SnapshotBuilder) -> Snapshot {
+        SavePipeline::capture(builder)
+    }
+
+    fn apply(world: &mut World, snapshot: &Snapshot) -> Result<(), bevy_save::Error> {
+        SavePipeline::apply(world, snapshot)
+    }
+}
+
+/// the electrical part of the schematic plugin, without window, renderer or ui
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: None,
+                    ..default()
+                }),
+                ..default()
+            })
+            .set(LogPlugin {
+                filter: "warn".into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>(),
+    );
+    app.add_plugins((
+        BevyonPlugin,
+        Material2dPlugin::<SchematicMaterial>::default(),
+        ElementsPlugin,
+    ));
+    add_save_load(&mut app);
+    // normally from the tools, info and console plugins
+    app.add_event::<NewPickingCollider>();
+    app.add_event::<SelectEvt>();
+    app.add_event::<PrintConsoleLine>();
+    app.init_resource::<InfoRes>();
+    app.add_systems(Last, print_console);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+fn print_console(mut e_console: EventReader<PrintConsoleLine>) {
+    for line in e_console.read() {
+        eprintln!("{}", line.line);
+    }
+}
+
+pub fn run(args: BatchArgs) -> ExitCode {
+    let mut app = headless_app();
+    let key = args.schematic.with_extension("");
+    let pipeline = BatchPipeline {
+        key: key.to_string_lossy().into_owned(),
+    };
+    if let Err(e) = app.world_mut().load(pipeline) {
+        eprintln!("{}: could not load: {:?}", args.schematic.display(), e);
+        return ExitCode::from(2);
+    }
+    // non-reflect components, then nets, as after loading in the editor
+    app.world_mut().send_event(LoadEvent);
+    app.update();
+    app.world_mut().send_event(SchematicChanged);
+    app.update();
+    let netlist = app.world_mut().run_system_once(schematic_netlist);
+    let stem = key
+        .file_name()
+        .map_or("schematic".into(), |s| s.to_string_lossy());
+    let spres = app.world().resource::<SPRes>();
+    // the log may be filtered, this should be seen whenever results are not from ngspice
+    if let Some(e) = spres.ngspice_error() {
+        eprintln!("using native solver, linear .op and .ac only: {}", e);
+    }
+    let outcome = run_batch(spres.backend(), &netlist, Path::new(&args.out), &stem);
+    for path in outcome.written.iter() {
+        println!("wrote {}", path.display());
+    }
+    if outcome.errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for e in outcome.errors.iter() {
+        eprintln!("error: {}", e);
+    }
+    ExitCode::from(1)
+}
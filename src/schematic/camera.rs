@@ -67,12 +67,20 @@ impl Default for SchematicCamera {
     }
 }
 
+/// request to move the camera onto a point of the schematic, zooming in if zoomed out further
+#[derive(Event)]
+pub struct CenterOn(pub Vec2);
+
+/// projection scale used by [`CenterOn`] at most
+const CENTER_ON_SCALE: f32 = 0.05;
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
+        app.add_event::<CenterOn>();
         app.add_systems(Startup, setup);
-        app.add_systems(PreUpdate, (zoom, pan));
+        app.add_systems(PreUpdate, (zoom, pan, center_on));
     }
 }
 
@@ -200,6 +208,19 @@ fn zoom(
         }
     }
 }
+fn center_on(
+    mut e_center: EventReader<CenterOn>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<SchematicCamera>>,
+) {
+    let Some(CenterOn(target)) = e_center.read().last() else {
+        return;
+    };
+    for (mut transform, mut proj) in &mut query {
+        transform.translation = target.extend(transform.translation.z);
+        proj.scale = proj.scale.min(CENTER_ON_SCALE);
+    }
+}
+
 /// max_scale_within_bounds is used to find the maximum safe zoom out/projection
 /// scale when we have been provided with minimum and maximum x boundaries for
 /// the camera.
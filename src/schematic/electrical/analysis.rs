@@ -0,0 +1,282 @@
+//! analysis configurations
+//! each analysis is kept as a resource so that its settings persist between runs
+//! and can be edited from the ui
+//! the analysis cards of a netlist are found by their dot command, see [`analysis_of`]
+
+use bevy::prelude::*;
+
+use super::units::parse_spice_number;
+
+/// analyses which are run from their dot command
+const ANALYSES: [&str; 4] = ["op", "dc", "ac", "tran"];
+
+/// analysis of a dot command, e.g. `tran` for `.tran 1u 1m`
+pub fn analysis_of(line: &str) -> Option<&'static str> {
+    let keyword = line.split_whitespace().next()?.to_lowercase();
+    let keyword = keyword.strip_prefix('.')?;
+    ANALYSES.into_iter().find(|a| *a == keyword)
+}
+
+/// one netlist per analysis card in `netlist`, each with only that card, as (analysis, netlist)
+/// the card is last, so that lines following it can be added before the `.end`
+pub fn split_analyses(netlist: &str) -> Vec<(&'static str, String)> {
+    let base = netlist
+        .lines()
+        .filter(|line| analysis_of(line).is_none())
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    netlist
+        .lines()
+        .filter_map(|line| Some((analysis_of(line)?, line.trim())))
+        .map(|(analysis, card)| (analysis, format!("{}{}\n", base, card)))
+        .collect()
+}
+
+/// ac sweep point spacing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AcSweep {
+    /// points per decade
+    #[default]
+    Dec,
+    /// points per octave
+    Oct,
+    /// total number of linearly spaced points
+    Lin,
+}
+
+impl AcSweep {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            AcSweep::Dec => "dec",
+            AcSweep::Oct => "oct",
+            AcSweep::Lin => "lin",
+        }
+    }
+    pub fn from_keyword(kw: &str) -> Option<Self> {
+        match kw.to_ascii_lowercase().as_str() {
+            "dec" => Some(AcSweep::Dec),
+            "oct" => Some(AcSweep::Oct),
+            "lin" => Some(AcSweep::Lin),
+            _ => None,
+        }
+    }
+}
+
+/// small signal ac analysis settings
+/// ngspice: ac dec|oct|lin points fstart fstop
+#[derive(Resource, Clone, Debug)]
+pub struct SimAc {
+    pub sweep: AcSweep,
+    pub points: u32,
+    pub fstart: f32,
+    pub fstop: f32,
+}
+
+impl Default for SimAc {
+    fn default() -> Self {
+        Self {
+            sweep: AcSweep::Dec,
+            points: 10,
+            fstart: 1.0,
+            fstop: 1e6,
+        }
+    }
+}
+
+impl SimAc {
+    /// spice command to run this analysis
+    pub fn command(&self) -> String {
+        format!(
+            "ac {} {} {:e} {:e}",
+            self.sweep.keyword(),
+            self.points.max(1),
+            self.fstart,
+            self.fstop
+        )
+    }
+    /// frequencies visited by the sweep, following ngspice point spacing
+    pub fn frequencies(&self) -> Vec<f64> {
+        let (fstart, fstop) = (self.fstart as f64, self.fstop as f64);
+        let points = self.points.max(1) as f64;
+        let log_step = match self.sweep {
+            AcSweep::Dec => 10f64.ln() / points,
+            AcSweep::Oct => 2f64.ln() / points,
+            AcSweep::Lin => {
+                if self.points <= 1 {
+                    return vec![fstart];
+                }
+                let step = (fstop - fstart) / (points - 1.0);
+                return (0..self.points).map(|i| fstart + step * i as f64).collect();
+            }
+        };
+        if !(fstart > 0.0 && fstop >= fstart) {
+            return vec![fstart];
+        }
+        let n = ((fstop / fstart).ln() / log_step + 1e-9).floor() as usize + 1;
+        (0..n)
+            .map(|i| fstart * (log_step * i as f64).exp())
+            .collect()
+    }
+}
+
+/// transient analysis settings
+/// ngspice: tran tstep tstop <tstart <tmax>>
+#[derive(Resource, Clone, Debug)]
+pub struct SimTran {
+    /// printing/plotting increment
+    pub tstep: f32,
+    /// final time
+    pub tstop: f32,
+    /// time at which results start being stored
+    pub tstart: f32,
+    /// maximum internal step size, left to ngspice if zero
+    pub tmax: f32,
+}
+
+impl Default for SimTran {
+    fn default() -> Self {
+        Self {
+            tstep: 1e-5,
+            tstop: 1e-3,
+            tstart: 0.0,
+            tmax: 0.0,
+        }
+    }
+}
+
+impl SimTran {
+    /// spice command to run this analysis
+    pub fn command(&self) -> String {
+        let mut cmd = format!("tran {:e} {:e} {:e}", self.tstep, self.tstop, self.tstart);
+        if self.tmax > 0.0 {
+            cmd.push_str(&format!(" {:e}", self.tmax));
+        }
+        cmd
+    }
+}
+
+/// one source swept by a dc analysis
+#[derive(Clone, Debug)]
+pub struct DcSweep {
+    /// spice name of the swept V or I source, e.g. `V1`
+    pub src: String,
+    pub start: f32,
+    pub stop: f32,
+    pub step: f32,
+}
+
+impl Default for DcSweep {
+    fn default() -> Self {
+        Self {
+            src: String::new(),
+            start: 0.0,
+            stop: 1.0,
+            step: 0.1,
+        }
+    }
+}
+
+impl DcSweep {
+    /// values the source takes over the sweep
+    pub fn values(&self) -> Vec<f32> {
+        let n = ((self.stop - self.start) / self.step).abs().round();
+        if !n.is_finite() {
+            return vec![self.start];
+        }
+        (0..=n as u32)
+            .map(|i| self.start + (self.stop - self.start).signum() * self.step.abs() * i as f32)
+            .collect()
+    }
+    fn args(&self) -> String {
+        format!(
+            "{} {:e} {:e} {:e}",
+            self.src, self.start, self.stop, self.step
+        )
+    }
+    /// read `src start stop step`
+    fn parse(args: &[&str]) -> Option<Self> {
+        let [src, start, stop, step] = args else {
+            return None;
+        };
+        Some(Self {
+            src: src.to_string(),
+            start: parse_spice_number(start)? as f32,
+            stop: parse_spice_number(stop)? as f32,
+            step: parse_spice_number(step)? as f32,
+        })
+    }
+}
+
+/// dc sweep analysis settings
+/// ngspice: dc srcnam vstart vstop vincr [src2 start2 stop2 incr2]
+/// the second source is the outer sweep, producing one curve per value
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SimDc {
+    pub sweep: DcSweep,
+    pub nested: Option<DcSweep>,
+}
+
+impl SimDc {
+    /// spice command to run this analysis, None if no source is picked
+    pub fn command(&self) -> Option<String> {
+        if self.sweep.src.is_empty() {
+            return None;
+        }
+        let mut cmd = format!("dc {}", self.sweep.args());
+        if let Some(nested) = self.nested.as_ref().filter(|n| !n.src.is_empty()) {
+            cmd.push(' ');
+            cmd.push_str(&nested.args());
+        }
+        Some(cmd)
+    }
+    /// read a dc dot command, e.g. `.dc V1 0 5 0.1 V2 0 1 0.5`
+    pub fn parse(card: &str) -> Option<Self> {
+        let tokens = card.split_whitespace().collect::<Vec<_>>();
+        if analysis_of(card)? != "dc" {
+            return None;
+        }
+        match tokens.len() {
+            5 => Some(Self {
+                sweep: DcSweep::parse(&tokens[1..5])?,
+                nested: None,
+            }),
+            9 => Some(Self {
+                sweep: DcSweep::parse(&tokens[1..5])?,
+                nested: Some(DcSweep::parse(&tokens[5..9])?),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_analyses() {
+        let netlist = "title\nR1 net_1 0 1k\n.tran 1u 1m\n.param x=1\n.OP\n";
+        let split = split_analyses(netlist);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].0, "tran");
+        assert_eq!(
+            split[0].1,
+            "title\nR1 net_1 0 1k\n.param x=1\n.tran 1u 1m\n"
+        );
+        assert_eq!(split[1].0, "op");
+        assert!(split[1].1.ends_with(".param x=1\n.OP\n"));
+        assert!(split_analyses("title\nR1 net_1 0 1k\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_dc() {
+        let dc = SimDc::parse(".dc V1 0 5 0.1 V2 0 1 500m").unwrap();
+        assert_eq!(dc.sweep.src, "V1");
+        let nested = dc.nested.unwrap();
+        assert_eq!(nested.src, "V2");
+        assert_eq!(nested.values(), [0.0, 0.5, 1.0]);
+        assert!(SimDc::parse(".dc V1 0 5 0.1").unwrap().nested.is_none());
+        assert!(SimDc::parse(".dc V1 0 5").is_none());
+        assert!(SimDc::parse(".tran 1u 1m").is_none());
+    }
+}
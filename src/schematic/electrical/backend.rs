@@ -0,0 +1,117 @@
+//! simulator backend abstraction
+//! [`super::SPRes`] talks to the simulator only through [`SimulatorBackend`], so that ngspice
+//! may be swapped out, e.g. for a mock in headless tests.
+
+use bevy_egui::egui::Color32;
+use paprika::PkVecvaluesall;
+
+use super::results::PlotInfo;
+
+pub trait SimulatorBackend: Send + Sync {
+    /// send a command, e.g. `source out/netlist.cir` or `bg_run`
+    fn command(&self, cmd: &str);
+    /// takes all output lines produced since last drained
+    fn drain_output(&self) -> Vec<(String, Color32)>;
+    /// takes all values produced since last drained, one entry per simulation step
+    fn vecvals_drain(&self) -> Vec<PkVecvaluesall>;
+    /// latest analysis name and percentage completed
+    fn progress(&self) -> Option<(String, f32)>;
+    /// true while a background simulation is running
+    fn is_running(&self) -> bool;
+    /// true once after each time a background simulation finishes
+    fn take_finished(&self) -> bool;
+    /// true for the native solver, whose results need not be checked against itself
+    fn is_native(&self) -> bool {
+        false
+    }
+    /// description of the latest plot, None if the backend does not describe its plots
+    fn plot_info(&self) -> Option<PlotInfo> {
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    };
+
+    use bevy_egui::egui::Color32;
+    use paprika::{PkVecvalues, PkVecvaluesall};
+
+    use super::SimulatorBackend;
+
+    /// deterministic backend: records commands and replies to `bg_run` with canned results
+    #[derive(Default)]
+    pub struct MockBackend {
+        commands: Mutex<Vec<String>>,
+        /// steps returned by each `bg_run`
+        canned: Vec<PkVecvaluesall>,
+        vecvals: Mutex<Vec<PkVecvaluesall>>,
+        output: Mutex<Vec<(String, Color32)>>,
+        finished: AtomicBool,
+    }
+
+    impl MockBackend {
+        /// backend which responds to `bg_run` with `steps`, each a list of (name, value)
+        pub fn with_results(steps: &[&[(&str, f64)]]) -> Self {
+            let canned = steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| PkVecvaluesall {
+                    count: step.len() as i32,
+                    index: i as i32,
+                    vecsa: step
+                        .iter()
+                        .map(|(name, val)| PkVecvalues {
+                            name: name.to_string(),
+                            creal: *val,
+                            cimag: 0.0,
+                            is_scale: false,
+                            is_complex: false,
+                        })
+                        .collect(),
+                })
+                .collect();
+            Self {
+                canned,
+                ..Default::default()
+            }
+        }
+        /// all commands received so far
+        pub fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl SimulatorBackend for MockBackend {
+        fn command(&self, cmd: &str) {
+            self.commands.lock().unwrap().push(cmd.to_owned());
+            self.output
+                .lock()
+                .unwrap()
+                .push((cmd.to_owned(), Color32::GREEN));
+            if cmd == "bg_run" {
+                // completes instantly
+                *self.vecvals.lock().unwrap() = self.canned.clone();
+                self.finished.store(true, Ordering::SeqCst);
+            }
+        }
+        fn drain_output(&self) -> Vec<(String, Color32)> {
+            self.output.lock().unwrap().drain(..).collect()
+        }
+        fn vecvals_drain(&self) -> Vec<PkVecvaluesall> {
+            self.vecvals.lock().unwrap().drain(..).collect()
+        }
+        fn progress(&self) -> Option<(String, f32)> {
+            None
+        }
+        fn is_running(&self) -> bool {
+            false
+        }
+        fn take_finished(&self) -> bool {
+            self.finished.swap(false, Ordering::SeqCst)
+        }
+    }
+}
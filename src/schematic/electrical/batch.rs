@@ -0,0 +1,131 @@
+//! batch simulation, without a window
+//! each analysis card among the directives of a schematic is run on its own, to completion,
+//! and its results written as csv. output of the simulator goes to stdout, and errors are
+//! collected so that the caller can fail.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    analysis::split_analyses,
+    backend::SimulatorBackend,
+    diagnostics::{classify, Severity},
+    export::{export, ExportFormat},
+    results::SimPlot,
+};
+
+/// interval at which a running simulation is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// time for the simulator to report a started run, after which it is taken to have failed
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// outcome of a batch run
+#[derive(Default)]
+pub struct BatchOutcome {
+    /// csv files written
+    pub written: Vec<PathBuf>,
+    /// simulator errors and analyses which produced no results
+    pub errors: Vec<String>,
+}
+
+/// run every analysis of `netlist`, writing `<stem>_<plot>.cir` and `<stem>_<plot>.csv` into `out`
+/// plots are named the way ngspice names them, e.g. `tran1` for the first transient analysis
+pub fn run_batch(
+    backend: &dyn SimulatorBackend,
+    netlist: &str,
+    out: &Path,
+    stem: &str,
+) -> BatchOutcome {
+    let mut outcome = BatchOutcome::default();
+    let analyses = split_analyses(netlist);
+    if analyses.is_empty() {
+        outcome
+            .errors
+            .push("no analysis directive (.op, .dc, .ac or .tran) in schematic".to_owned());
+        return outcome;
+    }
+    if let Err(e) = fs::create_dir_all(out) {
+        outcome.errors.push(format!("{}: {}", out.display(), e));
+        return outcome;
+    }
+    for (k, (analysis, netlist)) in analyses.iter().enumerate() {
+        let n = analyses[..=k].iter().filter(|(a, _)| a == analysis).count();
+        let plot_name = format!("{}{}", analysis, n);
+        let cir = out.join(format!("{}_{}.cir", stem, plot_name));
+        if let Err(e) = fs::write(&cir, format!("{}.end\n", netlist)) {
+            outcome.errors.push(format!("{}: {}", cir.display(), e));
+            continue;
+        }
+        println!("{}: running {}", plot_name, cir.display());
+        backend.command(&format!("source {}", cir.display()));
+        backend.command("bg_run");
+        let start = Instant::now();
+        loop {
+            print_output(backend, &mut outcome);
+            if backend.take_finished() {
+                break;
+            }
+            if !backend.is_running() && start.elapsed() > START_TIMEOUT {
+                outcome
+                    .errors
+                    .push(format!("{}: simulator did not run", plot_name));
+                break;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        print_output(backend, &mut outcome);
+        let plot =
+            SimPlot::from_vecvals(analysis, backend.vecvals_drain()).with_info(backend.plot_info());
+        if plot.columns().is_empty() {
+            outcome.errors.push(format!("{}: no results", plot_name));
+            continue;
+        }
+        let csv = out.join(format!("{}_{}.csv", stem, plot_name));
+        match export(&[plot], ExportFormat::Csv, &csv) {
+            Ok(()) => outcome.written.push(csv),
+            Err(e) => outcome.errors.push(format!("{}: {}", csv.display(), e)),
+        }
+    }
+    outcome
+}
+
+/// print simulator output, keeping errors
+fn print_output(backend: &dyn SimulatorBackend, outcome: &mut BatchOutcome) {
+    for (line, _) in backend.drain_output() {
+        println!("{}", line);
+        if classify(&line) == Some(Severity::Error) {
+            outcome.errors.push(line.trim().to_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::backend::mock::MockBackend, *};
+
+    #[test]
+    fn test_run_batch() {
+        let backend = MockBackend::with_results(&[&[("net_1", 1.0)]]);
+        let out = std::env::temp_dir().join("scirke_test_run_batch");
+        let outcome = run_batch(&backend, "title\n.op\n.op\n", &out, "rc");
+        assert!(outcome.errors.is_empty());
+        assert_eq!(
+            outcome.written,
+            [out.join("rc_op1.csv"), out.join("rc_op2.csv")]
+        );
+        assert_eq!(
+            fs::read_to_string(&outcome.written[1]).unwrap(),
+            "net_1\n1e0\n"
+        );
+        assert!(backend
+            .commands()
+            .contains(&format!("source {}", out.join("rc_op2.cir").display())));
+
+        let outcome = run_batch(&backend, "title\n", &out, "rc");
+        assert_eq!(outcome.errors.len(), 1);
+    }
+}
@@ -0,0 +1,357 @@
+//! device definitions, read from text files
+//! a definition holds what is needed to place a device: its spice type, default params, ports,
+//! pick box and symbol. one statement per line, `#` starts a comment:
+//!
+//! ```text
+//! # resistor
+//! type R
+//! key r
+//! params 1k
+//! port 0 3
+//! port 0 -3
+//! pick -2 -3 2 3
+//! move 0 3
+//! line 0 -3
+//! ```
+//!
+//! `type` is the spice prefix, or `Gnd`. `key` is the character placing the device in the device
+//! spawn tool. `port x y [name]` is listed in spice port order, and `pick` is the box, as two
+//! corners, in which the device is picked. the symbol is drawn with `move x y`, `line x y`,
+//! `arc cx cy r sweep` (in degrees, from the current point around the center), `circle cx cy r`,
+//! `quad cx cy x y` (quadratic bezier through control point c) and `close`.
+
+use std::{iter, sync::Arc};
+
+use bevy::{prelude::*, sprite::Mesh2dHandle};
+use euclid::{
+    default::{Box2D, Point2D},
+    Angle, Vector2D,
+};
+use lyon_tessellation::VertexBuffers;
+
+use crate::bevyon::{self, build_mesh, stroke, StrokeTessellator};
+
+use super::{
+    super::{spid::SpDeviceType, PickableDevice},
+    DeviceParams, DeviceType, DEVICE_COLOR, STROKE_OPTIONS,
+};
+
+/// one statement of a symbol path
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathCmd {
+    Move(Vec2),
+    Line(Vec2),
+    Arc {
+        center: Vec2,
+        radius: f32,
+        sweep: f32,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Quad {
+        ctrl: Vec2,
+        to: Vec2,
+    },
+    Close,
+}
+
+/// a device as read from its definition file
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceDef {
+    /// file name without extension, e.g. `r`
+    pub name: String,
+    pub dtype: SpDeviceType,
+    pub key: Option<String>,
+    pub params: String,
+    /// offset and pin name of each port, in spice port order
+    pub ports: Vec<(IVec2, String)>,
+    /// corners of the pick box
+    pub pick: [Vec2; 2],
+    pub path: Vec<PathCmd>,
+}
+
+/// numbers following a keyword, exactly `N` of them
+fn numbers<const N: usize>(args: &[&str]) -> Result<[f32; N], String> {
+    if args.len() != N {
+        return Err(format!("expected {} numbers, found {}", N, args.len()));
+    }
+    let mut out = [0.0; N];
+    for (o, a) in out.iter_mut().zip(args) {
+        *o = a.parse().map_err(|_| format!("not a number: {}", a))?;
+    }
+    Ok(out)
+}
+
+impl DeviceDef {
+    /// read a definition, errors are given with their line number
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let mut dtype = None;
+        let mut key = None;
+        let mut params = String::new();
+        let mut ports = vec![];
+        let mut pick = None;
+        let mut path = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((keyword, rest)) = line
+                .split_once(char::is_whitespace)
+                .or((!line.is_empty()).then_some((line, "")))
+            else {
+                continue;
+            };
+            let args = rest.split_whitespace().collect::<Vec<_>>();
+            let statement = match keyword {
+                "type" => SpDeviceType::from_prefix(rest.trim())
+                    .map(|t| dtype = Some(t))
+                    .ok_or_else(|| format!("unknown device type: {}", rest.trim())),
+                "key" => match args[..] {
+                    [k] if k.chars().count() == 1 => {
+                        key = Some(k.to_owned());
+                        Ok(())
+                    }
+                    _ => Err("expected a single character".to_owned()),
+                },
+                "params" => {
+                    params = rest.trim().to_owned();
+                    Ok(())
+                }
+                "port" => {
+                    let (xy, pin) = match args.len() {
+                        3 => (&args[..2], args[2]),
+                        _ => (&args[..], ""),
+                    };
+                    numbers::<2>(xy).and_then(|[x, y]| match (x.fract(), y.fract()) {
+                        (0.0, 0.0) => {
+                            ports.push((IVec2::new(x as i32, y as i32), pin.to_owned()));
+                            Ok(())
+                        }
+                        _ => Err("ports must be on the grid".to_owned()),
+                    })
+                }
+                "pick" => numbers::<4>(&args).map(|[x0, y0, x1, y1]| {
+                    pick = Some([Vec2::new(x0, y0), Vec2::new(x1, y1)]);
+                }),
+                "move" => numbers(&args).map(|[x, y]| path.push(PathCmd::Move(Vec2::new(x, y)))),
+                "line" => numbers(&args).map(|[x, y]| path.push(PathCmd::Line(Vec2::new(x, y)))),
+                "arc" => numbers(&args).map(|[cx, cy, radius, sweep]| {
+                    path.push(PathCmd::Arc {
+                        center: Vec2::new(cx, cy),
+                        radius,
+                        sweep,
+                    })
+                }),
+                "circle" => numbers(&args).map(|[cx, cy, radius]| {
+                    path.push(PathCmd::Circle {
+                        center: Vec2::new(cx, cy),
+                        radius,
+                    })
+                }),
+                "quad" => numbers(&args).map(|[cx, cy, x, y]| {
+                    path.push(PathCmd::Quad {
+                        ctrl: Vec2::new(cx, cy),
+                        to: Vec2::new(x, y),
+                    })
+                }),
+                "close" => numbers::<0>(&args).map(|_| path.push(PathCmd::Close)),
+                _ => Err(format!("unknown statement: {}", keyword)),
+            };
+            statement.map_err(|e| format!("{}: line {}: {}", name, i + 1, e))?;
+        }
+        let dtype = dtype.ok_or_else(|| format!("{}: missing type", name))?;
+        if dtype == SpDeviceType::X {
+            return Err(format!(
+                "{}: subcircuit symbols are made from their definition",
+                name
+            ));
+        }
+        if dtype.port_count() != Some(ports.len()) {
+            return Err(format!(
+                "{}: {} devices have {} ports, found {}",
+                name,
+                dtype.prefix(),
+                dtype.port_count().unwrap_or_default(),
+                ports.len()
+            ));
+        }
+        let pick = pick.ok_or_else(|| format!("{}: missing pick box", name))?;
+        Ok(Self {
+            name: name.to_owned(),
+            dtype,
+            key,
+            params,
+            ports,
+            pick,
+            path,
+        })
+    }
+
+    /// the definition as text, read back by [`DeviceDef::parse`]
+    pub fn to_text(&self) -> String {
+        let dtype = match self.dtype {
+            SpDeviceType::Gnd => "Gnd",
+            _ => self.dtype.prefix(),
+        };
+        let [p0, p1] = self.pick;
+        iter::once(format!("type {}", dtype))
+            .chain(self.key.iter().map(|k| format!("key {}", k)))
+            .chain((!self.params.is_empty()).then(|| format!("params {}", self.params)))
+            .chain(self.ports.iter().map(|(offset, pin)| {
+                format!("port {} {} {}", offset.x, offset.y, pin)
+                    .trim_end()
+                    .to_owned()
+            }))
+            .chain(iter::once(format!(
+                "pick {} {} {} {}",
+                p0.x, p0.y, p1.x, p1.y
+            )))
+            .chain(self.path.iter().map(|cmd| match cmd {
+                PathCmd::Move(to) => format!("move {} {}", to.x, to.y),
+                PathCmd::Line(to) => format!("line {} {}", to.x, to.y),
+                PathCmd::Arc {
+                    center,
+                    radius,
+                    sweep,
+                } => format!("arc {} {} {} {}", center.x, center.y, radius, sweep),
+                PathCmd::Circle { center, radius } => {
+                    format!("circle {} {} {}", center.x, center.y, radius)
+                }
+                PathCmd::Quad { ctrl, to } => {
+                    format!("quad {} {} {} {}", ctrl.x, ctrl.y, to.x, to.y)
+                }
+                PathCmd::Close => "close".to_owned(),
+            }))
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    /// tessellate the symbol and make a placeable device type
+    pub fn device_type(
+        &self,
+        stroke_tess: &mut StrokeTessellator,
+        meshes: &mut Assets<Mesh>,
+    ) -> DeviceType {
+        let mesh_res = meshes.add(path_mesh(&self.path, DEVICE_COLOR, stroke_tess));
+        let p = |v: Vec2| Point2D::new(v.x, v.y);
+        let collider = Arc::new(PickableDevice(Box2D::from_points(self.pick.map(p))));
+
+        DeviceType {
+            name: self.name.clone(),
+            params: DeviceParams::parse(&self.dtype, &self.params),
+            spice_type: self.dtype.clone(),
+            visuals: Mesh2dHandle(mesh_res),
+            collider,
+            ports: self.ports.iter().map(|(offset, _)| *offset).collect(),
+        }
+    }
+}
+
+/// stroke a symbol path into a mesh of one color
+pub fn path_mesh(path: &[PathCmd], color: Color, stroke_tess: &mut StrokeTessellator) -> Mesh {
+    let p = |v: Vec2| Point2D::new(v.x, v.y);
+    let mut path_builder = bevyon::path_builder().with_svg();
+    for cmd in path.iter() {
+        match *cmd {
+            PathCmd::Move(to) => {
+                path_builder.move_to(p(to));
+            }
+            PathCmd::Line(to) => {
+                path_builder.line_to(p(to));
+            }
+            PathCmd::Arc {
+                center,
+                radius,
+                sweep,
+            } => path_builder.arc(
+                p(center),
+                Vector2D::splat(radius),
+                Angle::degrees(sweep),
+                Angle::zero(),
+            ),
+            PathCmd::Circle { center, radius } => {
+                path_builder.move_to(p(center - Vec2::Y * radius));
+                path_builder.arc(
+                    p(center),
+                    Vector2D::splat(radius),
+                    Angle::two_pi(),
+                    Angle::zero(),
+                );
+            }
+            PathCmd::Quad { ctrl, to } => {
+                path_builder.quadratic_bezier_to(p(ctrl), p(to));
+            }
+            PathCmd::Close => path_builder.close(),
+        }
+    }
+    let path = path_builder.build();
+    let mut buffers = VertexBuffers::new();
+    stroke(stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
+    build_mesh(&buffers).with_inserted_attribute(
+        Mesh::ATTRIBUTE_COLOR,
+        vec![color.to_linear().to_f32_array(); buffers.vertices.len()],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::BUILTIN, *};
+
+    #[test]
+    fn test_builtin() {
+        for (name, text) in BUILTIN {
+            let def = DeviceDef::parse(name, text).unwrap();
+            assert!(!def.path.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_to_text() {
+        for (name, text) in BUILTIN {
+            let def = DeviceDef::parse(name, text).unwrap();
+            assert_eq!(DeviceDef::parse(name, &def.to_text()), Ok(def));
+        }
+        let def = DeviceDef::parse("gnd", "type GND\nport 0 1 a\npick -1 -1 1 1\nclose").unwrap();
+        assert_eq!(
+            def.to_text(),
+            "type Gnd\nport 0 1 a\npick -1 -1 1 1\nclose\n"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let def = DeviceDef::parse(
+            "sw",
+            "type W # current controlled\nkey w\nparams V1 CSW\nport 0 3 +\nport 0 -3\n\
+             pick -2 -3 2 3\nmove 0 3\narc 0 0 1 -90\nclose\n",
+        )
+        .unwrap();
+        assert_eq!(def.dtype, SpDeviceType::W);
+        assert_eq!(def.params, "V1 CSW");
+        assert_eq!(def.ports[0], (IVec2::new(0, 3), "+".to_owned()));
+        assert_eq!(def.ports[1].1, "");
+        assert_eq!(
+            def.path[1],
+            PathCmd::Arc {
+                center: Vec2::ZERO,
+                radius: 1.0,
+                sweep: -90.0
+            }
+        );
+
+        let err = |text| DeviceDef::parse("x", text).unwrap_err();
+        assert_eq!(
+            err("type R\nline 1"),
+            "x: line 2: expected 2 numbers, found 1"
+        );
+        assert_eq!(
+            err("type R\nport 0.5 1"),
+            "x: line 2: ports must be on the grid"
+        );
+        assert_eq!(err("type Z"), "x: line 1: unknown device type: Z");
+        assert_eq!(
+            err("type R\nport 0 1\npick 0 0 1 1"),
+            "x: R devices have 2 ports, found 1"
+        );
+    }
+}
@@ -6,23 +6,82 @@ use bevy::{
     sprite::MaterialMesh2dBundle,
 };
 
-use crate::schematic::{electrical::label::DEFAULT_FONT_SIZE, material::SchematicMaterial};
+use crate::schematic::{
+    electrical::{assign_model, label::DEFAULT_FONT_SIZE, stepping, units::parse_spice_number},
+    material::SchematicMaterial,
+};
 
-use super::{spid, DeviceType, ElementsRes, PickableElement, SchematicElement};
+use super::{
+    params::{self, ParamField},
+    spid::{self, SpDeviceType},
+    DeviceType, ElementsRes, PickableElement, SchematicElement,
+};
 
 /// component storing device parameters
-/// TODO: want to use trait object for this but how to serialize?
 #[derive(Component, Reflect, Clone)]
 #[reflect(Component)]
 pub enum DeviceParams {
     Raw(String), // passed directly to ngspice
-    Float(f32),
+    /// one field per parameter of the device type, see [`params::param_schema`]
+    Fields(Vec<ParamField>),
 }
 impl DeviceParams {
-    pub fn spice_param(&self) -> String {
+    /// params of a device line, as fields if the text fits the schema of `dtype`
+    pub fn parse(dtype: &SpDeviceType, text: &str) -> Self {
+        match params::parse_fields(dtype, text) {
+            Some(fields) => DeviceParams::Fields(fields),
+            None => DeviceParams::Raw(text.to_owned()),
+        }
+    }
+    /// device line text of the params of a device of type `dtype`
+    pub fn spice_param(&self, dtype: &SpDeviceType) -> String {
         match &self {
             DeviceParams::Raw(r) => r.clone(),
-            DeviceParams::Float(f) => f.to_string(),
+            DeviceParams::Fields(fields) => params::fields_spice_text(dtype, fields),
+        }
+    }
+    /// value of the field `name`, None for raw params or if not given
+    pub fn field(&self, name: &str) -> Option<&str> {
+        match self {
+            DeviceParams::Raw(_) => None,
+            DeviceParams::Fields(fields) => fields
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.value.trim())
+                .filter(|v| !v.is_empty()),
+        }
+    }
+    /// the value varied by steps and monte carlo runs, see [`params::stepped_field`]
+    /// raw params are varied by their leading number
+    pub fn value(&self, dtype: &SpDeviceType) -> Option<f64> {
+        match self {
+            DeviceParams::Raw(r) => stepping::leading_value(r),
+            DeviceParams::Fields(_) => {
+                parse_spice_number(self.field(params::stepped_field(dtype)?)?)
+            }
+        }
+    }
+    /// params with [`Self::value`] replaced by `value`, None if there is none to replace
+    pub fn with_value(&self, dtype: &SpDeviceType, value: f64) -> Option<Self> {
+        match self {
+            DeviceParams::Raw(r) => stepping::with_value(r, value).map(DeviceParams::Raw),
+            DeviceParams::Fields(fields) => {
+                let name = params::stepped_field(dtype)?;
+                let mut fields = fields.clone();
+                fields.iter_mut().find(|f| f.name == name)?.value = format!("{:e}", value);
+                Some(DeviceParams::Fields(fields))
+            }
+        }
+    }
+    /// use `model`, keeping the other params
+    pub fn assign_model(&mut self, model: &str) {
+        match self {
+            DeviceParams::Raw(r) => *r = assign_model(r, model),
+            DeviceParams::Fields(fields) => {
+                if let Some(field) = fields.iter_mut().find(|f| f.name == "model") {
+                    field.value = model.to_owned();
+                }
+            }
         }
     }
 }
@@ -33,6 +92,9 @@ pub struct DevicePorts {
     ports: Vec<Entity>,
 }
 impl DevicePorts {
+    pub fn new(ports: Vec<Entity>) -> Self {
+        Self { ports }
+    }
     pub fn get_ports(&self) -> &Vec<Entity> {
         &self.ports
     }
@@ -58,9 +120,20 @@ impl MapEntities for DeviceLabel {
     }
 }
 
+/// name of the definition the device was placed from, see [`super::DeviceRegistry`]
+#[derive(Component, Reflect, Clone, Default)]
+#[reflect(Component)]
+pub struct DeviceSymbol(String);
+impl DeviceSymbol {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
 #[derive(Bundle)]
 pub struct DeviceBundle {
     label: DeviceLabel,
+    symbol: DeviceSymbol,
     params: DeviceParams,
     ports: DevicePorts,
     mat: MaterialMesh2dBundle<SchematicMaterial>,
@@ -77,8 +150,9 @@ impl DeviceBundle {
     ) -> Self {
         Self {
             label: DeviceLabel { label },
+            symbol: DeviceSymbol(dtype.name),
             params: dtype.params,
-            ports: DevicePorts { ports },
+            ports: DevicePorts::new(ports),
             mat: MaterialMesh2dBundle {
                 mesh: dtype.visuals,
                 material: eres.mat_dflt.clone(),
@@ -94,13 +168,19 @@ impl DeviceBundle {
     }
 }
 
-pub fn update_device_param_labels(q: Query<(&DeviceParams, &DeviceLabel)>, mut commands: Commands) {
-    for (p, l) in q.iter() {
+pub fn update_device_param_labels(
+    q: Query<(&DeviceParams, &SchematicElement, &DeviceLabel)>,
+    mut commands: Commands,
+) {
+    for (p, se, l) in q.iter() {
+        let Some(dtype) = se.get_dtype() else {
+            continue;
+        };
         commands
             .get_entity(l.label)
             .unwrap()
             .insert(Text::from_section(
-                p.spice_param(),
+                p.spice_param(dtype),
                 TextStyle {
                     font_size: DEFAULT_FONT_SIZE,
                     color: Color::WHITE,
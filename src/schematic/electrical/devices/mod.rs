@@ -1,87 +1,220 @@
 //! Device: defines circuit devices such as resistor, mos, etc.
-//!
+//! devices are placed from definitions, see [`definition`], kept in the [`DeviceRegistry`]
+mod definition;
 mod device;
+mod params;
 
-pub use device::{DeviceParams, DevicePorts};
+pub use definition::{path_mesh, DeviceDef, PathCmd};
+pub use device::{DeviceParams, DevicePorts, DeviceSymbol};
+pub use params::{param_schema, ParamKind, ParamSpec};
+
+use params::ParamField;
 
 use device::{update_device_param_labels, DeviceBundle, DeviceLabel};
 
 use super::{
-    label::SchematicLabelBundle, nets::PortBundle, readable_idgen::IdTracker, spid, ElectricalSet,
-    ElementsRes, Pickable, PickableDevice, PickableElement, Preview, SchematicElement, Selected,
-    SpDeviceId,
+    label::SchematicLabelBundle, libngspice::config_file, nets::PortBundle,
+    readable_idgen::IdTracker, spid, ElectricalSet, ElementsRes, Pickable, PickableDevice,
+    PickableElement, Preview, SchematicElement, Selected, SpDeviceId,
 };
 use crate::{
     bevyon::{self, build_mesh, stroke, StrokeTessellator},
-    schematic::{EntityLoadSet, FreshLoad},
+    schematic::{ui::console::PrintConsoleLine, EntityLoadSet, FreshLoad},
 };
 
-use bevy::{color, prelude::*, sprite::Mesh2dHandle};
-use euclid::{default::Point2D, Angle, Vector2D};
+use bevy::{asset::io::file::FileAssetReader, color, prelude::*, sprite::Mesh2dHandle};
+use bevy_egui::egui::Color32;
+use euclid::default::{Box2D, Point2D};
 use lyon_tessellation::{StrokeOptions, VertexBuffers};
-use std::{iter, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs, iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// definitions built into the editor, so that every device type may be placed
+/// files in [`device_dirs`] come after these, and replace those of the same name
+const BUILTIN: [(&str, &str); 20] = [
+    ("gnd", include_str!("../../../../assets/devices/gnd.device")),
+    ("v", include_str!("../../../../assets/devices/v.device")),
+    ("i", include_str!("../../../../assets/devices/i.device")),
+    ("r", include_str!("../../../../assets/devices/r.device")),
+    ("l", include_str!("../../../../assets/devices/l.device")),
+    ("c", include_str!("../../../../assets/devices/c.device")),
+    ("e", include_str!("../../../../assets/devices/e.device")),
+    ("f", include_str!("../../../../assets/devices/f.device")),
+    ("g", include_str!("../../../../assets/devices/g.device")),
+    ("h", include_str!("../../../../assets/devices/h.device")),
+    ("b", include_str!("../../../../assets/devices/b.device")),
+    ("k", include_str!("../../../../assets/devices/k.device")),
+    ("s", include_str!("../../../../assets/devices/s.device")),
+    ("w", include_str!("../../../../assets/devices/w.device")),
+    ("t", include_str!("../../../../assets/devices/t.device")),
+    ("d", include_str!("../../../../assets/devices/d.device")),
+    ("q", include_str!("../../../../assets/devices/q.device")),
+    ("pnp", include_str!("../../../../assets/devices/pnp.device")),
+    ("m", include_str!("../../../../assets/devices/m.device")),
+    (
+        "pmos",
+        include_str!("../../../../assets/devices/pmos.device"),
+    ),
+];
+
+/// extension of device definition files
+const EXTENSION: &str = "device";
+
+/// directories device definitions are read from, in order: `assets/devices`, then `devices` next
+/// to the config file
+pub fn device_dirs() -> Vec<PathBuf> {
+    iter::once(
+        FileAssetReader::get_base_path()
+            .join("assets")
+            .join("devices"),
+    )
+    .chain(config_file().and_then(|f| Some(f.parent()?.join("devices"))))
+    .collect()
+}
+
+/// file a definition named `name` is saved to, in the last of [`device_dirs`]
+pub fn definition_path(name: &str) -> PathBuf {
+    device_dirs()
+        .pop()
+        .unwrap_or_default()
+        .join(format!("{}.{}", name, EXTENSION))
+}
 
+/// text of the definition files in `dir` as (name, text), by name
+fn read_dir(dir: &Path) -> Vec<Result<(String, String), String>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths = entries
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == EXTENSION))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            fs::read_to_string(&path)
+                .map(|text| (name, text))
+                .map_err(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+/// device definitions which may be placed, by name
 #[derive(Resource)]
-pub struct DefaultDevices {
-    g: DeviceType, // ground
-    v: DeviceType,
-    i: DeviceType,
-    r: DeviceType,
-    l: DeviceType,
-    c: DeviceType,
-    d: DeviceType,
-    q: DeviceType,
-    m: DeviceType,
+pub struct DeviceRegistry {
+    /// in order of loading, a definition read again replaces the earlier one in place
+    devices: Vec<(DeviceDef, DeviceType)>,
+    /// definition files which could not be read
+    errors: Vec<String>,
 }
 
-impl DefaultDevices {
-    pub fn gnd(&self) -> DeviceType {
-        self.g.clone()
+impl DeviceRegistry {
+    pub fn get(&self, name: &str) -> Option<DeviceType> {
+        self.devices
+            .iter()
+            .find(|(def, _)| def.name == name)
+            .map(|(_, dtype)| dtype.clone())
     }
-    pub fn voltage_source(&self) -> DeviceType {
-        self.v.clone()
+    /// default device of given spice type, the first defined, subcircuits have none
+    pub fn from_spice_type(&self, dtype: &spid::SpDeviceType) -> Option<DeviceType> {
+        self.devices
+            .iter()
+            .find(|(def, _)| def.dtype == *dtype)
+            .map(|(_, dtype)| dtype.clone())
     }
-    pub fn current_source(&self) -> DeviceType {
-        self.i.clone()
+    /// device placed by `key` in the device spawn tool
+    pub fn by_key(&self, key: &str) -> Option<DeviceType> {
+        self.devices
+            .iter()
+            .find(|(def, _)| def.key.as_deref() == Some(key))
+            .map(|(_, dtype)| dtype.clone())
     }
-    pub fn resistor(&self) -> DeviceType {
-        self.r.clone()
+    pub fn definitions(&self) -> impl Iterator<Item = &DeviceDef> {
+        self.devices.iter().map(|(def, _)| def)
     }
-    pub fn inductor(&self) -> DeviceType {
-        self.l.clone()
+    pub fn errors(&self) -> &[String] {
+        &self.errors
     }
-    pub fn capacitor(&self) -> DeviceType {
-        self.c.clone()
+    /// add a definition, replacing the one of the same name
+    pub fn define(
+        &mut self,
+        def: DeviceDef,
+        stroke_tess: &mut StrokeTessellator,
+        meshes: &mut Assets<Mesh>,
+    ) {
+        let dtype = def.device_type(stroke_tess, meshes);
+        match self.devices.iter_mut().find(|(d, _)| d.name == def.name) {
+            Some(entry) => *entry = (def, dtype),
+            None => self.devices.push((def, dtype)),
+        }
     }
-    pub fn diode(&self) -> DeviceType {
-        self.d.clone()
+}
+
+impl FromWorld for DeviceRegistry {
+    fn from_world(world: &mut World) -> Self {
+        let builtin = BUILTIN
+            .iter()
+            .map(|(name, text)| Ok((name.to_string(), text.to_string())));
+        let files = device_dirs()
+            .iter()
+            .flat_map(|dir| read_dir(dir))
+            .collect::<Vec<_>>();
+        world.resource_scope(|world, mut stroke_tess: Mut<StrokeTessellator>| {
+            let mut meshes = world.resource_mut::<Assets<Mesh>>();
+            let mut registry = DeviceRegistry {
+                devices: vec![],
+                errors: vec![],
+            };
+            for file in builtin.chain(files) {
+                match file.and_then(|(name, text)| DeviceDef::parse(&name, &text)) {
+                    Ok(def) => registry.define(def, &mut stroke_tess, &mut meshes),
+                    Err(e) => registry.errors.push(e),
+                }
+            }
+            registry
+        })
     }
-    pub fn bjt(&self) -> DeviceType {
-        self.q.clone()
+}
+
+/// place the device of the definition `name`, e.g. from the device list
+#[derive(Event)]
+pub struct SpawnDevice {
+    name: String,
+}
+
+impl SpawnDevice {
+    pub fn new(name: String) -> Self {
+        Self { name }
     }
-    pub fn mos(&self) -> DeviceType {
-        self.m.clone()
+    pub fn get_name(&self) -> &str {
+        &self.name
     }
 }
 
-impl FromWorld for DefaultDevices {
-    fn from_world(world: &mut World) -> Self {
-        DefaultDevices {
-            g: DeviceType::type_gnd(world),
-            v: DeviceType::type_v(world),
-            i: DeviceType::type_i(world),
-            r: DeviceType::type_r(world),
-            l: DeviceType::type_l(world),
-            c: DeviceType::type_c(world),
-            d: DeviceType::type_d(world),
-            q: DeviceType::type_q(world),
-            m: DeviceType::type_m(world),
-        }
+/// tell the user about definition files which could not be read
+fn report_registry(registry: Res<DeviceRegistry>, mut e_console: EventWriter<PrintConsoleLine>) {
+    for e in registry.errors() {
+        e_console.send(PrintConsoleLine::new(
+            format!("device definition {}", e),
+            Color32::YELLOW,
+        ));
     }
 }
 
 #[derive(Clone)]
 pub struct DeviceType {
+    /// name of the definition, see [`DeviceSymbol`]
+    name: String,
     params: DeviceParams,
     spice_type: spid::SpDeviceType,
     visuals: Mesh2dHandle,
@@ -90,6 +223,14 @@ pub struct DeviceType {
 }
 
 impl DeviceType {
+    /// offset of each port, in spice port order
+    pub fn get_ports(&self) -> &[IVec2] {
+        &self.ports
+    }
+    pub fn with_params(mut self, params: DeviceParams) -> Self {
+        self.params = params;
+        self
+    }
     fn as_non_reflect_bundle(&self) -> impl Bundle {
         (
             self.visuals.clone(),
@@ -107,361 +248,57 @@ const STROKE_OPTIONS: StrokeOptions = StrokeOptions::DEFAULT
     .with_line_cap(lyon_tessellation::LineCap::Round);
 
 impl DeviceType {
-    fn type_gnd(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0., 2.));
-        path_builder.line_to(Point2D::new(0., -1.));
-        path_builder.move_to(Point2D::new(0., -2.));
-        path_builder.line_to(Point2D::new(1., -1.));
-        path_builder.line_to(Point2D::new(-1., -1.));
-        path_builder.line_to(Point2D::new(0., -2.));
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_2x4());
-
-        let ports = Arc::new([IVec2::new(0, 2)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("0 0".to_owned()),
-            spice_type: spid::SpDeviceType::Gnd,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_v(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        let r = 1.2;
-        path_builder.move_to(Point2D::new(0.0, -3.0));
-        path_builder.line_to(Point2D::new(0.0, -r));
-        path_builder.move_to(Point2D::new(0.0, 3.0));
-        path_builder.line_to(Point2D::new(0.0, r));
-
-        path_builder.move_to(Point2D::new(0.0, -r));
-        path_builder.arc(
-            Point2D::zero(),
-            Vector2D::splat(r),
-            Angle::two_pi(),
-            Angle::zero(),
-        );
-        // +/- signs
-        path_builder.move_to(Point2D::new(0.0, 1.0));
-        path_builder.line_to(Point2D::new(0.0, 0.2));
-        path_builder.move_to(Point2D::new(-0.4, 0.6));
-        path_builder.line_to(Point2D::new(0.4, 0.6));
-        path_builder.move_to(Point2D::new(-0.4, -0.6));
-        path_builder.line_to(Point2D::new(0.4, -0.6));
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-        DeviceType {
-            params: DeviceParams::Raw("AC 1 SIN(3.3 1 2k 0 0)".to_owned()),
-            spice_type: spid::SpDeviceType::V,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_i(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        let r = 1.2;
-        path_builder.move_to(Point2D::new(0.0, -3.0));
-        path_builder.line_to(Point2D::new(0.0, -r));
-        path_builder.move_to(Point2D::new(0.0, 3.0));
-        path_builder.line_to(Point2D::new(0.0, r));
-
-        path_builder.move_to(Point2D::new(0.0, -r));
-        path_builder.arc(
-            Point2D::zero(),
-            Vector2D::splat(r),
-            Angle::two_pi(),
-            Angle::zero(),
-        );
-        // -> arrow pointing in direction of current flow
-        path_builder.move_to(Point2D::new(0.0, -0.8));
-        path_builder.line_to(Point2D::new(0.0, 0.8));
-        path_builder.move_to(Point2D::new(0.3, -0.5));
-        path_builder.line_to(Point2D::new(0.0, -0.8));
-        path_builder.line_to(Point2D::new(-0.3, -0.5));
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-        DeviceType {
-            params: DeviceParams::Raw("1u".to_owned()),
-            spice_type: spid::SpDeviceType::I,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_r(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 2.00));
-        path_builder.line_to(Point2D::new(1.00, 1.75));
-        path_builder.line_to(Point2D::new(-1.00, 1.25));
-        path_builder.line_to(Point2D::new(1.00, 0.75));
-        path_builder.line_to(Point2D::new(-1.00, 0.25));
-        path_builder.line_to(Point2D::new(1.00, -0.25));
-        path_builder.line_to(Point2D::new(-1.00, -0.75));
-        path_builder.line_to(Point2D::new(1.00, -1.25));
-        path_builder.line_to(Point2D::new(-1.00, -1.75));
-        path_builder.line_to(Point2D::new(0.00, -2.00));
-        path_builder.line_to(Point2D::new(0.00, -3.00));
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("1k".to_owned()),
-            spice_type: spid::SpDeviceType::R,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_l(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 2.00));
-        path_builder.line_to(Point2D::new(0.25, 2.00));
-        path_builder.move_to(Point2D::new(0.25, 1.00));
-        path_builder.line_to(Point2D::new(0.00, 1.00));
-        path_builder.move_to(Point2D::new(0.25, 0.00));
-        path_builder.line_to(Point2D::new(0.00, 0.00));
-        path_builder.move_to(Point2D::new(0.25, -1.00));
-        path_builder.line_to(Point2D::new(0.00, -1.00));
-        path_builder.move_to(Point2D::new(0.25, -2.00));
-        path_builder.line_to(Point2D::new(0.00, -2.00));
-        path_builder.line_to(Point2D::new(0.00, -3.00));
-        path_builder.move_to(Point2D::new(0.25, 2.00));
-        path_builder.arc(
-            Point2D::new(0.25, 1.50),
-            Vector2D::splat(0.5),
-            -Angle::pi(),
-            Angle::zero(),
-        );
-        path_builder.move_to(Point2D::new(0.25, 1.00));
-        path_builder.arc(
-            Point2D::new(0.25, 0.50),
-            Vector2D::splat(0.5),
-            -Angle::pi(),
-            Angle::zero(),
-        );
-        path_builder.move_to(Point2D::new(0.25, 0.00));
-        path_builder.arc(
-            Point2D::new(0.25, -0.50),
-            Vector2D::splat(0.5),
-            -Angle::pi(),
-            Angle::zero(),
-        );
-        path_builder.move_to(Point2D::new(0.25, -1.00));
-        path_builder.arc(
-            Point2D::new(0.25, -1.50),
-            Vector2D::splat(0.5),
-            -Angle::pi(),
-            Angle::zero(),
-        );
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("1n".to_owned()),
-            spice_type: spid::SpDeviceType::L,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_c(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
+    /// subcircuit box with pins split between left and right side, in spice port order
+    /// down the left side first, then down the right side
+    fn type_x(
+        npins: usize,
+        stroke_tess: &mut StrokeTessellator,
+        meshes: &mut Assets<Mesh>,
+    ) -> Self {
+        let rows = ((npins + 1) / 2).max(1) as i32;
+        let pin_y = |i: i32| rows - 1 - 2 * i;
         let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 0.50));
-        path_builder.move_to(Point2D::new(-1.00, 0.50));
-        path_builder.line_to(Point2D::new(1.00, 0.50));
-        path_builder.move_to(Point2D::new(0.00, -0.25));
-        path_builder.line_to(Point2D::new(0.00, -3.00));
-        path_builder.move_to(Point2D::new(0.00, -0.25));
-        path_builder.arc(
-            Point2D::new(0.00, -2.00),
-            Vector2D::splat(1.75),
-            Angle { radians: 0.6 },
-            Angle::zero(),
-        );
-        path_builder.move_to(Point2D::new(0.00, -0.25));
-        path_builder.arc(
-            Point2D::new(0.00, -2.00),
-            Vector2D::splat(1.75),
-            Angle { radians: -0.6 },
-            Angle::zero(),
-        );
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("1p".to_owned()),
-            spice_type: spid::SpDeviceType::C,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
+        // box
+        let h = rows as f32;
+        path_builder.move_to(Point2D::new(-2.00, h));
+        path_builder.line_to(Point2D::new(2.00, h));
+        path_builder.line_to(Point2D::new(2.00, -h));
+        path_builder.line_to(Point2D::new(-2.00, -h));
+        path_builder.close();
+        // pins
+        let ports = (0..npins as i32)
+            .map(|i| match i < rows {
+                true => IVec2::new(-3, pin_y(i)),
+                false => IVec2::new(3, pin_y(i - rows)),
+            })
+            .collect::<Vec<_>>();
+        for p in ports.iter() {
+            let p = p.as_vec2();
+            path_builder.move_to(Point2D::new(p.x, p.y));
+            path_builder.line_to(Point2D::new(p.x.signum() * 2.0, p.y));
         }
-    }
-    fn type_d(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 1.00));
-
-        path_builder.move_to(Point2D::new(0.00, -0.50));
-        path_builder.line_to(Point2D::new(1.00, 1.00));
-        path_builder.line_to(Point2D::new(-1.00, 1.00));
-        path_builder.line_to(Point2D::new(0.00, -0.50));
-        path_builder.line_to(Point2D::new(0.00, -3.00));
 
-        path_builder.move_to(Point2D::new(-1.00, -0.50));
-        path_builder.line_to(Point2D::new(1.00, -0.50));
         let path = path_builder.build();
         let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
+        stroke(stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
         let mesh = build_mesh(&buffers).with_inserted_attribute(
             Mesh::ATTRIBUTE_COLOR,
             vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
         );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_hndl = meshes.add(mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("".to_owned()), // TODO
-            spice_type: spid::SpDeviceType::D,
-            visuals: Mesh2dHandle(mesh_hndl),
-            collider,
-            ports,
-        }
-    }
-    fn type_q(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 0.50));
-
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let gnd_mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_res = meshes.add(gnd_mesh);
+        let mesh_res = meshes.add(mesh);
 
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
+        let collider = Arc::new(PickableDevice(Box2D::from_points([
+            Point2D::new(-3.0, -h),
+            Point2D::new(3.0, h),
+        ])));
 
         DeviceType {
-            params: DeviceParams::Raw("".to_owned()), // TODO
-            spice_type: spid::SpDeviceType::Q,
+            name: String::new(),
+            params: DeviceParams::Raw("".to_owned()), // set to the subcircuit name
+            spice_type: spid::SpDeviceType::X,
             visuals: Mesh2dHandle(mesh_res),
             collider,
-            ports,
-        }
-    }
-    fn type_m(world: &mut World) -> Self {
-        let mut stroke_tess = world.resource_mut::<StrokeTessellator>();
-        let mut path_builder = bevyon::path_builder().with_svg();
-        path_builder.move_to(Point2D::new(0.00, 3.00));
-        path_builder.line_to(Point2D::new(0.00, 0.50));
-
-        let path = path_builder.build();
-        let mut buffers = VertexBuffers::new();
-        stroke(&mut *stroke_tess, &path, &STROKE_OPTIONS, &mut buffers);
-        let gnd_mesh = build_mesh(&buffers).with_inserted_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            vec![DEVICE_COLOR.to_linear().to_f32_array(); buffers.vertices.len()],
-        );
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let mesh_res = meshes.add(gnd_mesh);
-
-        let collider = Arc::new(PickableDevice::_4x6());
-
-        let ports = Arc::new([IVec2::new(0, 3), IVec2::new(0, -3)]);
-
-        DeviceType {
-            params: DeviceParams::Raw("".to_owned()), // TODO
-            spice_type: spid::SpDeviceType::M,
-            visuals: Mesh2dHandle(mesh_res),
-            collider,
-            ports,
+            ports: ports.into(),
         }
     }
 }
@@ -504,6 +341,25 @@ pub fn spawn_preview_device_from_type(
         .collect()
 }
 
+/// box symbols of subcircuits, by number of pins
+/// symbols are generated once and shared between instances
+#[derive(Resource, Default)]
+pub struct SubcktSymbols(HashMap<usize, DeviceType>);
+
+impl SubcktSymbols {
+    pub fn get(
+        &mut self,
+        npins: usize,
+        stroke_tess: &mut StrokeTessellator,
+        meshes: &mut Assets<Mesh>,
+    ) -> DeviceType {
+        self.0
+            .entry(npins)
+            .or_insert_with(|| DeviceType::type_x(npins, stroke_tess, meshes))
+            .clone()
+    }
+}
+
 /// inspert spid component for entities which have SpDeviceType but not spid
 fn insert_spid(
     q: Query<(Entity, &SchematicElement), (Without<SpDeviceId>, With<DevicePorts>)>,
@@ -518,9 +374,19 @@ fn insert_spid(
             spid::SpDeviceType::R => SpDeviceId::new(idtracker.new_r_id("")),
             spid::SpDeviceType::L => SpDeviceId::new(idtracker.new_l_id("")),
             spid::SpDeviceType::C => SpDeviceId::new(idtracker.new_c_id("")),
+            spid::SpDeviceType::E => SpDeviceId::new(idtracker.new_e_id("")),
+            spid::SpDeviceType::F => SpDeviceId::new(idtracker.new_f_id("")),
+            spid::SpDeviceType::G => SpDeviceId::new(idtracker.new_g_id("")),
+            spid::SpDeviceType::H => SpDeviceId::new(idtracker.new_h_id("")),
+            spid::SpDeviceType::B => SpDeviceId::new(idtracker.new_b_id("")),
+            spid::SpDeviceType::K => SpDeviceId::new(idtracker.new_k_id("")),
+            spid::SpDeviceType::S => SpDeviceId::new(idtracker.new_s_id("")),
+            spid::SpDeviceType::W => SpDeviceId::new(idtracker.new_w_id("")),
+            spid::SpDeviceType::T => SpDeviceId::new(idtracker.new_t_id("")),
             spid::SpDeviceType::D => SpDeviceId::new(idtracker.new_d_id("")),
             spid::SpDeviceType::Q => SpDeviceId::new(idtracker.new_q_id("")),
             spid::SpDeviceType::M => SpDeviceId::new(idtracker.new_m_id("")),
+            spid::SpDeviceType::X => SpDeviceId::new(idtracker.new_x_id("")),
         };
         commands.entity(e).insert(spid);
     });
@@ -529,52 +395,43 @@ fn insert_spid(
 /// this system iterates through
 /// inserts non-refelct components for device type elements
 /// useful for applying mesh handles and such after loading
+/// devices take the symbol of their definition, or of the default device of their type if it is
+/// gone or was made for another type
 fn insert_non_reflect(
-    qd: Query<(Entity, &DevicePorts, &SchematicElement), With<FreshLoad>>,
-    default_devices: Res<DefaultDevices>,
+    qd: Query<
+        (
+            Entity,
+            &DevicePorts,
+            &SchematicElement,
+            Option<&DeviceSymbol>,
+        ),
+        With<FreshLoad>,
+    >,
+    registry: Res<DeviceRegistry>,
+    mut subckt_symbols: ResMut<SubcktSymbols>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut meshes: ResMut<Assets<Mesh>>,
     eres: Res<ElementsRes>,
     mut commands: Commands,
 ) {
-    for (device_ent, device, spid) in qd.iter() {
-        let bundle = match spid.get_dtype().unwrap() {
-            spid::SpDeviceType::Gnd => (
-                default_devices.g.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::V => (
-                default_devices.v.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::I => (
-                default_devices.i.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::R => (
-                default_devices.r.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::L => (
-                default_devices.l.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::C => (
-                default_devices.c.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::D => (
-                default_devices.d.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::Q => (
-                default_devices.q.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
-            spid::SpDeviceType::M => (
-                default_devices.m.as_non_reflect_bundle(),
-                eres.mat_dflt.clone(),
-            ),
+    for (device_ent, device, spid, symbol) in qd.iter() {
+        let dtype = spid.get_dtype().unwrap();
+        let device_type = match dtype {
+            spid::SpDeviceType::X => {
+                Some(subckt_symbols.get(device.get_ports().len(), &mut stroke_tess, &mut meshes))
+            }
+            _ => symbol
+                .and_then(|s| registry.get(s.name()))
+                .filter(|t| t.spice_type == *dtype)
+                .or_else(|| registry.from_spice_type(dtype)),
+        };
+        let Some(device_type) = device_type else {
+            warn!("no definition for device of type {:?}", dtype);
+            continue;
         };
-        commands.entity(device_ent).insert(bundle);
+        commands
+            .entity(device_ent)
+            .insert((device_type.as_non_reflect_bundle(), eres.mat_dflt.clone()));
         commands.entity(device_ent).remove::<FreshLoad>();
 
         for port_ent in device.get_ports().iter() {
@@ -599,9 +456,16 @@ impl Plugin for DevicesPlugin {
             (update_device_param_labels, insert_spid).in_set(ElectricalSet::React),
         );
         app.add_systems(PreUpdate, insert_non_reflect.in_set(EntityLoadSet::React));
+        app.add_systems(Startup, report_registry);
+        app.init_resource::<DeviceRegistry>();
+        app.init_resource::<SubcktSymbols>();
+        app.add_event::<SpawnDevice>();
         app.register_type::<SpDeviceId>();
         app.register_type::<DevicePorts>();
         app.register_type::<DeviceParams>();
+        app.register_type::<ParamField>();
+        app.register_type::<Vec<ParamField>>();
         app.register_type::<DeviceLabel>();
+        app.register_type::<DeviceSymbol>();
     }
 }
@@ -0,0 +1,505 @@
+//! parameter schema of devices
+//! each device type lists the parameters it takes, how each is written in the device line and
+//! how its value is checked. values are kept as entered, e.g. `4.7k`, so that they read back the
+//! way they were typed.
+
+use bevy::prelude::*;
+
+use super::super::{spid::SpDeviceType, units::parse_spice_number};
+
+/// transient source functions, see ngspice manual chapter 4.1
+const WAVEFORMS: [&str; 6] = ["sin", "pulse", "exp", "pwl", "sffm", "am"];
+
+/// how a parameter is written in the device line, and what it holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    /// number on its own, e.g. the resistance of a resistor
+    Value,
+    /// number after a keyword, e.g. `DC 1`
+    Keyword(&'static str),
+    /// number right after the previous parameter, e.g. the phase in `AC 1 90`
+    Follows,
+    /// number as `name=value`, e.g. `m=2`
+    Assign,
+    /// model name on its own
+    Model,
+    /// transient source function, e.g. `SIN(0 1 1k)`
+    Waveform,
+    /// id of another device of the given type, written with its prefix, e.g. `V1` for `1`
+    Reference(&'static SpDeviceType),
+    /// behavioral expression as `name=expression`, taking the rest of the line
+    Expression,
+}
+
+/// one parameter of a device type
+#[derive(Debug)]
+pub struct ParamSpec {
+    /// key in saved schematics, and in the device line for [`ParamKind::Assign`]
+    pub name: &'static str,
+    /// shown in the params editor
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub kind: ParamKind,
+    pub required: bool,
+}
+
+const fn spec(
+    name: &'static str,
+    label: &'static str,
+    unit: &'static str,
+    kind: ParamKind,
+    required: bool,
+) -> ParamSpec {
+    ParamSpec {
+        name,
+        label,
+        unit,
+        kind,
+        required,
+    }
+}
+
+const M: ParamSpec = spec("m", "multiplicity", "", ParamKind::Assign, false);
+const TEMP: ParamSpec = spec("temp", "temperature", "°C", ParamKind::Assign, false);
+const MODEL: ParamSpec = spec("model", "model", "", ParamKind::Model, true);
+const AREA: ParamSpec = spec("area", "area", "", ParamKind::Assign, false);
+const AC_PHASE: ParamSpec = spec("acphase", "ac phase", "°", ParamKind::Follows, false);
+const WAVEFORM: ParamSpec = spec("waveform", "waveform", "", ParamKind::Waveform, false);
+
+const SCHEMA_V: [ParamSpec; 4] = [
+    spec("dc", "dc", "V", ParamKind::Keyword("DC"), false),
+    spec(
+        "acmag",
+        "ac magnitude",
+        "V",
+        ParamKind::Keyword("AC"),
+        false,
+    ),
+    AC_PHASE,
+    WAVEFORM,
+];
+const SCHEMA_I: [ParamSpec; 4] = [
+    spec("dc", "dc", "A", ParamKind::Keyword("DC"), false),
+    spec(
+        "acmag",
+        "ac magnitude",
+        "A",
+        ParamKind::Keyword("AC"),
+        false,
+    ),
+    AC_PHASE,
+    WAVEFORM,
+];
+const SCHEMA_R: [ParamSpec; 3] = [
+    spec("value", "resistance", "Ω", ParamKind::Value, true),
+    M,
+    TEMP,
+];
+const SCHEMA_L: [ParamSpec; 3] = [
+    spec("value", "inductance", "H", ParamKind::Value, true),
+    spec("ic", "initial current", "A", ParamKind::Assign, false),
+    M,
+];
+const SCHEMA_C: [ParamSpec; 3] = [
+    spec("value", "capacitance", "F", ParamKind::Value, true),
+    spec("ic", "initial voltage", "V", ParamKind::Assign, false),
+    M,
+];
+const CONTROL: ParamSpec = spec(
+    "control",
+    "controlling source",
+    "",
+    ParamKind::Reference(&SpDeviceType::V),
+    true,
+);
+
+const SCHEMA_E: [ParamSpec; 1] = [spec("gain", "gain", "", ParamKind::Value, true)];
+const SCHEMA_F: [ParamSpec; 2] = [CONTROL, spec("gain", "gain", "", ParamKind::Value, true)];
+const SCHEMA_G: [ParamSpec; 1] = [spec(
+    "gain",
+    "transconductance",
+    "S",
+    ParamKind::Value,
+    true,
+)];
+const SCHEMA_H: [ParamSpec; 2] = [
+    CONTROL,
+    spec("gain", "transresistance", "Ω", ParamKind::Value, true),
+];
+const SCHEMA_B: [ParamSpec; 2] = [
+    spec("v", "voltage", "V", ParamKind::Expression, false),
+    spec("i", "current", "A", ParamKind::Expression, false),
+];
+const SCHEMA_K: [ParamSpec; 3] = [
+    spec(
+        "l1",
+        "inductor",
+        "",
+        ParamKind::Reference(&SpDeviceType::L),
+        true,
+    ),
+    spec(
+        "l2",
+        "inductor",
+        "",
+        ParamKind::Reference(&SpDeviceType::L),
+        true,
+    ),
+    spec("coupling", "coupling", "", ParamKind::Value, true),
+];
+const SCHEMA_S: [ParamSpec; 1] = [MODEL];
+const SCHEMA_W: [ParamSpec; 2] = [CONTROL, MODEL];
+const SCHEMA_T: [ParamSpec; 4] = [
+    spec("z0", "impedance", "Ω", ParamKind::Assign, true),
+    spec("td", "delay", "s", ParamKind::Assign, false),
+    spec("f", "frequency", "Hz", ParamKind::Assign, false),
+    spec("nl", "wavelengths at f", "", ParamKind::Assign, false),
+];
+const SCHEMA_D: [ParamSpec; 4] = [MODEL, AREA, M, TEMP];
+const SCHEMA_Q: [ParamSpec; 4] = [MODEL, AREA, M, TEMP];
+const SCHEMA_M: [ParamSpec; 5] = [
+    MODEL,
+    spec("w", "width", "m", ParamKind::Assign, false),
+    spec("l", "length", "m", ParamKind::Assign, false),
+    M,
+    TEMP,
+];
+
+/// parameters of a device type, empty for those edited as plain text
+pub fn param_schema(dtype: &SpDeviceType) -> &'static [ParamSpec] {
+    match dtype {
+        SpDeviceType::V => &SCHEMA_V,
+        SpDeviceType::I => &SCHEMA_I,
+        SpDeviceType::R => &SCHEMA_R,
+        SpDeviceType::L => &SCHEMA_L,
+        SpDeviceType::C => &SCHEMA_C,
+        SpDeviceType::E => &SCHEMA_E,
+        SpDeviceType::F => &SCHEMA_F,
+        SpDeviceType::G => &SCHEMA_G,
+        SpDeviceType::H => &SCHEMA_H,
+        SpDeviceType::B => &SCHEMA_B,
+        SpDeviceType::K => &SCHEMA_K,
+        SpDeviceType::S => &SCHEMA_S,
+        SpDeviceType::W => &SCHEMA_W,
+        SpDeviceType::T => &SCHEMA_T,
+        SpDeviceType::D => &SCHEMA_D,
+        SpDeviceType::Q => &SCHEMA_Q,
+        SpDeviceType::M => &SCHEMA_M,
+        SpDeviceType::Gnd | SpDeviceType::X => &[],
+    }
+}
+
+/// name of the field varied by steps and monte carlo runs, e.g. the resistance or dc value
+pub fn stepped_field(dtype: &SpDeviceType) -> Option<&'static str> {
+    param_schema(dtype)
+        .iter()
+        .find(|s| s.kind == ParamKind::Value || s.kind == ParamKind::Keyword("DC"))
+        .map(|s| s.name)
+}
+
+impl ParamSpec {
+    /// check an entered value, an empty value is fine unless required
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return match self.required {
+                true => Err(format!("{} is required", self.label)),
+                false => Ok(()),
+            };
+        }
+        match self.kind {
+            ParamKind::Model => {
+                match value.contains(|c: char| c.is_whitespace() || "=()".contains(c)) {
+                    true => Err(format!("not a model name: {}", value)),
+                    false => Ok(()),
+                }
+            }
+            ParamKind::Waveform => check_waveform(value),
+            ParamKind::Reference(_) => {
+                match value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    true => Ok(()),
+                    false => Err(format!("not a device id: {}", value)),
+                }
+            }
+            ParamKind::Expression => {
+                let mut depth = 0;
+                for c in value.chars() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' if depth == 0 => return Err(format!("unmatched ')': {}", value)),
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                match depth {
+                    0 => Ok(()),
+                    _ => Err(format!("missing ')': {}", value)),
+                }
+            }
+            _ => parse_spice_number(value)
+                .map(|_| ())
+                .ok_or_else(|| format!("not a number: {}", value)),
+        }
+    }
+}
+
+/// check a source function such as `SIN(0 1 1k)` or `PULSE(0 5 0 1n 1n 5u 10u)`
+fn check_waveform(value: &str) -> Result<(), String> {
+    let (name, args) = value
+        .split_once('(')
+        .ok_or_else(|| format!("expected e.g. SIN(0 1 1k): {}", value))?;
+    if !WAVEFORMS.contains(&name.trim().to_ascii_lowercase().as_str()) {
+        return Err(format!("unknown waveform: {}", name.trim()));
+    }
+    let args = args
+        .trim_end()
+        .strip_suffix(')')
+        .ok_or_else(|| format!("missing ')': {}", value))?;
+    match args
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|a| !a.is_empty())
+        .find(|a| parse_spice_number(a).is_none())
+    {
+        Some(arg) => Err(format!("not a number: {}", arg)),
+        None => Ok(()),
+    }
+}
+
+/// value of one parameter of a device
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct ParamField {
+    /// [`ParamSpec::name`]
+    pub name: String,
+    /// as entered, empty if not given
+    pub value: String,
+}
+
+/// device line text of parameter fields of `dtype`, skipping empty ones
+pub fn fields_spice_text(dtype: &SpDeviceType, fields: &[ParamField]) -> String {
+    let schema = param_schema(dtype);
+    let mut words = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let value = field.value.trim();
+        let Some(kind) = schema.iter().find(|s| s.name == field.name).map(|s| s.kind) else {
+            continue;
+        };
+        if value.is_empty() {
+            continue;
+        }
+        match kind {
+            ParamKind::Keyword(keyword) => words.push(format!("{} {}", keyword, value)),
+            ParamKind::Assign | ParamKind::Expression => {
+                words.push(format!("{}={}", field.name, value))
+            }
+            ParamKind::Reference(dtype) => words.push(format!("{}{}", dtype.prefix(), value)),
+            // meaningless without the parameter it follows
+            ParamKind::Follows if i == 0 || fields[i - 1].value.trim().is_empty() => {}
+            ParamKind::Value | ParamKind::Follows | ParamKind::Model | ParamKind::Waveform => {
+                words.push(value.to_owned())
+            }
+        }
+    }
+    words.join(" ")
+}
+
+/// words of a device line, with source functions such as `SIN(0 1 1k)` kept whole
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut depth = 0;
+    for word in text.split_whitespace() {
+        let continues = depth > 0
+            || (word.starts_with('(')
+                && tokens
+                    .last()
+                    .is_some_and(|t| t.chars().all(|c| c.is_ascii_alphabetic())));
+        match (continues, tokens.last_mut()) {
+            (true, Some(last)) => {
+                if depth > 0 {
+                    last.push(' ');
+                }
+                last.push_str(word);
+            }
+            _ => tokens.push(word.to_owned()),
+        }
+        depth += word.matches('(').count() as i32 - word.matches(')').count() as i32;
+    }
+    tokens
+}
+
+/// read the parameters of a device line into the fields of `dtype`
+/// None if the device type has no schema or the text does not fit it
+pub fn parse_fields(dtype: &SpDeviceType, text: &str) -> Option<Vec<ParamField>> {
+    let schema = param_schema(dtype);
+    if schema.is_empty() {
+        return None;
+    }
+    let mut values = vec![String::new(); schema.len()];
+    let tokens = tokenize(text);
+    let mut tokens = tokens.iter().peekable();
+    while let Some(token) = tokens.next() {
+        if let Some((name, value)) = token.split_once('=') {
+            let i = schema.iter().position(|s| {
+                matches!(s.kind, ParamKind::Assign | ParamKind::Expression)
+                    && s.name.eq_ignore_ascii_case(name.trim())
+            })?;
+            values[i] = value.to_owned();
+            // an expression runs up to the next assignment
+            if schema[i].kind == ParamKind::Expression {
+                while let Some(word) = tokens.next_if(|t| !t.contains('=')) {
+                    values[i].push(' ');
+                    values[i].push_str(word);
+                }
+                values[i] = values[i].trim().to_owned();
+            }
+            continue;
+        }
+        // other devices, by their id
+        let reference = schema.iter().enumerate().find_map(|(i, s)| match s.kind {
+            ParamKind::Reference(dtype) if values[i].is_empty() => {
+                let prefix = dtype.prefix();
+                token
+                    .get(..prefix.len())
+                    .filter(|p| p.eq_ignore_ascii_case(prefix))
+                    .map(|_| (i, &token[prefix.len()..]))
+            }
+            _ => None,
+        });
+        if let Some((i, id)) = reference {
+            values[i] = id.to_owned();
+            continue;
+        }
+        let keyword = schema
+            .iter()
+            .position(|s| matches!(s.kind, ParamKind::Keyword(k) if k.eq_ignore_ascii_case(token)));
+        if let Some(i) = keyword {
+            values[i] = tokens.next()?.clone();
+            let follows = schema
+                .get(i + 1)
+                .is_some_and(|s| s.kind == ParamKind::Follows);
+            if let Some(value) = tokens.next_if(|t| follows && parse_spice_number(t).is_some()) {
+                values[i + 1] = value.clone();
+            }
+            continue;
+        }
+        // positional, a bare number on a source is its dc value
+        let i = schema.iter().enumerate().position(|(i, s)| {
+            values[i].is_empty()
+                && matches!(
+                    s.kind,
+                    ParamKind::Value
+                        | ParamKind::Model
+                        | ParamKind::Waveform
+                        | ParamKind::Keyword("DC")
+                )
+                && s.check(token).is_ok()
+        })?;
+        values[i] = token.clone();
+    }
+    schema
+        .iter()
+        .zip(values.iter())
+        .all(|(s, v)| s.check(v).is_ok())
+        .then(|| {
+            schema
+                .iter()
+                .zip(values)
+                .map(|(s, value)| ParamField {
+                    name: s.name.to_owned(),
+                    value,
+                })
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(dtype: SpDeviceType, text: &str) -> String {
+        fields_spice_text(&dtype, &parse_fields(&dtype, text).unwrap())
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let fields = parse_fields(&SpDeviceType::V, "AC 1 SIN(3.3 1 2k 0 0)").unwrap();
+        assert_eq!(fields[1].value, "1");
+        assert_eq!(fields[3].value, "SIN(3.3 1 2k 0 0)");
+        assert_eq!(
+            roundtrip(SpDeviceType::V, "5 ac 1 90 PULSE (0 5 0 1n 1n 5u 10u)"),
+            "DC 5 AC 1 90 PULSE(0 5 0 1n 1n 5u 10u)"
+        );
+        assert_eq!(roundtrip(SpDeviceType::R, "4.7k m=2"), "4.7k m=2");
+        assert_eq!(
+            roundtrip(SpDeviceType::M, "MOSN w=1u l=1u"),
+            "MOSN w=1u l=1u"
+        );
+        assert_eq!(roundtrip(SpDeviceType::C, "1p IC=0.5"), "1p ic=0.5");
+        // not fitting the schema
+        assert!(parse_fields(&SpDeviceType::R, "1k tc1=0.01").is_none());
+        assert!(parse_fields(&SpDeviceType::R, "").is_none());
+        assert!(parse_fields(&SpDeviceType::V, "SIN(0 1").is_none());
+        assert!(parse_fields(&SpDeviceType::X, "sub").is_none());
+    }
+
+    #[test]
+    fn test_controlled_sources() {
+        let fields = parse_fields(&SpDeviceType::H, "Vsense 10k").unwrap();
+        assert_eq!(fields[0].value, "sense");
+        assert_eq!(fields_spice_text(&SpDeviceType::H, &fields), "Vsense 10k");
+        assert_eq!(roundtrip(SpDeviceType::G, "1m"), "1m");
+        // the control is required on current controlled sources
+        assert!(parse_fields(&SpDeviceType::F, "2").is_none());
+        assert_eq!(
+            roundtrip(SpDeviceType::B, "V=v(net_1) * (1 + i(V1))"),
+            "v=v(net_1) * (1 + i(V1))"
+        );
+        assert!(parse_fields(&SpDeviceType::B, "I=sin(time").is_none());
+    }
+
+    #[test]
+    fn test_coupling_switches_lines() {
+        let fields = parse_fields(&SpDeviceType::K, "Lp ls 0.99").unwrap();
+        assert_eq!(fields[0].value, "p");
+        assert_eq!(fields[1].value, "s");
+        assert_eq!(fields_spice_text(&SpDeviceType::K, &fields), "Lp Ls 0.99");
+        assert_eq!(roundtrip(SpDeviceType::W, "V1 CSW"), "V1 CSW");
+        assert_eq!(roundtrip(SpDeviceType::S, "SW"), "SW");
+        assert_eq!(roundtrip(SpDeviceType::T, "Z0=50 TD=1n"), "z0=50 td=1n");
+        assert!(parse_fields(&SpDeviceType::T, "TD=1n").is_none());
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(SCHEMA_R[0].check("10kohm").is_ok());
+        assert!(SCHEMA_R[0].check("").is_err());
+        assert!(SCHEMA_R[0].check("1k2").is_err());
+        assert!(M.check("").is_ok());
+        assert!(MODEL.check("2N3904").is_ok());
+        assert!(MODEL.check("a b").is_err());
+        assert!(WAVEFORM.check("pwl(0 0, 1m 1)").is_ok());
+        assert!(WAVEFORM.check("SQUARE(0 1)").is_err());
+        assert!(WAVEFORM.check("SIN(0 x)").is_err());
+    }
+
+    #[test]
+    fn test_phase_needs_magnitude() {
+        let mut fields = parse_fields(&SpDeviceType::I, "AC 1 90").unwrap();
+        fields[1].value.clear();
+        assert_eq!(fields_spice_text(&SpDeviceType::I, &fields), "");
+    }
+
+    #[test]
+    fn test_kind_of_device() {
+        // fields are written as the schema of the device says, others are left out
+        let field = |name: &str, value: &str| ParamField {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        let fields = [field("value", "1k"), field("acmag", "1")];
+        assert_eq!(fields_spice_text(&SpDeviceType::R, &fields), "1k");
+        assert_eq!(
+            fields_spice_text(&SpDeviceType::V, &[field("dc", "5"), field("acmag", "1")]),
+            "DC 5 AC 1"
+        );
+    }
+}
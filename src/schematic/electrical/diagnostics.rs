@@ -0,0 +1,213 @@
+//! simulator diagnostics
+//! errors and warnings printed by ngspice, e.g. `singular matrix: check node net_3` or
+//! `unknown model dmod for d1`, are linked to the devices and nets they mention. those are
+//! highlighted with their own material until the next run starts, and listed in the diagnostics
+//! panel.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+
+use crate::schematic::ui::console::PrintConsoleLine;
+
+use super::{
+    simrun::SimRun,
+    spid::{SchType, SpType},
+    NetId, SchematicElement, SpDeviceId,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn color(&self) -> Color32 {
+        match self {
+            Severity::Error => Color32::RED,
+            Severity::Warning => Color32::YELLOW,
+        }
+    }
+}
+
+/// an error or warning and the elements it mentions
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// (spice name or net, entity), several entities for a net with several segments
+    pub links: Vec<(String, Entity)>,
+}
+
+/// diagnostics of the latest run
+#[derive(Resource, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn entries(&self) -> &[Diagnostic] {
+        &self.entries
+    }
+}
+
+/// marks an element mentioned by a diagnostic
+#[derive(Component)]
+pub struct Flagged;
+
+/// parts of messages which are errors even without saying so
+const ERROR_PATTERNS: [&str; 8] = [
+    "error",
+    "singular matrix",
+    "timestep too small",
+    "unknown model",
+    "unable to find",
+    "could not find",
+    "no such",
+    "failed",
+];
+
+/// severity of a simulator output line, None if it is neither an error nor a warning
+pub fn classify(line: &str) -> Option<Severity> {
+    let line = line.to_lowercase();
+    if line.contains("warning") {
+        Some(Severity::Warning)
+    } else if ERROR_PATTERNS.iter().any(|p| line.contains(p)) {
+        Some(Severity::Error)
+    } else {
+        None
+    }
+}
+
+/// words of a message which may be device or net names, lowercase as ngspice reports them
+/// hierarchical names such as `x1.r2` give both parts, `v1#branch` gives `v1`
+fn mentioned_names(line: &str) -> Vec<String> {
+    let mut names = vec![];
+    for word in line.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#')) {
+        let word = word.split('#').next().unwrap_or_default().to_lowercase();
+        if !word.is_empty() && !names.contains(&word) {
+            names.push(word);
+        }
+    }
+    names
+}
+
+/// link errors and warnings in the simulator output to elements
+/// messages of the app itself are not classified, they may read like errors without being any
+fn collect_diagnostics(
+    mut e_console: EventReader<PrintConsoleLine>,
+    simrun: Res<SimRun>,
+    mut was_running: Local<bool>,
+    mut diagnostics: ResMut<Diagnostics>,
+    q_devices: Query<(Entity, &SchematicElement, &SpDeviceId)>,
+    q_nets: Query<(Entity, &SchematicElement, &NetId)>,
+    q_flagged: Query<Entity, With<Flagged>>,
+    mut commands: Commands,
+) {
+    // diagnostics are of the latest run only
+    if simrun.is_running() && !*was_running {
+        diagnostics.entries.clear();
+        for e in q_flagged.iter() {
+            commands.entity(e).remove::<Flagged>();
+        }
+    }
+    *was_running = simrun.is_running();
+    let mut lines = e_console
+        .read()
+        .filter(|l| l.from_simulator)
+        .filter_map(|l| Some((classify(&l.line)?, l.line.trim().to_owned())))
+        .peekable();
+    if lines.peek().is_none() {
+        return;
+    }
+    let mut elements = HashMap::<String, Vec<Entity>>::new();
+    for (e, se, spdid) in q_devices.iter() {
+        if let Some(name) = se.spice_name(spdid) {
+            elements.entry(name.to_lowercase()).or_default().push(e);
+        }
+    }
+    for (e, se, netid) in q_nets.iter() {
+        if matches!(se.get_schtype(), SchType::Spice(SpType::Net)) {
+            elements
+                .entry(netid.get_id().to_lowercase())
+                .or_default()
+                .push(e);
+        }
+    }
+    for (severity, message) in lines {
+        let links = mentioned_names(&message)
+            .into_iter()
+            .filter_map(|name| Some((elements.get(&name)?, name)))
+            .flat_map(|(entities, name)| entities.iter().map(move |e| (name.clone(), *e)))
+            .collect::<Vec<_>>();
+        for (_, e) in links.iter() {
+            commands.entity(*e).insert(Flagged);
+        }
+        diagnostics.entries.push(Diagnostic {
+            severity,
+            message,
+            links,
+        });
+    }
+}
+
+pub struct DiagnosticsPlugin;
+
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Diagnostics>();
+        app.add_systems(Update, collect_diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify("Warning: singular matrix:  check node net_3"),
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            classify("doAnalyses: TRAN:  Timestep too small; time = 1e-9"),
+            Some(Severity::Error)
+        );
+        assert_eq!(classify("Circuit: rc"), None);
+    }
+
+    #[test]
+    fn test_mentioned_names() {
+        assert_eq!(
+            mentioned_names("trouble with node \"net_2\", r3-instance R3"),
+            ["trouble", "with", "node", "net_2", "r3", "instance"]
+        );
+        assert_eq!(mentioned_names("x1.m2 v1#branch"), ["x1", "m2", "v1"]);
+    }
+
+    #[test]
+    fn test_only_simulator_output() {
+        let mut world = World::new();
+        world.init_resource::<SimRun>();
+        world.init_resource::<Diagnostics>();
+        world.init_resource::<Events<PrintConsoleLine>>();
+        world.send_event(PrintConsoleLine::new(
+            "export failed: out/rc.csv".to_owned(),
+            Color32::RED,
+        ));
+        world.send_event(PrintConsoleLine::simulator(
+            "Error: unknown model dmod".to_owned(),
+            Color32::RED,
+        ));
+        world.run_system_once(collect_diagnostics);
+
+        let entries = world.resource::<Diagnostics>().entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "Error: unknown model dmod");
+    }
+}
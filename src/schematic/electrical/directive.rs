@@ -0,0 +1,167 @@
+//! simulation directives placed on the schematic
+//! a directive is spice text such as `.param rload=1k`, `.temp 85` or `.include models.lib`.
+//! directives are saved with the design and emitted verbatim into the netlist.
+
+use std::sync::Arc;
+
+use bevy::{
+    ecs::{entity::MapEntities, reflect::ReflectMapEntities},
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    text::TextLayoutInfo,
+};
+use euclid::default::{Box2D, Point2D};
+
+use crate::schematic::{material::SchematicMaterial, EntityLoadSet, FreshLoad};
+
+use super::{
+    label::{SchematicLabelBundle, DEFAULT_FONT_SIZE},
+    spid::SchType,
+    ElementsRes, PickableDevice, PickableElement, Preview, SchematicElement, Selected,
+};
+
+/// offset of the directive text from the directive anchor
+const LABEL_OFFSET: IVec2 = IVec2::new(1, 0);
+
+/// component storing directive text, one spice card per line
+#[derive(Component, Reflect, Clone)]
+#[reflect(Component, MapEntities)]
+pub struct Directive {
+    text: String,
+    label: Entity,
+}
+
+impl Directive {
+    pub fn new(text: String, label: Entity) -> Self {
+        Self { text, label }
+    }
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+    }
+}
+
+impl MapEntities for Directive {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.label = entity_mapper.map_entity(self.label);
+    }
+}
+
+#[derive(Bundle)]
+struct DirectiveBundle {
+    directive: Directive,
+    mat: MaterialMesh2dBundle<SchematicMaterial>,
+    pe: PickableElement,
+    se: SchematicElement,
+}
+
+/// non-reflect components of a directive
+fn non_reflect_bundle(
+    eres: &ElementsRes,
+) -> (Mesh2dHandle, Handle<SchematicMaterial>, PickableElement) {
+    (
+        Mesh2dHandle(eres.mesh_port.clone()),
+        eres.mat_dflt.clone(),
+        PickableElement {
+            behavior: Arc::new(PickableDevice::_2x4()),
+        },
+    )
+}
+
+/// spawn a directive as preview, returns the directive and label entities
+pub fn spawn_preview_directive(
+    text: String,
+    commands: &mut Commands,
+    eres: &ElementsRes,
+) -> Box<[Entity]> {
+    let directive_entity = commands.spawn_empty().id();
+    let label_entity = commands
+        .spawn(SchematicLabelBundle::new(
+            directive_entity,
+            LABEL_OFFSET,
+            text.clone(),
+        ))
+        .id();
+    let (mesh, material, pe) = non_reflect_bundle(eres);
+    commands.entity(directive_entity).insert((
+        DirectiveBundle {
+            directive: Directive::new(text, label_entity),
+            mat: MaterialMesh2dBundle {
+                mesh,
+                material,
+                ..Default::default()
+            },
+            pe,
+            se: SchematicElement {
+                schtype: SchType::Directive,
+            },
+        },
+        Preview,
+        Selected,
+    ));
+    Box::new([directive_entity, label_entity])
+}
+
+/// show directive text in its label
+fn update_directive_labels(q: Query<&Directive, Changed<Directive>>, mut commands: Commands) {
+    for d in q.iter() {
+        let Some(mut label) = commands.get_entity(d.label) else {
+            continue;
+        };
+        label.insert(Text::from_section(
+            d.text.clone(),
+            TextStyle {
+                font_size: DEFAULT_FONT_SIZE,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+    }
+}
+
+/// fit the picking collider to the anchor and the text as currently displayed
+/// the label is zoom invariant, so its size in world units changes with zoom
+fn update_directive_colliders(
+    mut q: Query<(&Directive, &mut PickableElement)>,
+    q_label: Query<(&TextLayoutInfo, &GlobalTransform)>,
+) {
+    for (d, mut pe) in q.iter_mut() {
+        let Ok((layout, gt)) = q_label.get(d.label) else {
+            continue;
+        };
+        let size = layout.logical_size * gt.compute_transform().scale.truncate();
+        let x = LABEL_OFFSET.x as f32;
+        let y = LABEL_OFFSET.y as f32;
+        pe.behavior = Arc::new(PickableDevice(Box2D::from_points([
+            Point2D::new(-1.0, (y - size.y).min(-1.0)),
+            Point2D::new(x + size.x, y.max(1.0)),
+        ])));
+    }
+}
+
+/// inserts non-reflect components for directives after loading
+fn insert_non_reflect(
+    q: Query<Entity, (With<FreshLoad>, With<Directive>)>,
+    eres: Res<ElementsRes>,
+    mut commands: Commands,
+) {
+    for e in q.iter() {
+        commands.entity(e).insert(non_reflect_bundle(&eres));
+        commands.entity(e).remove::<FreshLoad>();
+    }
+}
+
+pub struct DirectivePlugin;
+
+impl Plugin for DirectivePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (update_directive_labels, update_directive_colliders),
+        );
+        app.add_systems(PreUpdate, insert_non_reflect.in_set(EntityLoadSet::React));
+        app.register_type::<Directive>();
+    }
+}
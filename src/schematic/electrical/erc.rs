@@ -0,0 +1,500 @@
+//! electrical rules check
+//! runs on the nets assigned by the net graph each time the schematic changes. findings are
+//! listed in the erc window and marked on the canvas next to the elements concerned.
+//! simulation can be blocked while there are errors.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use bevy::{prelude::*, text::Text2dBounds};
+
+use crate::schematic::{guides::ZoomInvariant, SchematicChanged};
+
+use super::{
+    devices::{param_schema, DeviceParams, DevicePorts, ParamKind},
+    diagnostics::Severity,
+    label::{SchematicLabel, DEFAULT_FONT_SIZE},
+    nets::{LineVertex, Port},
+    spid::SpDeviceType,
+    ElectricalSet, NetId, Preview, SchematicElement, SpDeviceId,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErcRule {
+    NoGround,
+    FloatingPin,
+    SourceLoop,
+    CurrentSourceCap,
+    DuplicateName,
+    DeadEndWire,
+    MissingReference,
+}
+
+impl ErcRule {
+    /// short name, shown in the canvas markers
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErcRule::NoGround => "no ground",
+            ErcRule::FloatingPin => "floating pin",
+            ErcRule::SourceLoop => "source loop",
+            ErcRule::CurrentSourceCap => "i source in series with c",
+            ErcRule::DuplicateName => "duplicate name",
+            ErcRule::DeadEndWire => "dead end",
+            ErcRule::MissingReference => "missing device",
+        }
+    }
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErcRule::FloatingPin | ErcRule::DeadEndWire => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ErcFinding {
+    pub rule: ErcRule,
+    pub message: String,
+    /// elements concerned, none for findings about the whole schematic
+    pub entities: Vec<Entity>,
+}
+
+/// findings of the latest check
+#[derive(Resource, Default)]
+pub struct Erc {
+    findings: Vec<ErcFinding>,
+    /// refuse to simulate while there are errors
+    pub block_on_errors: bool,
+}
+
+impl Erc {
+    pub fn findings(&self) -> &[ErcFinding] {
+        &self.findings
+    }
+    pub fn errors(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.rule.severity() == Severity::Error)
+            .count()
+    }
+    /// true if simulation should not start
+    pub fn blocks(&self) -> bool {
+        self.block_on_errors && self.errors() > 0
+    }
+}
+
+/// a device as seen by the check
+struct ErcDevice {
+    entity: Entity,
+    name: String,
+    dtype: SpDeviceType,
+    /// (port entity, net) in port order
+    ports: Vec<(Entity, String)>,
+    /// devices named in the params, e.g. the inductors of a coupling, as (type, name)
+    references: Vec<(SpDeviceType, String)>,
+}
+
+/// the schematic as seen by the check
+#[derive(Default)]
+struct ErcInput {
+    devices: Vec<ErcDevice>,
+    /// number of elements (ports, wire segments and vertices) on each net
+    net_members: HashMap<String, usize>,
+    /// wire vertices which are not ports, with their number of segments
+    vertices: Vec<(Entity, usize)>,
+}
+
+fn check(input: &ErcInput) -> Vec<ErcFinding> {
+    let mut findings = vec![];
+    let devices = &input.devices;
+    if !devices.is_empty() && !devices.iter().any(|d| d.dtype == SpDeviceType::Gnd) {
+        findings.push(ErcFinding {
+            rule: ErcRule::NoGround,
+            message: "no ground: every circuit needs a node 0".to_owned(),
+            entities: vec![],
+        });
+    }
+    // floating pins: a port alone on its net
+    for d in devices.iter() {
+        for (i, (port, net)) in d.ports.iter().enumerate() {
+            if input.net_members.get(net).copied().unwrap_or(0) <= 1 {
+                findings.push(ErcFinding {
+                    rule: ErcRule::FloatingPin,
+                    message: format!("pin {} of {} is not connected", i + 1, d.name),
+                    entities: vec![*port],
+                });
+            }
+        }
+    }
+    findings.extend(source_loops(devices));
+    // a net joining only a current source and a capacitor has no dc path
+    let mut net_devices = BTreeMap::<&str, Vec<usize>>::new();
+    for (i, d) in devices.iter().enumerate() {
+        for (_, net) in d.ports.iter() {
+            net_devices.entry(net).or_default().push(i);
+        }
+    }
+    let mut series = HashSet::new();
+    for (net, ds) in net_devices.iter() {
+        let [a, b] = ds[..] else {
+            continue;
+        };
+        let (a, b) = (&devices[a], &devices[b]);
+        let pair = match (&a.dtype, &b.dtype) {
+            (SpDeviceType::I, SpDeviceType::C) => (a, b),
+            (SpDeviceType::C, SpDeviceType::I) => (b, a),
+            _ => continue,
+        };
+        if series.insert((pair.0.entity, pair.1.entity)) {
+            findings.push(ErcFinding {
+                rule: ErcRule::CurrentSourceCap,
+                message: format!(
+                    "{} is in series with {} at {}: no dc path",
+                    pair.0.name, pair.1.name, net
+                ),
+                entities: vec![pair.0.entity, pair.1.entity],
+            });
+        }
+    }
+    // duplicate names, in order of first appearance
+    let mut names = Vec::<(String, Vec<&ErcDevice>)>::new();
+    for d in devices.iter().filter(|d| d.dtype != SpDeviceType::Gnd) {
+        let name = d.name.to_lowercase();
+        match names.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, ds)) => ds.push(d),
+            None => names.push((name, vec![d])),
+        }
+    }
+    for (_, ds) in names.into_iter().filter(|(_, ds)| ds.len() > 1) {
+        findings.push(ErcFinding {
+            rule: ErcRule::DuplicateName,
+            message: format!("{} devices are named {}", ds.len(), ds[0].name),
+            entities: ds.iter().map(|d| d.entity).collect(),
+        });
+    }
+    for d in devices.iter() {
+        for (dtype, name) in d.references.iter() {
+            if !devices
+                .iter()
+                .any(|r| r.dtype == *dtype && r.name.eq_ignore_ascii_case(name))
+            {
+                findings.push(ErcFinding {
+                    rule: ErcRule::MissingReference,
+                    message: format!(
+                        "{} refers to {}, which is not in the schematic",
+                        d.name, name
+                    ),
+                    entities: vec![d.entity],
+                });
+            }
+        }
+    }
+    for (vertex, segments) in input.vertices.iter() {
+        if *segments == 1 {
+            findings.push(ErcFinding {
+                rule: ErcRule::DeadEndWire,
+                message: "wire ends without connecting to anything".to_owned(),
+                entities: vec![*vertex],
+            });
+        }
+    }
+    findings
+}
+
+/// loops made only of voltage sources and inductors, which short each other at dc
+/// controlled voltage sources count as voltage sources by their output
+fn source_loops(devices: &[ErcDevice]) -> Vec<ErcFinding> {
+    let mut findings = vec![];
+    // edges added so far: net -> (other net, device index)
+    let mut adjacency = HashMap::<&str, Vec<(&str, usize)>>::new();
+    let loop_dtypes = [
+        SpDeviceType::V,
+        SpDeviceType::L,
+        SpDeviceType::E,
+        SpDeviceType::H,
+    ];
+    for (i, d) in devices.iter().enumerate() {
+        if !loop_dtypes.contains(&d.dtype) {
+            continue;
+        }
+        let [(_, a), (_, b), ..] = &d.ports[..] else {
+            continue;
+        };
+        if let Some(path) = path_between(&adjacency, a, b) {
+            let mut members = path.into_iter().map(|j| &devices[j]).collect::<Vec<_>>();
+            members.push(d);
+            findings.push(ErcFinding {
+                rule: ErcRule::SourceLoop,
+                message: format!(
+                    "loop of voltage sources and inductors: {}",
+                    members
+                        .iter()
+                        .map(|d| d.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                entities: members.iter().map(|d| d.entity).collect(),
+            });
+        } else {
+            adjacency.entry(a).or_default().push((b, i));
+            adjacency.entry(b).or_default().push((a, i));
+        }
+    }
+    findings
+}
+
+/// devices along a path from net `a` to net `b`, empty if `a` is `b`, None if not connected
+fn path_between(
+    adjacency: &HashMap<&str, Vec<(&str, usize)>>,
+    a: &str,
+    b: &str,
+) -> Option<Vec<usize>> {
+    let mut from = HashMap::<&str, Option<(&str, usize)>>::from([(a, None)]);
+    let mut queue = VecDeque::from([a]);
+    while let Some(net) = queue.pop_front() {
+        if net == b {
+            let mut path = vec![];
+            let mut at = net;
+            while let Some(Some((prev, device))) = from.get(at) {
+                path.push(*device);
+                at = prev;
+            }
+            return Some(path);
+        }
+        for (next, device) in adjacency.get(net).into_iter().flatten() {
+            if !from.contains_key(next) {
+                from.insert(next, Some((net, *device)));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// canvas marker of a finding, next to the element concerned
+#[derive(Component)]
+struct ErcMarker;
+
+fn marker_bundle(parent: Entity, rule: ErcRule) -> impl Bundle {
+    let color = match rule.severity() {
+        Severity::Error => Color::srgb(1.0, 0.2, 0.2),
+        Severity::Warning => Color::srgb(1.0, 0.8, 0.0),
+    };
+    (
+        ErcMarker,
+        SchematicLabel::new(parent, IVec2::new(1, 1)),
+        Text2dBundle {
+            text: Text::from_section(
+                format!("! {}", rule.name()),
+                TextStyle {
+                    font_size: DEFAULT_FONT_SIZE,
+                    color,
+                    ..default()
+                },
+            ),
+            text_anchor: bevy::sprite::Anchor::BottomLeft,
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(100.0, 1.0),
+            },
+            ..default()
+        },
+        ZoomInvariant,
+    )
+}
+
+/// check the schematic and replace the canvas markers
+fn run_erc(
+    q_devices: Query<
+        (
+            Entity,
+            &DevicePorts,
+            &SchematicElement,
+            &SpDeviceId,
+            &DeviceParams,
+        ),
+        Without<Preview>,
+    >,
+    q_nid: Query<&NetId, Without<Preview>>,
+    q_vertices: Query<(Entity, &LineVertex), (Without<Port>, Without<Preview>)>,
+    q_markers: Query<Entity, With<ErcMarker>>,
+    mut erc: ResMut<Erc>,
+    mut commands: Commands,
+) {
+    let mut input = ErcInput::default();
+    for nid in q_nid.iter() {
+        *input
+            .net_members
+            .entry(nid.get_id().to_owned())
+            .or_default() += 1;
+    }
+    for (entity, ports, se, spdid, params) in q_devices.iter() {
+        let (Some(dtype), Some(name)) = (se.get_dtype(), se.spice_name(spdid)) else {
+            continue;
+        };
+        input.devices.push(ErcDevice {
+            entity,
+            name,
+            dtype: dtype.clone(),
+            ports: ports
+                .get_ports()
+                .iter()
+                .filter_map(|p| Some((*p, q_nid.get(*p).ok()?.get_id().to_owned())))
+                .collect(),
+            references: param_schema(dtype)
+                .iter()
+                .filter_map(|s| match s.kind {
+                    ParamKind::Reference(rtype) => Some((
+                        rtype.clone(),
+                        format!("{}{}", rtype.prefix(), params.field(s.name)?),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        });
+    }
+    input.vertices = q_vertices
+        .iter()
+        .map(|(e, lv)| (e, lv.branches.len()))
+        .collect();
+    erc.findings = check(&input);
+    for e in q_markers.iter() {
+        commands.entity(e).despawn();
+    }
+    for f in erc.findings.iter() {
+        for e in f.entities.iter() {
+            commands.spawn(marker_bundle(*e, f.rule));
+        }
+    }
+}
+
+pub struct ErcPlugin;
+
+impl Plugin for ErcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Erc>();
+        app.add_systems(
+            Update,
+            run_erc
+                .after(ElectricalSet::Prune)
+                .run_if(on_event::<SchematicChanged>()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: u32, dtype: SpDeviceType, name: &str, nets: &[&str]) -> ErcDevice {
+        ErcDevice {
+            entity: Entity::from_raw(id),
+            name: name.to_owned(),
+            dtype,
+            ports: nets
+                .iter()
+                .enumerate()
+                .map(|(i, n)| (Entity::from_raw(id * 10 + i as u32), n.to_string()))
+                .collect(),
+            references: vec![],
+        }
+    }
+
+    fn rules(input: &ErcInput) -> Vec<ErcRule> {
+        check(input).into_iter().map(|f| f.rule).collect()
+    }
+
+    #[test]
+    fn test_clean() {
+        let input = ErcInput {
+            devices: vec![
+                device(1, SpDeviceType::Gnd, "Gnd1", &["0"]),
+                device(2, SpDeviceType::V, "V1", &["net_1", "0"]),
+                device(3, SpDeviceType::R, "R1", &["net_1", "0"]),
+            ],
+            net_members: HashMap::from([("0".to_owned(), 5), ("net_1".to_owned(), 4)]),
+            vertices: vec![(Entity::from_raw(100), 2)],
+        };
+        assert!(check(&input).is_empty());
+    }
+
+    #[test]
+    fn test_findings() {
+        let input = ErcInput {
+            devices: vec![
+                device(1, SpDeviceType::V, "V1", &["net_1", "net_2"]),
+                device(2, SpDeviceType::L, "L1", &["net_2", "net_3"]),
+                device(3, SpDeviceType::V, "V2", &["net_3", "net_1"]),
+                device(4, SpDeviceType::I, "I1", &["net_1", "net_4"]),
+                device(5, SpDeviceType::C, "C1", &["net_4", "net_5"]),
+                device(6, SpDeviceType::R, "R1", &["net_5", "net_1"]),
+                device(7, SpDeviceType::R, "r1", &["net_5", "net_6"]),
+            ],
+            net_members: HashMap::from([
+                ("net_1".to_owned(), 4),
+                ("net_2".to_owned(), 2),
+                ("net_3".to_owned(), 2),
+                ("net_4".to_owned(), 2),
+                ("net_5".to_owned(), 3),
+                ("net_6".to_owned(), 1),
+            ]),
+            vertices: vec![(Entity::from_raw(100), 1)],
+        };
+        assert_eq!(
+            rules(&input),
+            [
+                ErcRule::NoGround,
+                ErcRule::FloatingPin,
+                ErcRule::SourceLoop,
+                ErcRule::CurrentSourceCap,
+                ErcRule::DuplicateName,
+                ErcRule::DeadEndWire,
+            ]
+        );
+        let findings = check(&input);
+        assert_eq!(findings[1].message, "pin 2 of r1 is not connected");
+        assert_eq!(
+            findings[2].message,
+            "loop of voltage sources and inductors: V1, L1, V2"
+        );
+        assert_eq!(findings[4].entities.len(), 2);
+    }
+
+    #[test]
+    fn test_missing_reference() {
+        let mut f1 = device(3, SpDeviceType::F, "F1", &["net_1", "0"]);
+        f1.references = vec![(SpDeviceType::V, "V1".to_owned())];
+        let mut h1 = device(4, SpDeviceType::H, "H1", &["net_1", "0"]);
+        h1.references = vec![(SpDeviceType::V, "V2".to_owned())];
+        let mut k1 = device(5, SpDeviceType::K, "K1", &[]);
+        k1.references = vec![(SpDeviceType::L, "L1".to_owned())];
+        let input = ErcInput {
+            devices: vec![
+                device(1, SpDeviceType::Gnd, "Gnd1", &["0"]),
+                device(2, SpDeviceType::V, "v1", &["net_1", "0"]),
+                f1,
+                h1,
+                k1,
+            ],
+            net_members: HashMap::from([("0".to_owned(), 4), ("net_1".to_owned(), 3)]),
+            vertices: vec![],
+        };
+        assert_eq!(
+            rules(&input),
+            [
+                ErcRule::SourceLoop,
+                ErcRule::MissingReference,
+                ErcRule::MissingReference,
+            ]
+        );
+        let findings = check(&input);
+        assert_eq!(
+            findings[1].message,
+            "H1 refers to V2, which is not in the schematic"
+        );
+    }
+
+    #[test]
+    fn test_shorted_source() {
+        let devices = [device(1, SpDeviceType::V, "V1", &["net_1", "net_1"])];
+        assert_eq!(source_loops(&devices).len(), 1);
+    }
+}
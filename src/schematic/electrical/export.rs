@@ -0,0 +1,285 @@
+//! export of simulation results
+//! the plots of a run are written as csv, one row per point, or as an ngspice raw file in ascii
+//! or binary form, so that they can be loaded by other waveform viewers. vectors are written in
+//! the order described by ngspice, if known.
+
+use std::{fs, io, path::Path};
+
+use super::results::{SimPlot, SimVector};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    RawAscii,
+    RawBinary,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Csv,
+        ExportFormat::RawAscii,
+        ExportFormat::RawBinary,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::RawAscii => "raw (ascii)",
+            ExportFormat::RawBinary => "raw (binary)",
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::RawAscii | ExportFormat::RawBinary => "raw",
+        }
+    }
+}
+
+/// write the plots of a run to `path`
+pub fn export(plots: &[SimPlot], format: ExportFormat, path: &Path) -> io::Result<()> {
+    if plots.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no results"));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match format {
+        ExportFormat::Csv => fs::write(path, to_csv(plots)),
+        ExportFormat::RawAscii => fs::write(path, to_raw_ascii(plots)),
+        ExportFormat::RawBinary => fs::write(path, to_raw_binary(plots)),
+    }
+}
+
+/// csv with a header row, complex vectors as `re(name)` and `im(name)` columns
+/// stepped runs get leading columns with the stepped values, so that all steps fit in one table
+pub fn to_csv(plots: &[SimPlot]) -> String {
+    let Some(first) = plots.first() else {
+        return String::new();
+    };
+    let columns = first.columns();
+    let mut header = first
+        .get_step()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for v in columns.iter() {
+        if v.imag().is_some() {
+            header.push(format!("re({})", v.get_name()));
+            header.push(format!("im({})", v.get_name()));
+        } else {
+            header.push(v.get_name().to_owned());
+        }
+    }
+    let mut csv = header.join(",") + "\n";
+    for plot in plots {
+        let columns = plot.columns();
+        for i in 0..points(&columns) {
+            let mut row = plot
+                .get_step()
+                .iter()
+                .map(|(_, value)| format!("{:e}", value))
+                .collect::<Vec<_>>();
+            for v in columns.iter() {
+                row.push(format!("{:e}", v.real()[i]));
+                if let Some(imag) = v.imag() {
+                    row.push(format!("{:e}", imag[i]));
+                }
+            }
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// ngspice ascii raw file, one plot per step
+pub fn to_raw_ascii(plots: &[SimPlot]) -> String {
+    let mut raw = String::new();
+    for plot in plots {
+        let columns = plot.columns();
+        let complex = is_complex(&columns);
+        raw.push_str(&raw_header(plot, &columns));
+        raw.push_str("Values:\n");
+        for i in 0..points(&columns) {
+            for (j, v) in columns.iter().enumerate() {
+                if j == 0 {
+                    raw.push_str(&format!(" {}", i));
+                }
+                if complex {
+                    let im = v.imag().map_or(0.0, |imag| imag[i]);
+                    raw.push_str(&format!("\t{:.15e},{:.15e}\n", v.real()[i], im));
+                } else {
+                    raw.push_str(&format!("\t{:.15e}\n", v.real()[i]));
+                }
+            }
+        }
+    }
+    raw
+}
+
+/// ngspice binary raw file: the ascii header followed by little endian doubles, point by point
+pub fn to_raw_binary(plots: &[SimPlot]) -> Vec<u8> {
+    let mut raw = vec![];
+    for plot in plots {
+        let columns = plot.columns();
+        let complex = is_complex(&columns);
+        raw.extend(raw_header(plot, &columns).bytes());
+        raw.extend(b"Binary:\n");
+        for i in 0..points(&columns) {
+            for v in columns.iter() {
+                raw.extend(v.real()[i].to_le_bytes());
+                if complex {
+                    raw.extend(v.imag().map_or(0.0, |imag| imag[i]).to_le_bytes());
+                }
+            }
+        }
+    }
+    raw
+}
+
+/// number of points common to all columns
+fn points(columns: &[&SimVector]) -> usize {
+    columns.iter().map(|v| v.len()).min().unwrap_or(0)
+}
+
+fn is_complex(columns: &[&SimVector]) -> bool {
+    columns.iter().any(|v| v.imag().is_some())
+}
+
+/// header up to, not including, the values
+fn raw_header(plot: &SimPlot, columns: &[&SimVector]) -> String {
+    let info = plot.get_info();
+    let title = info.map_or("scirke", |i| i.title.as_str());
+    let date = info.map_or("", |i| i.date.as_str());
+    let mut plotname = info
+        .map(|i| i.plotname.clone())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| plotname_of(plot.get_analysis()).to_owned());
+    for (name, value) in plot.get_step() {
+        plotname.push_str(&format!(" {}={:e}", name, value));
+    }
+    let flags = if is_complex(columns) {
+        "complex"
+    } else {
+        "real"
+    };
+    let mut header = format!(
+        "Title: {}\nDate: {}\nPlotname: {}\nFlags: {}\nNo. Variables: {}\nNo. Points: {}\nVariables:\n",
+        title,
+        date,
+        plotname,
+        flags,
+        columns.len(),
+        points(columns)
+    );
+    for (i, v) in columns.iter().enumerate() {
+        header.push_str(&format!(
+            "\t{}\t{}\t{}\n",
+            i,
+            v.get_name(),
+            var_type(v.get_name())
+        ));
+    }
+    header
+}
+
+/// plot name ngspice gives to an analysis
+fn plotname_of(analysis: &str) -> &str {
+    match analysis {
+        "op" => "Operating Point",
+        "dc" => "DC transfer characteristic",
+        "ac" => "AC Analysis",
+        "tran" => "Transient Analysis",
+        other => other,
+    }
+}
+
+/// raw file variable type of a vector
+fn var_type(name: &str) -> &'static str {
+    match name {
+        "time" => "time",
+        "frequency" => "frequency",
+        _ if name.ends_with("#branch") || name.starts_with('@') => "current",
+        _ => "voltage",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paprika::{PkVecvalues, PkVecvaluesall};
+
+    use super::*;
+
+    fn plot(analysis: &str, points: &[&[(&str, f64, Option<f64>)]]) -> SimPlot {
+        let steps = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| PkVecvaluesall {
+                count: point.len() as i32,
+                index: i as i32,
+                vecsa: point
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (name, re, im))| PkVecvalues {
+                        name: name.to_string(),
+                        creal: *re,
+                        cimag: im.unwrap_or(0.0),
+                        is_scale: j == 0,
+                        is_complex: im.is_some(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        SimPlot::from_vecvals(analysis, steps)
+    }
+
+    #[test]
+    fn test_csv() {
+        let tran = plot(
+            "tran",
+            &[
+                &[("time", 0.0, None), ("net_1", 1.0, None)],
+                &[("time", 1e-3, None), ("net_1", 0.5, None)],
+            ],
+        );
+        assert_eq!(to_csv(&[tran.clone()]), "time,net_1\n0e0,1e0\n1e-3,5e-1\n");
+
+        let stepped = [
+            tran.clone().with_step(vec![("R1".to_owned(), 1e3)]),
+            tran.with_step(vec![("R1".to_owned(), 2e3)]),
+        ];
+        let csv = to_csv(&stepped);
+        assert!(csv.starts_with("R1,time,net_1\n1e3,0e0,1e0\n"));
+        assert!(csv.ends_with("2e3,1e-3,5e-1\n"));
+
+        let ac = plot(
+            "ac",
+            &[&[("frequency", 1.0, Some(0.0)), ("net_1", 0.5, Some(-0.5))]],
+        );
+        assert_eq!(
+            to_csv(&[ac]),
+            "re(frequency),im(frequency),re(net_1),im(net_1)\n1e0,0e0,5e-1,-5e-1\n"
+        );
+    }
+
+    #[test]
+    fn test_raw() {
+        let tran = plot(
+            "tran",
+            &[
+                &[("time", 0.0, None), ("v1#branch", -1.0, None)],
+                &[("time", 1e-3, None), ("v1#branch", -2.0, None)],
+            ],
+        );
+        let raw = to_raw_ascii(&[tran.clone()]);
+        assert!(raw.contains("Plotname: Transient Analysis\nFlags: real\n"));
+        assert!(raw.contains("No. Variables: 2\nNo. Points: 2\n"));
+        assert!(raw.contains("\t0\ttime\ttime\n\t1\tv1#branch\tcurrent\n"));
+        assert!(raw.ends_with(" 1\t1.000000000000000e-3\n\t-2.000000000000000e0\n"));
+
+        let binary = to_raw_binary(&[tran]);
+        let start = binary.windows(8).position(|w| w == b"Binary:\n").unwrap() + 8;
+        assert_eq!(binary.len() - start, 2 * 2 * 8);
+        assert_eq!(binary[binary.len() - 8..], (-2.0f64).to_le_bytes());
+    }
+}
@@ -0,0 +1,521 @@
+//! spice netlist import
+//! reads a `.cir` file and spawns its devices as a placed and wired schematic.
+//! supported elements are R, L, C, V, I, D, Q and M, along with `.model` cards.
+//! reference designators of the original netlist are kept where they do not collide.
+//!
+//! layout: devices are placed in a single row. every port is routed to a horizontal lane
+//! belonging to its net, above the row for ports on top of a device and below for the rest.
+//! nets with ports both on top and on bottom get a vertical connector left of the row.
+//! lanes and connectors are unique to each net, so that wires of different nets only ever cross.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+
+use crate::schematic::{ui::console::PrintConsoleLine, SchematicChanged};
+
+use super::{
+    devices::{spawn_preview_device_from_type, DeviceParams, DeviceRegistry},
+    models::{ModelLibrary, SpiceModel},
+    nets::create_lineseg,
+    readable_idgen::IdTracker,
+    spid::SpDeviceType,
+    ElementsRes, NetId, Preview, Selected, SpDeviceId,
+};
+
+/// least horizontal distance between devices
+const DEVICE_PITCH: i32 = 8;
+/// distance between the device row and the first lane, and between lanes
+const LANE_PITCH: i32 = 2;
+
+/// request to import the netlist at path
+#[derive(Event)]
+pub struct ImportNetlist {
+    path: PathBuf,
+}
+
+impl ImportNetlist {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+/// a device read from a netlist
+#[derive(Debug, Clone, PartialEq)]
+struct ImportedDevice {
+    dtype: SpDeviceType,
+    /// reference designator without the type prefix, e.g. `1` for `R1`
+    id: String,
+    /// net of each port, in spice order
+    nodes: Vec<String>,
+    params: String,
+}
+
+#[derive(Debug, Default)]
+struct ImportedNetlist {
+    devices: Vec<ImportedDevice>,
+    models: Vec<SpiceModel>,
+    /// lines which could not be imported
+    warnings: Vec<String>,
+}
+
+/// parse netlist text. the first line is the title and is ignored
+fn parse_netlist(src: &str) -> ImportedNetlist {
+    let mut out = ImportedNetlist::default();
+    // join continuation lines, keeping the line number of the first
+    let mut cards: Vec<(usize, String)> = vec![];
+    for (i, line) in src.lines().enumerate().skip(1) {
+        let line = line.trim();
+        if let Some(cont) = line.strip_prefix('+') {
+            if let Some((_, card)) = cards.last_mut() {
+                card.push(' ');
+                card.push_str(cont.trim());
+            }
+        } else if !line.is_empty() && !line.starts_with('*') {
+            cards.push((i + 1, line.to_owned()));
+        }
+    }
+    for (lineno, card) in cards {
+        if card.starts_with('.') {
+            let cmd = card.split_whitespace().next().unwrap().to_ascii_lowercase();
+            match cmd.as_str() {
+                ".end" => break,
+                ".model" => match SpiceModel::parse(&card) {
+                    Some(model) => out.models.push(model),
+                    None => out
+                        .warnings
+                        .push(format!("line {}: malformed model: {}", lineno, card)),
+                },
+                _ => out
+                    .warnings
+                    .push(format!("line {}: ignored {}", lineno, cmd)),
+            }
+            continue;
+        }
+        match parse_device(&card) {
+            Some(device) => out.devices.push(device),
+            None => out
+                .warnings
+                .push(format!("line {}: unsupported element: {}", lineno, card)),
+        }
+    }
+    out
+}
+
+/// parse a device line, e.g. `R1 in out 1k`
+fn parse_device(card: &str) -> Option<ImportedDevice> {
+    let mut tokens = card.split_whitespace();
+    let name = tokens.next()?;
+    // subcircuit instances need their definition, which is not imported
+    let mut dtype =
+        SpDeviceType::from_prefix(name.get(..1)?).filter(|dtype| *dtype != SpDeviceType::X)?;
+    let count = dtype.port_count()?;
+    let id = name[1..].to_owned();
+    if id.is_empty() {
+        return None;
+    }
+    let mut nodes = tokens
+        .by_ref()
+        .take(count)
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    if nodes.len() < count {
+        return None;
+    }
+    let mut params = tokens.collect::<Vec<_>>().join(" ");
+    // ground symbols are netlisted as `VGnd1 net 0 0`
+    if dtype == SpDeviceType::V && id.to_ascii_lowercase().starts_with("gnd") && nodes[1] == "0" {
+        dtype = SpDeviceType::Gnd;
+        nodes.truncate(1);
+        params = format!("0 {}", params);
+    }
+    Some(ImportedDevice {
+        dtype,
+        id,
+        nodes,
+        params,
+    })
+}
+
+/// placement of imported devices and the wires connecting them
+#[derive(Debug, Default)]
+struct Layout {
+    /// origin of each device
+    positions: Vec<IVec2>,
+    /// wire segments of each net
+    wires: Vec<(String, IVec2, IVec2)>,
+}
+
+/// place devices, each given as a list of (port offset, net), and route their nets
+/// lanes are laid clear of the furthest port of any device from its origin
+fn layout(devices: &[Vec<(IVec2, String)>]) -> Layout {
+    let extent = devices
+        .iter()
+        .flatten()
+        .map(|(offset, _)| offset.x.abs().max(offset.y.abs()))
+        .max()
+        .unwrap_or(0);
+    let pitch = DEVICE_PITCH.max(2 * extent + LANE_PITCH);
+    let positions = (0..devices.len() as i32)
+        .map(|i| IVec2::new(i * pitch, 0))
+        .collect::<Vec<_>>();
+    // port locations of each net, split by routing direction, in order of appearance
+    let mut nets: Vec<(String, Vec<IVec2>, Vec<IVec2>)> = vec![];
+    for (ports, pos) in devices.iter().zip(positions.iter()) {
+        for (offset, net) in ports {
+            let i = match nets.iter().position(|(n, _, _)| n == net) {
+                Some(i) => i,
+                None => {
+                    nets.push((net.clone(), vec![], vec![]));
+                    nets.len() - 1
+                }
+            };
+            if offset.y > 0 {
+                nets[i].1.push(*pos + *offset);
+            } else {
+                nets[i].2.push(*pos + *offset);
+            }
+        }
+    }
+    let mut wires = vec![];
+    let (mut n_up, mut n_down, mut n_conn) = (0, 0, 0);
+    for (net, up, down) in nets {
+        if up.len() + down.len() < 2 {
+            // nothing to connect to
+            continue;
+        }
+        let connector = (!up.is_empty() && !down.is_empty()).then(|| {
+            n_conn += 1;
+            -extent - LANE_PITCH * n_conn
+        });
+        let mut lanes = vec![];
+        if !up.is_empty() {
+            n_up += 1;
+            lanes.push((extent + LANE_PITCH * n_up, up));
+        }
+        if !down.is_empty() {
+            n_down += 1;
+            lanes.push((-extent - LANE_PITCH * n_down, down));
+        }
+        for (y, ports) in lanes.iter() {
+            let mut xs = ports
+                .iter()
+                .map(|p| p.x)
+                .chain(connector)
+                .collect::<Vec<_>>();
+            xs.sort();
+            xs.dedup();
+            for p in ports {
+                wires.push((net.clone(), *p, IVec2::new(p.x, *y)));
+            }
+            for x in xs.windows(2) {
+                wires.push((net.clone(), IVec2::new(x[0], *y), IVec2::new(x[1], *y)));
+            }
+        }
+        if let (Some(x), [(y0, _), (y1, _)]) = (connector, lanes.as_slice()) {
+            wires.push((net.clone(), IVec2::new(x, *y0), IVec2::new(x, *y1)));
+        }
+    }
+    Layout { positions, wires }
+}
+
+/// read netlists requested by [`ImportNetlist`] and spawn them as schematic elements
+/// the schematic is marked changed on the frame after spawning, once device transforms have propagated
+fn import_netlist(
+    mut e_import: EventReader<ImportNetlist>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+    mut e_changed: EventWriter<SchematicChanged>,
+    mut commands: Commands,
+    eres: Res<ElementsRes>,
+    registry: Res<DeviceRegistry>,
+    mut idtracker: ResMut<IdTracker>,
+    mut models: ResMut<ModelLibrary>,
+    mut spawned: Local<bool>,
+) {
+    if *spawned {
+        *spawned = false;
+        e_changed.send(SchematicChanged);
+    }
+    for ev in e_import.read() {
+        let src = match fs::read_to_string(&ev.path) {
+            Ok(src) => src,
+            Err(e) => {
+                e_console_rgstr.send(PrintConsoleLine::new(
+                    format!("import {}: {}", ev.path.display(), e),
+                    Color32::RED,
+                ));
+                continue;
+            }
+        };
+        let imported = parse_netlist(&src);
+        for warning in imported.warnings.iter() {
+            e_console_rgstr.send(PrintConsoleLine::new(
+                format!("import: {}", warning),
+                Color32::YELLOW,
+            ));
+        }
+        let nmodels = imported.models.len();
+        for model in imported.models {
+            models.insert(model);
+        }
+
+        let mut devices = imported.devices;
+        // the spice ground node is connected through a ground symbol
+        if devices.iter().any(|d| d.nodes.iter().any(|n| n == "0")) {
+            devices.push(ImportedDevice {
+                dtype: SpDeviceType::Gnd,
+                id: String::new(),
+                nodes: vec!["0".to_owned()],
+                params: "0 0".to_owned(),
+            });
+        }
+        let dtypes = devices
+            .iter()
+            .map(|d| {
+                registry
+                    .from_spice_type(&d.dtype)
+                    .ok_or_else(|| format!("no device definition of type {}", d.dtype.prefix()))
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let dtypes = match dtypes {
+            Ok(dtypes) => dtypes,
+            Err(e) => {
+                e_console_rgstr.send(PrintConsoleLine::new(
+                    format!("import {}: {}", ev.path.display(), e),
+                    Color32::RED,
+                ));
+                continue;
+            }
+        };
+        let placed = layout(
+            &devices
+                .iter()
+                .zip(dtypes.iter())
+                .map(|(d, dtype)| {
+                    dtype
+                        .get_ports()
+                        .iter()
+                        .cloned()
+                        .zip(d.nodes.iter().cloned())
+                        .collect()
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let mut port_nets = vec![];
+        for ((device, dtype), pos) in devices.iter().zip(dtypes).zip(placed.positions.iter()) {
+            let entities = spawn_preview_device_from_type(dtype, &mut commands, &eres);
+            // device, ports, label
+            let device_entity = entities[0];
+            for (port, net) in entities[1..].iter().zip(device.nodes.iter()) {
+                port_nets.push((*port, net.clone()));
+            }
+            commands
+                .entity(device_entity)
+                .insert((
+                    Transform::from_translation(pos.as_vec2().extend(0.0)),
+                    DeviceParams::parse(&device.dtype, &device.params),
+                ))
+                .remove::<(Preview, Selected)>();
+            // without a registered id, one is assigned by the devices module
+            if !device.id.is_empty() && idtracker.register_device_id(&device.dtype, &device.id) {
+                commands
+                    .entity(device_entity)
+                    .insert(SpDeviceId::new(device.id.clone()));
+            }
+            for e in entities[1..].iter() {
+                commands.entity(*e).remove::<(Preview, Selected)>();
+            }
+        }
+        // keep net names where possible; ground and colliding names get fresh ids
+        let mut netids = HashMap::<String, NetId>::new();
+        let mut netid = |net: &str| {
+            netids
+                .entry(net.to_owned())
+                .or_insert_with(|| {
+                    if net != "0" && idtracker.register_net_id(net) {
+                        NetId::new(net.to_owned())
+                    } else {
+                        NetId::new(idtracker.new_net_id())
+                    }
+                })
+                .clone()
+        };
+        for (port, net) in port_nets {
+            commands.entity(port).insert(netid(&net));
+        }
+        for (net, src, dst) in placed.wires.iter() {
+            let netid = netid(net);
+            for e in create_lineseg(
+                &mut commands,
+                &eres,
+                src.as_vec2().extend(0.0),
+                dst.as_vec2().extend(0.0),
+            ) {
+                commands.entity(e).insert(netid.clone());
+            }
+        }
+        e_console_rgstr.send(PrintConsoleLine::new(
+            format!(
+                "imported {} devices and {} models from {}",
+                devices.len(),
+                nmodels,
+                ev.path.display()
+            ),
+            Color32::GREEN,
+        ));
+        *spawned = true;
+    }
+}
+
+pub struct ImportPlugin;
+
+impl Plugin for ImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImportNetlist>();
+        app.add_systems(Update, import_netlist.before(super::ElectricalSet::Direct));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETLIST: &str = "divider
+* comment
+V1 in 0 DC 5 AC 1
+R1 in out
++ 1k
+R2 out 0 1k
+D1 out 0 dmod
+Q1 c b 0 npn1
+M1 d g 0 0 nmos1 w=1u l=1u
+VGnd1 gnd 0 0
+X1 a b sub
+.model dmod D(is=1e-14)
+.tran 1u 1m
+.end
+R3 ignored 0 1
+";
+
+    #[test]
+    fn test_parse_netlist() {
+        let imported = parse_netlist(NETLIST);
+        let devices = imported.devices;
+        assert_eq!(devices.len(), 7);
+        assert_eq!(devices[0].dtype, SpDeviceType::V);
+        assert_eq!(devices[0].id, "1");
+        assert_eq!(devices[0].nodes, ["in", "0"]);
+        assert_eq!(devices[0].params, "DC 5 AC 1");
+        // continuation line
+        assert_eq!(devices[1].params, "1k");
+        assert_eq!(devices[4].nodes, ["c", "b", "0"]);
+        assert_eq!(devices[4].params, "npn1");
+        assert_eq!(devices[5].nodes, ["d", "g", "0", "0"]);
+        assert_eq!(devices[5].params, "nmos1 w=1u l=1u");
+        assert_eq!(devices[6].dtype, SpDeviceType::Gnd);
+        assert_eq!(devices[6].id, "Gnd1");
+        assert_eq!(devices[6].nodes, ["gnd"]);
+        assert_eq!(devices[6].params, "0 0");
+
+        assert_eq!(imported.models.len(), 1);
+        assert_eq!(imported.models[0].get_name(), "dmod");
+        // subcircuit instance and analysis
+        assert_eq!(imported.warnings.len(), 2);
+    }
+
+    /// true if p lies on the axis aligned segment a-b
+    fn on_segment(p: IVec2, a: IVec2, b: IVec2) -> bool {
+        p.x >= a.x.min(b.x) && p.x <= a.x.max(b.x) && p.y >= a.y.min(b.y) && p.y <= a.y.max(b.y)
+    }
+
+    #[test]
+    fn test_layout() {
+        let two = |a: &str, b: &str, y: i32| {
+            vec![
+                (IVec2::new(0, y), a.to_owned()),
+                (IVec2::new(0, -y), b.to_owned()),
+            ]
+        };
+        let small = vec![
+            two("in", "0", 3),
+            two("in", "out", 3),
+            two("out", "0", 3),
+            vec![
+                (IVec2::new(1, 3), "in".to_owned()),
+                (IVec2::new(-2, 0), "out".to_owned()),
+                (IVec2::new(1, -3), "0".to_owned()),
+            ],
+            vec![(IVec2::new(0, 2), "0".to_owned())],
+        ];
+        // ports further from the origin, and past the device pitch
+        let large = vec![
+            two("in", "0", 5),
+            vec![
+                (IVec2::new(-6, 1), "in".to_owned()),
+                (IVec2::new(6, -1), "out".to_owned()),
+            ],
+            two("out", "0", 5),
+            two("in", "out", 3),
+        ];
+        for devices in [small, large] {
+            let placed = layout(&devices);
+            assert_eq!(placed.positions.len(), devices.len());
+            let ports = devices
+                .iter()
+                .zip(placed.positions.iter())
+                .flat_map(|(d, pos)| {
+                    d.iter()
+                        .map(move |(offset, net)| (*pos + *offset, net.clone()))
+                })
+                .collect::<Vec<_>>();
+            // wires are axis aligned
+            for (_, a, b) in placed.wires.iter() {
+                assert!(a.x == b.x || a.y == b.y);
+                assert_ne!(a, b);
+            }
+            // no port or wire end touches a wire of another net
+            let vertices = ports
+                .iter()
+                .cloned()
+                .chain(
+                    placed
+                        .wires
+                        .iter()
+                        .flat_map(|(n, a, b)| [(*a, n.clone()), (*b, n.clone())]),
+                )
+                .collect::<Vec<_>>();
+            for (p, pnet) in vertices.iter() {
+                for (wnet, a, b) in placed.wires.iter() {
+                    assert!(
+                        pnet == wnet || !on_segment(*p, *a, *b),
+                        "{} touches {}",
+                        pnet,
+                        wnet
+                    );
+                }
+            }
+            // all ports of a net are connected through its wires
+            for net in ["in", "out", "0"] {
+                let mut reached = vec![ports.iter().find(|(_, n)| n == net).unwrap().0];
+                loop {
+                    let before = reached.len();
+                    for (_, a, b) in placed.wires.iter().filter(|(n, _, _)| n == net) {
+                        if reached.contains(a) && !reached.contains(b) {
+                            reached.push(*b);
+                        } else if reached.contains(b) && !reached.contains(a) {
+                            reached.push(*a);
+                        }
+                    }
+                    if reached.len() == before {
+                        break;
+                    }
+                }
+                for (p, _) in ports.iter().filter(|(_, n)| n == net) {
+                    assert!(reached.contains(p), "{} port at {} not connected", net, p);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! locating the ngspice shared library
+//! in order: the path in `SCIRKE_LIBNGSPICE`, the `libngspice` entry of the config file, the
+//! standard library directories of the platform, and finally the system's own library search.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// environment variable holding the path to libngspice
+pub const ENV_VAR: &str = "SCIRKE_LIBNGSPICE";
+/// environment variable overriding the location of the config file
+const CONFIG_ENV_VAR: &str = "SCIRKE_CONFIG";
+/// config file entry holding the path to libngspice, as `libngspice = <path>`
+const CONFIG_KEY: &str = "libngspice";
+
+#[cfg(target_family = "windows")]
+const LIB_NAMES: &[&str] = &["ngspice.dll"];
+#[cfg(target_os = "macos")]
+const LIB_NAMES: &[&str] = &["libngspice.dylib", "libngspice.0.dylib"];
+#[cfg(not(any(target_family = "windows", target_os = "macos")))]
+const LIB_NAMES: &[&str] = &["libngspice.so", "libngspice.so.0"];
+
+#[cfg(target_family = "windows")]
+const STD_DIRS: &[&str] = &[
+    "C:\\Spice64\\bin",
+    "C:\\Spice\\bin",
+    "C:\\Program Files\\ngspice\\bin",
+];
+#[cfg(target_os = "macos")]
+const STD_DIRS: &[&str] = &[
+    "/opt/homebrew/lib",
+    "/usr/local/lib",
+    "/opt/local/lib",
+    "/usr/lib",
+];
+#[cfg(not(any(target_family = "windows", target_os = "macos")))]
+const STD_DIRS: &[&str] = &[
+    "/usr/local/lib",
+    "/usr/local/lib64",
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+];
+
+/// where libngspice may be loaded from
+#[derive(Debug, PartialEq)]
+pub enum Candidate {
+    /// path given by the user, through the environment or the config file
+    Configured(PathBuf, &'static str),
+    /// library in a standard directory
+    Standard(PathBuf),
+    /// bare library name, left to the system's search, e.g. `LD_LIBRARY_PATH`
+    System(PathBuf),
+}
+
+impl Candidate {
+    pub fn path(&self) -> &Path {
+        match self {
+            Candidate::Configured(path, _)
+            | Candidate::Standard(path)
+            | Candidate::System(path) => path,
+        }
+    }
+}
+
+/// config file, `scirke/config` in the user's config directory unless overridden
+pub fn config_file() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR) {
+        return Some(path.into());
+    }
+    let dir = if cfg!(target_family = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".config")))?
+    };
+    Some(dir.join("scirke").join("config"))
+}
+
+/// value of `key` in a config file of `key = value` lines, with `#` comments
+fn config_value<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+/// places to load libngspice from, in order of preference
+pub fn candidates() -> Vec<Candidate> {
+    let config = config_file().and_then(|path| fs::read_to_string(path).ok());
+    candidates_from(env::var_os(ENV_VAR), config.as_deref(), STD_DIRS, LIB_NAMES)
+}
+
+fn candidates_from(
+    env: Option<OsString>,
+    config: Option<&str>,
+    dirs: &[&str],
+    names: &[&str],
+) -> Vec<Candidate> {
+    let configured = env
+        .map(|path| Candidate::Configured(path.into(), ENV_VAR))
+        .into_iter()
+        .chain(
+            config
+                .and_then(|c| config_value(c, CONFIG_KEY))
+                .map(|path| Candidate::Configured(path.into(), "config file")),
+        );
+    let standard = dirs
+        .iter()
+        .flat_map(|dir| names.iter().map(move |name| Path::new(dir).join(name)))
+        .filter(|path| path.exists())
+        .map(Candidate::Standard);
+    let system = names.iter().map(|name| Candidate::System(name.into()));
+    configured.chain(standard).chain(system).collect()
+}
+
+/// how to point the editor at libngspice, for when it could not be loaded
+pub fn help() -> String {
+    let config = config_file().map_or("the config file".to_owned(), |path| {
+        path.display().to_string()
+    });
+    format!(
+        "set {} to its path, or add `{} = <path>` to {}",
+        ENV_VAR, CONFIG_KEY, config
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_value() {
+        let config = "# scirke\n\nfoo = 1\nlibngspice = /opt/ngspice/lib/libngspice.so # local\n";
+        assert_eq!(
+            config_value(config, "libngspice"),
+            Some("/opt/ngspice/lib/libngspice.so")
+        );
+        assert_eq!(config_value(config, "foo"), Some("1"));
+        assert_eq!(config_value(config, "bar"), None);
+        assert_eq!(config_value("libngspice =\n", "libngspice"), None);
+    }
+
+    #[test]
+    fn test_candidates_order() {
+        let candidates = candidates_from(
+            Some("/env/libngspice.so".into()),
+            Some("libngspice = /cfg/libngspice.so"),
+            &["/nonexistent"],
+            &["libngspice.so"],
+        );
+        assert_eq!(
+            candidates,
+            [
+                Candidate::Configured("/env/libngspice.so".into(), ENV_VAR),
+                Candidate::Configured("/cfg/libngspice.so".into(), "config file"),
+                Candidate::System("libngspice.so".into()),
+            ]
+        );
+        let candidates = candidates_from(None, None, &[], &["libngspice.so"]);
+        assert_eq!(candidates, [Candidate::System("libngspice.so".into())]);
+    }
+}
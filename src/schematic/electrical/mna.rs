@@ -0,0 +1,411 @@
+//! modified nodal analysis solver for linear circuits
+//! solves dc operating points and single frequency ac of R, L, C, V and I devices,
+//! reading the same netlist text that [`super::netlisting`] writes for ngspice.
+//! results are named as ngspice names its vectors: node names and `v1#branch`.
+
+use std::fmt;
+
+use num::complex::Complex64;
+
+use super::units::parse_spice_number;
+
+/// conductance from every node to ground, as in spice, so that floating nodes are solvable
+const GMIN: f64 = 1e-12;
+
+#[derive(Debug, PartialEq)]
+pub enum MnaError {
+    /// device the solver does not model, e.g. a diode
+    Unsupported(String),
+    /// a device line which could not be read
+    Parse(String),
+    /// circuit matrix is singular, e.g. a loop of voltage sources
+    Singular,
+}
+
+impl fmt::Display for MnaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MnaError::Unsupported(dev) => write!(f, "device not supported: {}", dev),
+            MnaError::Parse(line) => write!(f, "could not parse: {}", line),
+            MnaError::Singular => write!(f, "singular circuit matrix"),
+        }
+    }
+}
+
+/// independent source value
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Source {
+    dc: f64,
+    ac: Complex64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Element {
+    R(f64),
+    L(f64),
+    C(f64),
+    V(Source),
+    I(Source),
+}
+
+#[derive(Debug)]
+struct Device {
+    /// lower case spice name, e.g. `r1`
+    name: String,
+    /// node indices, 0 is ground
+    pos: usize,
+    neg: usize,
+    element: Element,
+}
+
+/// a linear circuit ready to be solved
+#[derive(Debug, Default)]
+pub struct Circuit {
+    /// node names, index 0 is ground
+    nodes: Vec<String>,
+    devices: Vec<Device>,
+}
+
+/// node voltages and branch currents of a solved circuit
+#[derive(Debug)]
+pub struct Solution {
+    values: Vec<(String, Complex64)>,
+}
+
+impl Solution {
+    pub fn values(&self) -> &[(String, Complex64)] {
+        &self.values
+    }
+    pub fn get(&self, name: &str) -> Option<Complex64> {
+        self.values
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    }
+}
+
+impl Circuit {
+    /// read a spice netlist, the first line is the title and dot commands are ignored
+    /// subcircuit definitions are skipped whole, their instances are not supported
+    pub fn parse(netlist: &str) -> Result<Self, MnaError> {
+        let mut circuit = Circuit {
+            nodes: vec!["0".to_owned()],
+            devices: vec![],
+        };
+        // join continuation lines
+        let mut lines: Vec<String> = vec![];
+        for line in netlist.lines().skip(1) {
+            let line = line.trim();
+            if let Some(cont) = line.strip_prefix('+') {
+                if let Some(last) = lines.last_mut() {
+                    last.push(' ');
+                    last.push_str(cont);
+                }
+            } else {
+                lines.push(line.to_owned());
+            }
+        }
+        // depth of nested subcircuit definitions
+        let mut depth = 0usize;
+        for line in lines {
+            let keyword = line.split_whitespace().next().unwrap_or_default();
+            if keyword.eq_ignore_ascii_case(".subckt") {
+                depth += 1;
+            } else if keyword.eq_ignore_ascii_case(".ends") {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            if depth > 0 {
+                continue;
+            }
+            if line.eq_ignore_ascii_case(".end") {
+                break;
+            }
+            if line.is_empty() || line.starts_with('*') || line.starts_with('.') {
+                continue;
+            }
+            let device = circuit.parse_device(&line)?;
+            circuit.devices.push(device);
+        }
+        Ok(circuit)
+    }
+
+    fn parse_device(&mut self, line: &str) -> Result<Device, MnaError> {
+        let spaced = line.replace(['(', ')', ','], " ");
+        let tokens = spaced.split_whitespace().collect::<Vec<_>>();
+        let parse_err = || MnaError::Parse(line.to_owned());
+        if tokens.len() < 3 {
+            return Err(parse_err());
+        }
+        let name = tokens[0].to_ascii_lowercase();
+        let value = || {
+            tokens
+                .get(3)
+                .and_then(|t| parse_spice_number(t))
+                .ok_or_else(parse_err)
+        };
+        let element = match name.chars().next() {
+            Some('r') => match value()? {
+                r if r == 0.0 => return Err(parse_err()),
+                r => Element::R(r),
+            },
+            Some('l') => Element::L(value()?),
+            Some('c') => Element::C(value()?),
+            Some('v') => Element::V(parse_source(&tokens[3..])),
+            Some('i') => Element::I(parse_source(&tokens[3..])),
+            _ => return Err(MnaError::Unsupported(tokens[0].to_owned())),
+        };
+        Ok(Device {
+            name,
+            pos: self.node(tokens[1]),
+            neg: self.node(tokens[2]),
+            element,
+        })
+    }
+
+    /// index of node, added if new
+    fn node(&mut self, name: &str) -> usize {
+        if name == "0" || name.eq_ignore_ascii_case("gnd") {
+            return 0;
+        }
+        match self.nodes.iter().position(|n| n == name) {
+            Some(i) => i,
+            None => {
+                self.nodes.push(name.to_owned());
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// dc operating point: inductors are shorts, capacitors are open
+    pub fn solve_op(&self) -> Result<Solution, MnaError> {
+        self.solve(None)
+    }
+
+    /// small signal solution at `freq` Hz, driven by the ac values of the sources
+    pub fn solve_ac(&self, freq: f64) -> Result<Solution, MnaError> {
+        self.solve(Some(freq))
+    }
+
+    fn solve(&self, freq: Option<f64>) -> Result<Solution, MnaError> {
+        let n = self.nodes.len() - 1;
+        // voltage sources and inductors carry a branch current unknown
+        let branches = self
+            .devices
+            .iter()
+            .filter(|d| matches!(d.element, Element::V(_) | Element::L(_)))
+            .collect::<Vec<_>>();
+        let size = n + branches.len();
+        let zero = Complex64::new(0.0, 0.0);
+        let mut a = vec![vec![zero; size]; size];
+        let mut b = vec![zero; size];
+        let w = freq.map_or(0.0, |f| 2.0 * std::f64::consts::PI * f);
+        let jw = Complex64::new(0.0, w);
+
+        for i in 0..n {
+            a[i][i] += GMIN;
+        }
+        let mut branch = n;
+        for d in self.devices.iter() {
+            let (p, q) = (d.pos.checked_sub(1), d.neg.checked_sub(1));
+            let mut admittance = |y: Complex64| {
+                if let Some(p) = p {
+                    a[p][p] += y;
+                }
+                if let Some(q) = q {
+                    a[q][q] += y;
+                }
+                if let (Some(p), Some(q)) = (p, q) {
+                    a[p][q] -= y;
+                    a[q][p] -= y;
+                }
+            };
+            match d.element {
+                Element::R(r) => admittance(Complex64::new(1.0 / r, 0.0)),
+                Element::C(c) => admittance(jw * c),
+                Element::I(src) => {
+                    // positive current flows from pos through the source to neg
+                    let i = if freq.is_some() {
+                        src.ac
+                    } else {
+                        src.dc.into()
+                    };
+                    if let Some(p) = p {
+                        b[p] -= i;
+                    }
+                    if let Some(q) = q {
+                        b[q] += i;
+                    }
+                }
+                Element::V(_) | Element::L(_) => {
+                    if let Some(p) = p {
+                        a[p][branch] += 1.0;
+                        a[branch][p] += 1.0;
+                    }
+                    if let Some(q) = q {
+                        a[q][branch] -= 1.0;
+                        a[branch][q] -= 1.0;
+                    }
+                    match d.element {
+                        Element::V(src) => {
+                            b[branch] = if freq.is_some() {
+                                src.ac
+                            } else {
+                                src.dc.into()
+                            };
+                        }
+                        Element::L(l) => a[branch][branch] -= jw * l,
+                        _ => unreachable!(),
+                    }
+                    branch += 1;
+                }
+            }
+        }
+
+        let x = gauss_solve(a, b)?;
+        let values = self.nodes[1..]
+            .iter()
+            .cloned()
+            .chain(branches.iter().map(|d| format!("{}#branch", d.name)))
+            .zip(x)
+            .collect();
+        Ok(Solution { values })
+    }
+}
+
+/// dc and ac values of a source from the tokens following its nodes, e.g.
+/// `3`, `DC 3 AC 1 0` or `AC 1 SIN 3.3 1 2k 0 0`
+/// without an explicit dc value, the time zero value of the transient function is used
+fn parse_source(tokens: &[&str]) -> Source {
+    let num = |i: usize| tokens.get(i).and_then(|t| parse_spice_number(t));
+    let mut dc = None;
+    let mut tran0 = None;
+    let mut ac = Complex64::new(0.0, 0.0);
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].to_ascii_lowercase().as_str() {
+            "dc" => {
+                dc = num(i + 1);
+                i += 1;
+            }
+            "ac" => {
+                let mag = num(i + 1).unwrap_or(1.0);
+                let phase = num(i + 2);
+                ac = Complex64::from_polar(mag, phase.unwrap_or(0.0).to_radians());
+                i += if phase.is_some() { 2 } else { 1 };
+            }
+            "sin" | "pulse" | "exp" | "sffm" | "am" => tran0 = tran0.or(num(i + 1)),
+            // time value pairs, value at first time point
+            "pwl" => tran0 = tran0.or(num(i + 2)),
+            t => {
+                if dc.is_none() {
+                    dc = parse_spice_number(t);
+                }
+            }
+        }
+        i += 1;
+    }
+    Source {
+        dc: dc.or(tran0).unwrap_or(0.0),
+        ac,
+    }
+}
+
+/// solve `a x = b` by gaussian elimination with partial pivoting
+fn gauss_solve(
+    mut a: Vec<Vec<Complex64>>,
+    mut b: Vec<Complex64>,
+) -> Result<Vec<Complex64>, MnaError> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i][col].norm().total_cmp(&a[j][col].norm()))
+            .unwrap();
+        if a[pivot][col].norm() < 1e-30 {
+            return Err(MnaError::Singular);
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let f = a[row][col] / a[col][col];
+            if f == Complex64::new(0.0, 0.0) {
+                continue;
+            }
+            for k in col..n {
+                let v = a[col][k];
+                a[row][k] -= f * v;
+            }
+            let v = b[col];
+            b[row] -= f * v;
+        }
+    }
+    let mut x = vec![Complex64::new(0.0, 0.0); n];
+    for row in (0..n).rev() {
+        let s: Complex64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1e-9)
+    }
+
+    #[test]
+    fn test_divider_op() {
+        let circuit = Circuit::parse(
+            "title\nV1 in net_0 AC 1 SIN(3 1 2k 0 0)\nR1 in out 1k\nR2 out net_0 2k\nVGnd1 net_0 0 0\n.op\n.end\n",
+        )
+        .unwrap();
+        let sol = circuit.solve_op().unwrap();
+        assert!(close(sol.get("in").unwrap().re, 3.0));
+        assert!(close(sol.get("out").unwrap().re, 2.0));
+        // current flows into the positive terminal of the source
+        assert!(close(sol.get("v1#branch").unwrap().re, -1e-3));
+    }
+
+    #[test]
+    fn test_current_source_and_inductor_op() {
+        let circuit =
+            Circuit::parse("title\nI1 0 a 1m\nL1 a b 1u\nR1 b 0 1k\nC1 a 0 1p\n").unwrap();
+        let sol = circuit.solve_op().unwrap();
+        assert!(close(sol.get("a").unwrap().re, 1.0));
+        assert!(close(sol.get("b").unwrap().re, 1.0));
+        assert!(close(sol.get("l1#branch").unwrap().re, 1e-3));
+    }
+
+    #[test]
+    fn test_rc_lowpass_ac() {
+        let circuit =
+            Circuit::parse("title\nV1 in 0 DC 0 AC 1\nR1 in out 1k\nC1 out 0 1u\n").unwrap();
+        let fc = 1.0 / (2.0 * std::f64::consts::PI * 1e3 * 1e-6);
+        let out = circuit.solve_ac(fc).unwrap().get("out").unwrap();
+        assert!(close(out.norm(), 1.0 / 2f64.sqrt()));
+        assert!(close(out.arg().to_degrees(), -45.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Circuit::parse("title\nD1 a 0 dmod\n").unwrap_err(),
+            MnaError::Unsupported("D1".to_owned())
+        );
+        assert_eq!(
+            Circuit::parse("title\nR1 a 0\n").unwrap_err(),
+            MnaError::Parse("R1 a 0".to_owned())
+        );
+        // devices of a subcircuit body are not top level, its instances are unsupported
+        let subckt = "title\n.subckt buf a b\nR1 a b 1\n.ends buf\nR1 in 0 1k\n";
+        assert_eq!(Circuit::parse(subckt).unwrap().devices.len(), 1);
+        assert_eq!(
+            Circuit::parse(&format!("{}X1 in 0 buf\n", subckt)).unwrap_err(),
+            MnaError::Unsupported("X1".to_owned())
+        );
+        let loop_circuit = Circuit::parse("title\nV1 a 0 1\nV2 a 0 2\n").unwrap();
+        assert_eq!(loop_circuit.solve_op().unwrap_err(), MnaError::Singular);
+    }
+}
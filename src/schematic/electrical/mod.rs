@@ -2,24 +2,64 @@
 //! a schematic element may be reused in a circuit or device designer context (or more)
 //! must support: picking by point/ray, by area intersect, by area contained
 //! picking by point/ray should only ever mark 1 entity as picked
+mod analysis;
+mod backend;
+mod batch;
 mod devices;
+mod diagnostics;
+mod directive;
+mod erc;
+mod export;
+mod import;
 mod label;
+mod libngspice;
+mod mna;
+mod models;
+mod native;
 mod netlisting;
 mod nets;
+mod oppoint;
+mod probe;
+mod rawfile;
 mod readable_idgen;
+mod results;
+mod simrun;
 mod spid;
 mod spmanager;
-
-pub use devices::{spawn_preview_device_from_type, DefaultDevices, DeviceParams};
-pub use netlisting::SimAcHz;
+mod stepping;
+mod subckt;
+mod units;
+
+pub use analysis::{AcSweep, DcSweep, SimAc, SimDc, SimTran};
+pub use batch::run_batch;
+pub use devices::{
+    definition_path, device_dirs, param_schema, path_mesh, spawn_preview_device_from_type,
+    DeviceDef, DeviceParams, DeviceRegistry, ParamKind, ParamSpec, PathCmd, SpawnDevice,
+    SubcktSymbols,
+};
+pub use diagnostics::Diagnostics;
+pub use directive::{spawn_preview_directive, Directive};
+pub use erc::Erc;
+pub use export::{export, ExportFormat};
+pub use import::ImportNetlist;
+pub use models::{assign_model, ModelLibrary, ModelOrigin};
+pub use netlisting::schematic_netlist;
 pub use nets::{create_preview_lineseg, LineVertex};
-pub use spid::{NetId, SpDeviceId};
+pub use oppoint::OpAnnotation;
+pub use probe::{probe_trace, Probe, Probes};
+pub use rawfile::LoadRawFile;
+pub use results::{SimPlot, SimResults, SimVector};
+pub use simrun::{SimControl, SimRun};
+pub use spid::{NetId, SpDeviceId, SpDeviceType};
 pub use spmanager::SPRes;
+pub use stepping::{step_label, Distribution, StepConfig, StepMode, Tolerance, MAX_STEPS};
+pub use subckt::{spawn_preview_subckt, SpawnSubckt, SubcktBuilder};
+pub use units::{format_eng, parse_eng, ComplexFormat};
 
 use label::{sch_label_update, SchematicLabel};
 use nets::{PickableLineSeg, PickableVertex};
 use readable_idgen::IdTracker;
-use spid::{SchType, SpDeviceType, SpType};
+use spid::{SchType, SpType};
 use spmanager::SPManagerPlugin;
 
 use super::{
@@ -82,6 +122,8 @@ pub struct ElementsRes {
     pub mat_pckd: Handle<SchematicMaterial>,
     /// selected + picked material
     pub mat_alld: Handle<SchematicMaterial>,
+    /// material of elements mentioned by a simulator error or warning
+    pub mat_diag: Handle<SchematicMaterial>,
 
     /// schematic elements
     /// lsse
@@ -94,6 +136,7 @@ pub struct ElementsRes {
 
 const MAT_SEL_COLOR: Srgba = basic_colors::YELLOW;
 const MAT_PCK_COLOR: Srgba = basic_colors::WHITE;
+const MAT_DIAG_COLOR: Srgba = basic_colors::RED;
 
 impl FromWorld for ElementsRes {
     fn from_world(world: &mut World) -> Self {
@@ -174,6 +217,9 @@ impl FromWorld for ElementsRes {
             mat_alld: mats.add(SchematicMaterial {
                 color: bevy::prelude::Color::Srgba(MAT_SEL_COLOR + MAT_PCK_COLOR).into(),
             }),
+            mat_diag: mats.add(SchematicMaterial {
+                color: bevy::prelude::Color::Srgba(MAT_DIAG_COLOR).into(),
+            }),
 
             pe_device: PickableElement {
                 behavior: Arc::from(PickableDevice::_4x6()),
@@ -220,6 +266,15 @@ impl SchematicElement {
             None
         }
     }
+    /// true for independent V and I sources, which may be swept by a dc analysis
+    pub fn is_source(&self) -> bool {
+        matches!(self.get_dtype(), Some(SpDeviceType::V | SpDeviceType::I))
+    }
+    /// full spice name of a device, e.g. `R1`
+    pub fn spice_name(&self, spdid: &SpDeviceId) -> Option<String> {
+        self.get_dtype()
+            .map(|x| x.prefix().to_owned() + spdid.get_id())
+    }
 }
 
 /// Pickable trait to define how elements consider themselves "picked"
@@ -250,7 +305,6 @@ impl Plugin for ElementsPlugin {
         app.add_systems(Update, (picking, selection, sch_label_update));
         app.add_systems(PostUpdate, set_mat);
         app.init_resource::<ElementsRes>();
-        app.init_resource::<DefaultDevices>();
         app.register_type::<Selected>();
         app.register_type::<NetId>();
         app.register_type::<SchematicElement>();
@@ -259,9 +313,20 @@ impl Plugin for ElementsPlugin {
         app.register_type::<SpType>();
         app.register_type::<SchematicLabel>();
         app.init_resource::<IdTracker>();
+        app.init_resource::<ModelLibrary>();
         app.add_plugins(devices::DevicesPlugin);
+        app.add_plugins(directive::DirectivePlugin);
         app.add_plugins(nets::NetsPlugin);
         app.add_plugins(netlisting::NetlistPlugin);
+        app.add_plugins(simrun::SimRunPlugin);
+        app.add_plugins(stepping::SteppingPlugin);
+        app.add_plugins(probe::ProbePlugin);
+        app.add_plugins(oppoint::OpPointPlugin);
+        app.add_plugins(diagnostics::DiagnosticsPlugin);
+        app.add_plugins(erc::ErcPlugin);
+        app.add_plugins(import::ImportPlugin);
+        app.add_plugins(rawfile::RawFilePlugin);
+        app.add_plugins(subckt::SubcktPlugin);
         app.add_plugins(SPManagerPlugin);
         app.configure_sets(
             Update,
@@ -382,11 +447,13 @@ fn set_mat(
         &mut Handle<SchematicMaterial>,
         Option<&Picked>,
         Option<&Selected>,
+        Has<diagnostics::Flagged>,
     )>,
     element_res: Res<ElementsRes>,
 ) {
-    for (mut mat, pcked, seld) in q_sse.iter_mut() {
+    for (mut mat, pcked, seld, flagged) in q_sse.iter_mut() {
         match (pcked, seld) {
+            (None, None) if flagged => *mat = element_res.mat_diag.clone(),
             (None, None) => *mat = element_res.mat_dflt.clone(),
             (None, Some(_)) => *mat = element_res.mat_seld.clone(),
             (Some(_), None) => *mat = element_res.mat_pckd.clone(),
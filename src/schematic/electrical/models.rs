@@ -0,0 +1,284 @@
+//! spice device models
+//! devices such as diodes and transistors name a model in their parameters, e.g. `D1 a k dmod`.
+//! the netlister emits what is needed for every model referenced this way:
+//! the `.model` card itself, or the `.include`/`.lib` line of the file it was loaded from.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use super::spid::SpDeviceType;
+
+/// models available without loading any file, named as in the netlisting example
+const BUILTIN_MODELS: [&str; 5] = [
+    ".model DMOD D",
+    ".model BJTN NPN",
+    ".model BJTP PNP",
+    ".model MOSN NMOS level=1",
+    ".model MOSP PMOS level=1",
+];
+
+/// how a model gets into the netlist
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModelOrigin {
+    /// the card itself is emitted
+    Card,
+    /// the file defining the model is included
+    Include(PathBuf),
+    /// a section of a library file is included
+    LibSection(PathBuf, String),
+}
+
+impl ModelOrigin {
+    /// netlist line making the model available
+    fn netlist_line(&self, card: &str) -> String {
+        match self {
+            ModelOrigin::Card => card.to_owned(),
+            ModelOrigin::Include(path) => format!(".include \"{}\"", path.display()),
+            ModelOrigin::LibSection(path, section) => {
+                format!(".lib \"{}\" {}", path.display(), section)
+            }
+        }
+    }
+}
+
+/// a `.model` card
+#[derive(Clone, Debug)]
+pub struct SpiceModel {
+    name: String,
+    /// model type, e.g. `NPN`
+    kind: String,
+    card: String,
+    origin: ModelOrigin,
+}
+
+impl SpiceModel {
+    /// read a card such as `.model DMOD D(is=1e-14)`
+    pub fn parse(card: &str) -> Option<Self> {
+        let card = card.trim();
+        let mut tokens = card
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .filter(|t| !t.is_empty());
+        if !tokens.next()?.eq_ignore_ascii_case(".model") {
+            return None;
+        }
+        let name = tokens.next()?;
+        let kind = tokens.next()?;
+        Some(Self {
+            name: name.to_owned(),
+            kind: kind.to_ascii_uppercase(),
+            card: card.to_owned(),
+            origin: ModelOrigin::Card,
+        })
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn get_card(&self) -> &str {
+        &self.card
+    }
+    pub fn get_origin(&self) -> &ModelOrigin {
+        &self.origin
+    }
+    /// true if a device of dtype may use this model
+    pub fn fits(&self, dtype: &SpDeviceType) -> bool {
+        match dtype {
+            SpDeviceType::D => self.kind == "D",
+            SpDeviceType::Q => matches!(self.kind.as_str(), "NPN" | "PNP"),
+            SpDeviceType::M => matches!(self.kind.as_str(), "NMOS" | "PMOS" | "VDMOS"),
+            SpDeviceType::S => self.kind == "SW",
+            SpDeviceType::W => self.kind == "CSW",
+            _ => false,
+        }
+    }
+}
+
+/// models read from the text of a model or library file
+/// models inside `.lib <section>` blocks remember their section
+fn parse_models(src: &str, path: &Path, include: bool) -> Vec<SpiceModel> {
+    let mut cards: Vec<String> = vec![];
+    for line in src.lines() {
+        let line = line.trim();
+        if let Some(cont) = line.strip_prefix('+') {
+            if let Some(card) = cards.last_mut() {
+                card.push(' ');
+                card.push_str(cont.trim());
+            }
+        } else if !line.is_empty() && !line.starts_with('*') {
+            cards.push(line.to_owned());
+        }
+    }
+    let mut section: Option<String> = None;
+    let mut models = vec![];
+    for card in cards {
+        let tokens = card.split_whitespace().collect::<Vec<_>>();
+        match tokens[0].to_ascii_lowercase().as_str() {
+            // `.lib name` opens a section, `.lib file name` calls another library
+            ".lib" if tokens.len() == 2 => section = Some(tokens[1].to_owned()),
+            ".endl" => section = None,
+            ".model" => {
+                let Some(mut model) = SpiceModel::parse(&card) else {
+                    continue;
+                };
+                model.origin = match (&section, include) {
+                    (Some(section), _) => ModelOrigin::LibSection(path.into(), section.clone()),
+                    (None, true) => ModelOrigin::Include(path.into()),
+                    (None, false) => ModelOrigin::Card,
+                };
+                models.push(model);
+            }
+            _ => {}
+        }
+    }
+    models
+}
+
+/// models available to the netlister
+#[derive(Resource)]
+pub struct ModelLibrary {
+    models: Vec<SpiceModel>,
+}
+
+impl Default for ModelLibrary {
+    fn default() -> Self {
+        Self {
+            models: BUILTIN_MODELS
+                .iter()
+                .filter_map(|card| SpiceModel::parse(card))
+                .collect(),
+        }
+    }
+}
+
+impl ModelLibrary {
+    /// add a model, replacing any existing model of the same name
+    pub fn insert(&mut self, model: SpiceModel) {
+        match self
+            .models
+            .iter_mut()
+            .find(|m| m.name.eq_ignore_ascii_case(&model.name))
+        {
+            Some(m) => *m = model,
+            None => self.models.push(model),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&SpiceModel> {
+        self.models
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+    pub fn models(&self) -> &[SpiceModel] {
+        &self.models
+    }
+    /// load the models of a `.model` or `.lib` file, returns the number of models loaded
+    /// cards of model files are copied into the netlist, library files are included instead
+    pub fn load_file(&mut self, path: &Path) -> Result<usize, String> {
+        let src = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let include = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("lib"));
+        let models = parse_models(&src, path, include);
+        let n = models.len();
+        for model in models {
+            self.insert(model);
+        }
+        Ok(n)
+    }
+    /// models referenced by any of the device parameter strings
+    fn referenced<'a>(&self, params: impl Iterator<Item = &'a str>) -> Vec<&SpiceModel> {
+        let mut used: Vec<&SpiceModel> = vec![];
+        for token in params.flat_map(|p| p.split_whitespace()) {
+            if let Some(m) = self.get(token) {
+                if !used.iter().any(|u| std::ptr::eq(*u, m)) {
+                    used.push(m);
+                }
+            }
+        }
+        used
+    }
+    /// netlist lines making the referenced models available, without duplicate includes
+    pub fn netlist_lines<'a>(&self, params: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+        for model in self.referenced(params) {
+            let line = model.origin.netlist_line(&model.card);
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+}
+
+/// device parameters with the model set to model
+/// the model name is the first parameter of diodes and transistors
+pub fn assign_model(params: &str, model: &str) -> String {
+    let mut tokens = params.split_whitespace();
+    let first = tokens.next();
+    let rest = match first {
+        // keep instance parameters such as `w=1u` or `area=2`
+        Some(t) if t.contains('=') => params.split_whitespace().collect::<Vec<_>>(),
+        _ => tokens.collect(),
+    };
+    std::iter::once(model)
+        .chain(rest)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_models() {
+        let src = "* library
+.model d1n4148 D(Is=2.52n Rs=.568 N=1.752
++ Cjo=4p M=.4 tt=20n)
+.lib tt
+.model nch NMOS level=1
+.endl
+.lib other.lib ff
+";
+        let path = Path::new("lib/models.lib");
+        let models = parse_models(src, path, true);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].get_name(), "d1n4148");
+        assert_eq!(models[0].get_kind(), "D");
+        assert!(models[0].get_card().ends_with("Cjo=4p M=.4 tt=20n)"));
+        assert_eq!(models[0].origin, ModelOrigin::Include(path.into()));
+        assert_eq!(
+            models[1].origin,
+            ModelOrigin::LibSection(path.into(), "tt".to_owned())
+        );
+        assert!(models[1].fits(&SpDeviceType::M));
+        assert!(!models[1].fits(&SpDeviceType::Q));
+        // model files are copied as cards
+        assert_eq!(parse_models(src, path, false)[0].origin, ModelOrigin::Card);
+    }
+
+    #[test]
+    fn test_netlist_lines() {
+        let mut lib = ModelLibrary::default();
+        for model in parse_models(".model qa NPN\n.model qb PNP\n", Path::new("bjt.lib"), true) {
+            lib.insert(model);
+        }
+        let params = ["qa", "qb area=2", "DMOD", "1k"];
+        assert_eq!(
+            lib.netlist_lines(params.into_iter()),
+            [".include \"bjt.lib\"", ".model DMOD D"]
+        );
+    }
+
+    #[test]
+    fn test_assign_model() {
+        assert_eq!(assign_model("", "DMOD"), "DMOD");
+        assert_eq!(assign_model("BJTN area=2", "qa"), "qa area=2");
+        assert_eq!(assign_model("w=1u l=1u", "MOSN"), "MOSN w=1u l=1u");
+    }
+}
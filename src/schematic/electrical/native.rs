@@ -0,0 +1,276 @@
+//! native simulator backend built on the [`super::mna`] solver
+//! runs `.op` and `.ac` analyses of linear circuits without libngspice, and cross-checks
+//! operating points and ac sweeps computed by ngspice.
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+use paprika::{PkVecvalues, PkVecvaluesall};
+
+use crate::schematic::ui::console::PrintConsoleLine;
+
+use super::{
+    analysis::{analysis_of, AcSweep, SimAc},
+    backend::SimulatorBackend,
+    mna::{Circuit, MnaError, Solution},
+    results::SimPlot,
+    simrun::SimFinished,
+    units::parse_spice_number,
+    SPRes,
+};
+
+/// relative deviation from ngspice above which the cross-check reports a mismatch
+const CROSS_CHECK_TOL: f64 = 1e-3;
+
+/// analyses the native solver can run
+#[derive(Debug, Clone)]
+enum NativeAnalysis {
+    Op,
+    Ac(SimAc),
+}
+
+impl NativeAnalysis {
+    /// read an analysis dot command, e.g. `.ac dec 10 1 1meg`
+    fn parse(card: &str) -> Option<Self> {
+        let tokens = card.split_whitespace().collect::<Vec<_>>();
+        match tokens.first()?.to_ascii_lowercase().as_str() {
+            ".op" => Some(NativeAnalysis::Op),
+            ".ac" => Some(NativeAnalysis::Ac(SimAc {
+                sweep: AcSweep::from_keyword(tokens.get(1)?)?,
+                points: parse_spice_number(tokens.get(2)?)? as u32,
+                fstart: parse_spice_number(tokens.get(3)?)? as f32,
+                fstop: parse_spice_number(tokens.get(4)?)? as f32,
+            })),
+            _ => None,
+        }
+    }
+
+    /// per-step values in the shape ngspice sends them
+    fn run(&self, circuit: &Circuit) -> Result<Vec<PkVecvaluesall>, MnaError> {
+        match self {
+            NativeAnalysis::Op => Ok(vec![vecvals(0, None, &circuit.solve_op()?)]),
+            NativeAnalysis::Ac(ac) => ac
+                .frequencies()
+                .into_iter()
+                .enumerate()
+                .map(|(i, f)| Ok(vecvals(i, Some(f), &circuit.solve_ac(f)?)))
+                .collect(),
+        }
+    }
+}
+
+/// one simulation step, with frequency as the scale for ac
+fn vecvals(index: usize, freq: Option<f64>, sol: &Solution) -> PkVecvaluesall {
+    let is_complex = freq.is_some();
+    let scale = freq.map(|f| PkVecvalues {
+        name: "frequency".to_owned(),
+        creal: f,
+        cimag: 0.0,
+        is_scale: true,
+        is_complex,
+    });
+    let vecsa = scale
+        .into_iter()
+        .chain(sol.values().iter().map(|(name, v)| PkVecvalues {
+            name: name.clone(),
+            creal: v.re,
+            cimag: v.im,
+            is_scale: false,
+            is_complex,
+        }))
+        .collect::<Vec<_>>();
+    PkVecvaluesall {
+        count: vecsa.len() as i32,
+        index: index as i32,
+        vecsa,
+    }
+}
+
+/// the first analysis dot command in a netlist, other dot commands such as `.model` are skipped
+fn analysis_card(netlist: &str) -> Option<&str> {
+    netlist
+        .lines()
+        .map(str::trim)
+        .find(|l| analysis_of(l).is_some())
+}
+
+/// simulator backend solving linear circuits natively, used when libngspice is unavailable
+#[derive(Default)]
+pub struct NativeBackend {
+    /// netlist loaded by the last `source` command
+    netlist: Mutex<Option<String>>,
+    vecvals: Mutex<Vec<PkVecvaluesall>>,
+    output: Mutex<Vec<(String, Color32)>>,
+    finished: AtomicBool,
+}
+
+impl NativeBackend {
+    fn print(&self, line: String, color: Color32) {
+        self.output.lock().unwrap().push((line, color));
+    }
+
+    fn run(&self) {
+        let netlist = self.netlist.lock().unwrap().clone();
+        let result = netlist
+            .as_deref()
+            .ok_or_else(|| "no circuit loaded".to_owned())
+            .and_then(|netlist| {
+                let analysis = analysis_card(netlist)
+                    .and_then(NativeAnalysis::parse)
+                    .ok_or_else(|| "only .op and .ac are supported".to_owned())?;
+                let circuit = Circuit::parse(netlist).map_err(|e| e.to_string())?;
+                analysis.run(&circuit).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(vecvals) => *self.vecvals.lock().unwrap() = vecvals,
+            Err(e) => self.print(format!("native solver: {}", e), Color32::RED),
+        }
+        // completes synchronously
+        self.finished.store(true, Ordering::SeqCst);
+    }
+}
+
+impl SimulatorBackend for NativeBackend {
+    fn command(&self, cmd: &str) {
+        match cmd.split_once(' ') {
+            Some(("source", path)) => match fs::read_to_string(path.trim()) {
+                Ok(netlist) => *self.netlist.lock().unwrap() = Some(netlist),
+                Err(e) => self.print(format!("native solver: {}: {}", path, e), Color32::RED),
+            },
+            _ => match cmd.trim() {
+                "bg_run" | "run" => self.run(),
+                // runs complete immediately, nothing to halt
+                "bg_halt" | "bg_resume" => {}
+                _ => self.print(
+                    format!("native solver: unsupported command: {}", cmd),
+                    Color32::LIGHT_RED,
+                ),
+            },
+        }
+    }
+    fn drain_output(&self) -> Vec<(String, Color32)> {
+        self.output.lock().unwrap().drain(..).collect()
+    }
+    fn vecvals_drain(&self) -> Vec<PkVecvaluesall> {
+        self.vecvals.lock().unwrap().drain(..).collect()
+    }
+    fn progress(&self) -> Option<(String, f32)> {
+        None
+    }
+    fn is_running(&self) -> bool {
+        false
+    }
+    fn take_finished(&self) -> bool {
+        self.finished.swap(false, Ordering::SeqCst)
+    }
+    fn is_native(&self) -> bool {
+        true
+    }
+}
+
+/// compare finished ngspice op and ac results against the native solver
+/// circuits the native solver does not support are skipped silently
+pub fn cross_check(
+    mut e_finished: EventReader<SimFinished>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+    spres: Res<SPRes>,
+) {
+    for ev in e_finished.read() {
+        // stepped runs do not simulate out/netlist.cir as is
+        if spres.backend().is_native() || !ev.get_plot().get_step().is_empty() {
+            continue;
+        }
+        let Some(msg) = cross_check_plot(ev.get_plot()) else {
+            continue;
+        };
+        e_console_rgstr.send(msg);
+    }
+}
+
+fn cross_check_plot(plot: &SimPlot) -> Option<PrintConsoleLine> {
+    let netlist = fs::read_to_string("out/netlist.cir").ok()?;
+    let circuit = Circuit::parse(&netlist).ok()?;
+    let scale = plot.scale();
+    // worst relative deviation and the vector it occurs in
+    let mut worst = (0.0, String::new());
+    let steps = match plot.get_analysis() {
+        "op" if plot.is_single_point() => 1,
+        "ac" => scale?.len(),
+        _ => return None,
+    };
+    for i in 0..steps {
+        let sol = match scale {
+            Some(f) if plot.get_analysis() == "ac" => circuit.solve_ac(f.real()[i]),
+            _ => circuit.solve_op(),
+        }
+        .ok()?;
+        for v in plot.vectors() {
+            let Some(native) = sol.get(v.get_name()) else {
+                continue;
+            };
+            let spice = num::complex::Complex64::new(v.real()[i], v.imag().map_or(0.0, |im| im[i]));
+            let dev = (spice - native).norm() / spice.norm().max(native.norm()).max(1e-9);
+            if dev > worst.0 {
+                worst = (dev, v.get_name().to_owned());
+            }
+        }
+    }
+    Some(if worst.0 > CROSS_CHECK_TOL {
+        PrintConsoleLine::new(
+            format!(
+                "cross-check: native solver deviates by {:.1e} on {}",
+                worst.0, worst.1
+            ),
+            Color32::YELLOW,
+        )
+    } else {
+        PrintConsoleLine::new(
+            format!(
+                "cross-check: native solver agrees within {:.0e}",
+                CROSS_CHECK_TOL
+            ),
+            Color32::GRAY,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_ac_sweep() {
+        let netlist = "title\nV1 in 0 AC 1\nR1 in out 1k\nC1 out 0 1u\n.ac dec 10 1 1k\n.end\n";
+        let analysis = analysis_card(netlist)
+            .and_then(NativeAnalysis::parse)
+            .unwrap();
+        let circuit = Circuit::parse(netlist).unwrap();
+        let plot = SimPlot::from_vecvals("ac", analysis.run(&circuit).unwrap());
+        let freq = plot.scale().unwrap();
+        assert_eq!(freq.get_name(), "frequency");
+        assert_eq!(freq.len(), 31);
+        assert!((freq.real()[30] - 1e3).abs() < 1e-6);
+        // passband at 1 Hz
+        let out = plot.get("out").unwrap();
+        assert!((out.real()[0] - 1.0).abs() < 1e-3);
+        assert!(out.imag().is_some());
+    }
+
+    #[test]
+    fn test_analysis_card() {
+        let netlist = "title\n.model DMOD D\n.param r=1k\nR1 a 0 1k\n.op\n.save all\n.end\n";
+        assert_eq!(analysis_card(netlist), Some(".op"));
+        assert!(matches!(
+            analysis_card(netlist).and_then(NativeAnalysis::parse),
+            Some(NativeAnalysis::Op)
+        ));
+        assert_eq!(analysis_card("title\n.temp 85\n.end\n"), None);
+    }
+}
@@ -14,30 +14,34 @@
 //! VGND1 net_0 0 0
 //!
 //! for the most part, each line describes a device and its port connections
-use std::{collections::HashMap, fs};
+//! directives placed on the schematic are emitted verbatim, before the devices
+//! subcircuit instances bring their `.subckt` definition along, emitted once per subcircuit
+//! stepped runs write one netlist per step, with the value of the stepped devices rewritten
+//! couplings (K) come after all other devices, so that the inductors they name are defined
+use std::{collections::BTreeMap, fs};
 
 use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 use bevy_egui::egui::Color32;
 
-use crate::schematic::ui::console::PrintConsoleLine;
+use crate::schematic::{ui::console::PrintConsoleLine, SchematicSet};
 
 use super::{
+    analysis::{split_analyses, SimAc, SimDc, SimTran},
     devices::{DeviceParams, DevicePorts},
-    label::SchematicLabelBundle,
-    nets::{Port, PortLabel},
+    directive::Directive,
+    erc::Erc,
+    models::ModelLibrary,
+    oppoint::op_save_vectors,
+    probe::Probes,
+    results::SimResults,
+    simrun::SimRun,
+    spid::SpDeviceType,
     spmanager::SPRes,
+    stepping::{StepConfig, Tolerance},
+    subckt::Subckt,
     NetId, SchematicElement, SpDeviceId,
 };
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct SimAcHz(pub f32);
-
-impl Default for SimAcHz {
-    fn default() -> Self {
-        Self(60.0)
-    }
-}
-
 #[derive(Event)]
 pub struct Netlist;
 
@@ -46,113 +50,475 @@ pub struct NetlistPlugin;
 impl Plugin for NetlistPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<Netlist>();
+        // preupdate: run on schematic that has been seen
         app.add_systems(
             PreUpdate,
-            (netlist, pksim)
-                .chain()
-                .run_if(input_just_pressed(KeyCode::Space)),
-        ); // preupdate: run on schematic that has been seen
-        app.init_resource::<SimAcHz>();
+            pksim
+                .run_if(input_just_pressed(KeyCode::Space))
+                .in_set(SchematicSet),
+        );
+        app.init_resource::<SimAc>();
+        app.init_resource::<SimTran>();
+        app.init_resource::<SimDc>();
+        app.init_resource::<SimResults>();
     }
 }
 
-fn netlist(
+/// netlist line of a single device: name, net of each port, then params
+pub fn device_line(
+    d: &DevicePorts,
+    params: &DeviceParams,
+    se: &SchematicElement,
+    spdid: &SpDeviceId,
+    q_nid: &Query<&NetId>,
+) -> Option<String> {
+    // push device id
+    let mut line = se.spice_name(spdid)?;
+    line.push_str(" ");
+    // push net id for each port
+    for port in d.get_ports().iter() {
+        let net = q_nid.get(*port).unwrap().get_id();
+        line.push_str(net);
+        line.push_str(" ");
+    }
+    // followed by device value (e.g. resistance, voltage) and params if any
+    line.push_str(&params.spice_param(se.get_dtype()?));
+    Some(line)
+}
+
+/// netlist of the schematic as drawn, including its directives, without `.end`
+pub fn schematic_netlist(
     q_devices: Query<(&DevicePorts, &DeviceParams, &SchematicElement, &SpDeviceId)>,
     q_nid: Query<&NetId>,
-) {
+    q_directives: Query<(&Directive, &Transform)>,
+    q_subckts: Query<&Subckt>,
+    models: Res<ModelLibrary>,
+) -> String {
+    netlist(&q_devices, &q_nid, &q_directives, &q_subckts, &models, &[])
+}
+
+fn netlist(
+    q_devices: &Query<(&DevicePorts, &DeviceParams, &SchematicElement, &SpDeviceId)>,
+    q_nid: &Query<&NetId>,
+    q_directives: &Query<(&Directive, &Transform)>,
+    q_subckts: &Query<&Subckt>,
+    models: &ModelLibrary,
+    step: &[(String, f64)],
+) -> String {
     let mut netlist = String::from("Netlist Created by Sircke\n");
-    for (d, params, se, spdid) in q_devices.iter() {
-        let Some(spdid) = spice_id(se, spdid) else {
-            let s = format!(
-                "netlist failed: this device did not have a device type {:?}, {:?}",
-                se, spdid
-            );
+    // models used by any device, including those inside subcircuits
+    let params = q_devices
+        .iter()
+        .filter_map(|(_, params, se, _)| Some(params.spice_param(se.get_dtype()?)))
+        .chain(q_subckts.iter().map(|s| s.get_body().to_owned()))
+        .collect::<Vec<_>>();
+    for line in models.netlist_lines(params.iter().map(String::as_str)) {
+        netlist.push_str(&line);
+        netlist.push_str("\n");
+    }
+    // directives in reading order: top to bottom, left to right
+    let mut directives = q_directives.iter().collect::<Vec<_>>();
+    directives.sort_by(|(_, a), (_, b)| {
+        (-a.translation.y, a.translation.x)
+            .partial_cmp(&(-b.translation.y, b.translation.x))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (directive, _) in directives {
+        for line in directive.get_text().lines() {
+            netlist.push_str(line);
+            netlist.push_str("\n");
+        }
+    }
+    // each subcircuit definition once, by name
+    let subckts = q_subckts
+        .iter()
+        .map(|s| (s.get_name(), s))
+        .collect::<BTreeMap<_, _>>();
+    for subckt in subckts.values() {
+        netlist.push_str(&subckt.definition());
+    }
+    let mut devices = q_devices.iter().collect::<Vec<_>>();
+    devices.sort_by_key(|(_, _, se, _)| se.get_dtype() == Some(&SpDeviceType::K));
+    for (d, params, se, spdid) in devices {
+        // value of this device in the current step, if stepped
+        let stepped = se
+            .spice_name(spdid)
+            .and_then(|name| step.iter().find(|(n, _)| *n == name))
+            .and_then(|(_, value)| params.with_value(se.get_dtype()?, *value));
+        let Some(line) = device_line(d, stepped.as_ref().unwrap_or(params), se, spdid, q_nid)
+        else {
             error!("netlist failed: this device did not have a device type");
             continue;
         };
-        // push device id
-        netlist.push_str(&spdid);
-        netlist.push_str(" ");
-        // push net id for each port
-        for port in d.get_ports().iter() {
-            let net = q_nid.get(*port).unwrap().get_id();
-            netlist.push_str(net);
-            netlist.push_str(" ");
-        }
-        // followed by device value (e.g. resistance, voltage) and params if any
-        netlist.push_str(&params.spice_param());
+        netlist.push_str(&line);
         netlist.push_str("\n");
     }
-    if netlist == String::from("Netlist Created by Sircke\n") {
+    if q_devices.is_empty() {
         // empty netlist
-        netlist.push_str("V_0 0 n1 0"); // give it something so spice doesnt hang
+        netlist.push_str("V_0 0 n1 0\n"); // give it something so spice doesnt hang
     }
-    fs::write("out/netlist.cir", netlist.as_bytes()).expect("Unable to write file");
+    netlist
 }
 
-fn spice_id(se: &SchematicElement, spdid: &SpDeviceId) -> Option<String> {
-    se.get_dtype()
-        .map(|x| x.prefix().to_owned() + spdid.get_id())
+/// `netlist` with a single analysis card, and that analysis
+/// analysis cards among the directives take precedence over `cmd`, the card of `analysis`,
+/// preferring one of the same analysis. only one is kept, as all would be run into one plot
+fn with_analysis(netlist: &str, analysis: &'static str, cmd: &str) -> (&'static str, String) {
+    let mut split = split_analyses(netlist);
+    if split.is_empty() {
+        return (analysis, format!("{}.{}\n", netlist, cmd));
+    }
+    let k = split.iter().position(|(a, _)| *a == analysis).unwrap_or(0);
+    split.swap_remove(k)
 }
 
 fn pksim(
     mut e_console_rgstr: EventWriter<PrintConsoleLine>,
-    q_ports: Query<(Entity, &NetId), With<Port>>,
-    q_labeled_ports: Query<(Entity, &PortLabel), With<Port>>,
+    q_devices: Query<(&DevicePorts, &DeviceParams, &SchematicElement, &SpDeviceId)>,
+    q_nid: Query<&NetId>,
+    q_directives: Query<(&Directive, &Transform)>,
+    q_subckts: Query<&Subckt>,
+    q_stepped: Query<(
+        &DeviceParams,
+        &SchematicElement,
+        &SpDeviceId,
+        Option<&Tolerance>,
+    )>,
+    models: Res<ModelLibrary>,
     spres: Res<SPRes>,
-    mut commands: Commands,
     keys: Res<ButtonInput<KeyCode>>,
-    sim_achz: Res<SimAcHz>,
+    sim_ac: Res<SimAc>,
+    sim_tran: Res<SimTran>,
+    sim_dc: Res<SimDc>,
+    step_config: Res<StepConfig>,
+    probes: Res<Probes>,
+    erc: Res<Erc>,
+    mut simrun: ResMut<SimRun>,
 ) {
-    // clear all port labels
-    for (e, p) in q_labeled_ports.iter() {
-        commands.entity(p.get_label_entity()).despawn();
-        commands.entity(e).remove::<PortLabel>();
-    }
-
-    // run sim
-    e_console_rgstr.send(PrintConsoleLine::new(
-        "source out/netlist.cir".to_owned(),
-        Color32::GRAY,
-    ));
-    spres.command("source out/netlist.cir");
+    if simrun.is_running() || spres.backend().is_running() {
+        e_console_rgstr.send(PrintConsoleLine::new(
+            "a simulation is already running".to_owned(),
+            Color32::RED,
+        ));
+        return;
+    }
+    if erc.blocks() {
+        e_console_rgstr.send(PrintConsoleLine::new(
+            format!("simulation blocked by {} erc errors", erc.errors()),
+            Color32::RED,
+        ));
+        return;
+    }
+    let analysis;
     let cmd;
-    if keys.pressed(KeyCode::ControlLeft) {
-        // run acop
-        let hz = **sim_achz;
-        // ac step_type step_size start end (start and end are inclusive)
-        // since dcop, start and end can be same
-        cmd = format!("ac lin 0 {} {}", hz, hz);
+    if keys.pressed(KeyCode::AltLeft) {
+        // run dc sweep, results go to dc sweep viewer
+        let Some(dc) = sim_dc.command() else {
+            e_console_rgstr.send(PrintConsoleLine::new(
+                "dc sweep: no source selected".to_owned(),
+                Color32::RED,
+            ));
+            return;
+        };
+        analysis = "dc";
+        cmd = dc;
+    } else if keys.pressed(KeyCode::ShiftLeft) {
+        // run transient, results go to waveform viewer
+        analysis = "tran";
+        cmd = sim_tran.command();
+    } else if keys.pressed(KeyCode::ControlLeft) {
+        // run ac sweep, results go to bode plot
+        // a sweep with start and end at the same frequency is an ac operating point
+        analysis = "ac";
+        cmd = sim_ac.command();
     } else {
         // run dcop
+        analysis = "op";
         cmd = "op".to_owned();
     }
-    // send to ngspice
-    spres.command(&cmd);
-    // send to console for posterity
-    e_console_rgstr.send(PrintConsoleLine::new(cmd, Color32::GRAY));
-
-    // get results, display as port labels
-    if let Some(pkvecvaluesall) = spres.get_spm().vecvals_pop() {
-        let mut results = HashMap::<String, String>::new();
-        for v in pkvecvaluesall.vecsa {
-            if v.is_complex {
-                let cv = num::complex::Complex::new(v.creal, v.cimag);
-                results.insert(v.name, format! {"{:+.2e}", cv});
-            } else {
-                results.insert(v.name, format! {"{:+.2e}", v.creal});
-            }
+
+    // values of the stepped devices for each step, if any
+    let devices = q_stepped.iter().filter_map(|(params, se, spdid, tol)| {
+        let value = params.value(se.get_dtype()?);
+        Some((se.spice_name(spdid)?, value, tol.copied()))
+    });
+    let steps = match step_config.steps(devices) {
+        Ok(steps) => steps,
+        Err(e) => {
+            e_console_rgstr.send(PrintConsoleLine::new(e, Color32::RED));
+            return;
+        }
+    };
+
+    // analysis goes into the netlist as a dot command so that it can be run with bg_run
+    let write_netlist = |path: &str, step: &[(String, f64)]| {
+        let netlist = netlist(&q_devices, &q_nid, &q_directives, &q_subckts, &models, step);
+        let (analysis, mut netlist) = with_analysis(&netlist, analysis, &cmd);
+        let card = netlist.lines().last().unwrap_or_default().to_owned();
+        // probed device currents and device operating points,
+        // after the analysis card which the native solver looks for first
+        let mut saves = probes.save_vectors();
+        if analysis == "op" {
+            saves.extend(op_save_vectors(
+                q_devices.iter().map(|(_, _, se, spdid)| (se, spdid)),
+            ));
         }
-        for (ent, netid) in q_ports.iter() {
-            let val = results.get(netid.get_id()).unwrap();
-            insert_new_label(ent, &mut commands, val.clone());
+        if !saves.is_empty() {
+            netlist.push_str(&format!(".save all {}\n", saves.join(" ")));
         }
+        netlist.push_str(".end\n");
+        fs::write(path, netlist.as_bytes()).expect("Unable to write file");
+        (analysis, card)
+    };
+    let configured = analysis;
+    let ((analysis, card), path) = if steps.is_empty() {
+        let run = write_netlist("out/netlist.cir", &[]);
+        simrun.start(run.0);
+        (run, "out/netlist.cir".to_owned())
+    } else {
+        // every step has the same directives, and so the same analysis card
+        let mut run = (configured, String::new());
+        let runs = steps
+            .into_iter()
+            .enumerate()
+            .map(|(k, step)| {
+                let path = format!("out/netlist_step{}.cir", k);
+                run = write_netlist(&path, &step);
+                (step, path)
+            })
+            .collect();
+        simrun.start_steps(run.0, runs);
+        (run, simrun.next_step().unwrap_or_default())
+    };
+    // the plots keep the card they were run with, the settings may change meanwhile
+    simrun.set_card(card);
+    if analysis != configured {
+        e_console_rgstr.send(PrintConsoleLine::new(
+            format!(
+                "running the .{} directive instead of .{}",
+                analysis, configured
+            ),
+            Color32::GRAY,
+        ));
+    }
+
+    // run sim in background, results are collected by simrun once done
+    // simrun sources and runs the remaining steps one after the other
+    for cmd in [format!("source {}", path), "bg_run".to_owned()] {
+        spres.command(&cmd);
+        // send to console for posterity
+        e_console_rgstr.send(PrintConsoleLine::new(cmd, Color32::GRAY));
     }
 }
 
-fn insert_new_label(parent: Entity, commands: &mut Commands, val: String) {
-    let label_entity = commands
-        .spawn(SchematicLabelBundle::new(parent, IVec2::splat(0), val))
-        .id();
-    commands.entity(parent).insert(PortLabel::new(label_entity));
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::schematic::electrical::{
+        models::SpiceModel,
+        spid::{SchType, SpDeviceType, SpType},
+        subckt::SubcktBuilder,
+    };
+
+    /// spawn a device with one port per net
+    fn spawn_device(world: &mut World, dtype: SpDeviceType, id: &str, nets: &[&str], param: &str) {
+        let ports = nets
+            .iter()
+            .map(|net| world.spawn(NetId::new(net.to_string())).id())
+            .collect();
+        world.spawn((
+            DevicePorts::new(ports),
+            DeviceParams::Raw(param.to_owned()),
+            SchematicElement {
+                schtype: SchType::Spice(SpType::Device(dtype)),
+            },
+            SpDeviceId::new(id.to_owned()),
+        ));
+    }
+
+    fn netlist_of(world: &mut World) -> String {
+        world.run_system_once(
+            |q_devices: Query<(&DevicePorts, &DeviceParams, &SchematicElement, &SpDeviceId)>,
+             q_nid: Query<&NetId>,
+             q_directives: Query<(&Directive, &Transform)>,
+             q_subckts: Query<&Subckt>,
+             models: Res<ModelLibrary>| {
+                netlist(&q_devices, &q_nid, &q_directives, &q_subckts, &models, &[])
+            },
+        )
+    }
+
+    #[test]
+    fn test_netlist_devices() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::V, "1", &["net_1", "0"], "3");
+        spawn_device(&mut world, SpDeviceType::R, "1", &["net_1", "net_2"], "1k");
+        spawn_device(&mut world, SpDeviceType::Gnd, "GND1", &["0"], "0");
+
+        let netlist = netlist_of(&mut world);
+        let mut lines = netlist.lines();
+        assert_eq!(lines.next(), Some("Netlist Created by Sircke"));
+        let mut devices = lines.map(|l| l.trim_end()).collect::<Vec<_>>();
+        devices.sort();
+        assert_eq!(devices, ["R1 net_1 net_2 1k", "V1 net_1 0 3", "VGND1 0 0"]);
+    }
+
+    #[test]
+    fn test_netlist_step() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::R, "1", &["a", "0"], "1k");
+        spawn_device(&mut world, SpDeviceType::R, "2", &["a", "0"], "1k");
+
+        let netlist = world.run_system_once(
+            |q_devices: Query<(&DevicePorts, &DeviceParams, &SchematicElement, &SpDeviceId)>,
+             q_nid: Query<&NetId>,
+             q_directives: Query<(&Directive, &Transform)>,
+             q_subckts: Query<&Subckt>,
+             models: Res<ModelLibrary>| {
+                let step = [("R2".to_owned(), 4.7e3)];
+                netlist(
+                    &q_devices,
+                    &q_nid,
+                    &q_directives,
+                    &q_subckts,
+                    &models,
+                    &step,
+                )
+            },
+        );
+        // only the stepped device is rewritten
+        assert!(netlist.contains("R1 a 0 1k"));
+        assert!(netlist.contains("R2 a 0 4.7e3"));
+    }
+
+    #[test]
+    fn test_netlist_models() {
+        let mut world = World::new();
+        let mut models = ModelLibrary::default();
+        models.insert(SpiceModel::parse(".model DMOD D(is=1e-14)").unwrap());
+        models.insert(SpiceModel::parse(".model QMOD NPN").unwrap());
+        world.insert_resource(models);
+        spawn_device(&mut world, SpDeviceType::D, "1", &["a", "0"], "dmod");
+
+        let netlist = netlist_of(&mut world);
+        // only referenced models are emitted, before the devices
+        assert_eq!(netlist.lines().nth(1), Some(".model DMOD D(is=1e-14)"));
+        assert!(!netlist.contains("QMOD"));
+    }
+
+    #[test]
+    fn test_netlist_directives() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::R, "1", &["a", "0"], "{rload}");
+        let label = world.spawn_empty().id();
+        world.spawn((
+            Directive::new(".temp 85".to_owned(), label),
+            Transform::from_xyz(0.0, -5.0, 0.0),
+        ));
+        world.spawn((
+            Directive::new(".param rload=1k\n.options gmin=1e-12".to_owned(), label),
+            Transform::from_xyz(10.0, 5.0, 0.0),
+        ));
+
+        let netlist = netlist_of(&mut world);
+        // verbatim, top to bottom
+        let lines = netlist.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[1..4],
+            [".param rload=1k", ".options gmin=1e-12", ".temp 85"]
+        );
+        assert_eq!(lines[4].trim_end(), "R1 a 0 {rload}");
+    }
+
+    #[test]
+    fn test_netlist_analysis() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::R, "1", &["a", "0"], "1k");
+        let netlist = netlist_of(&mut world);
+        // the configured analysis, without directives
+        let (analysis, op) = with_analysis(&netlist, "op", "op");
+        assert_eq!(analysis, "op");
+        assert_eq!(op, format!("{}.op\n", netlist));
+
+        let label = world.spawn_empty().id();
+        world.spawn((
+            Directive::new(".tran 1u 1m\n.ac dec 10 1 1meg".to_owned(), label),
+            Transform::default(),
+        ));
+        let netlist = netlist_of(&mut world);
+        // a directive of another analysis replaces the configured one
+        let (analysis, tran) = with_analysis(&netlist, "op", "op");
+        assert_eq!(analysis, "tran");
+        assert!(tran.ends_with("R1 a 0 1k\n.tran 1u 1m\n"));
+        assert!(!tran.contains(".op") && !tran.contains(".ac"));
+        // one of the same analysis is preferred
+        let (analysis, ac) = with_analysis(&netlist, "ac", "ac dec 1 1 1");
+        assert_eq!(analysis, "ac");
+        assert!(ac.ends_with(".ac dec 10 1 1meg\n"));
+        assert!(!ac.contains(".tran"));
+    }
+
+    fn build_subckt(world: &mut World) -> Result<Subckt, String> {
+        world.run_system_once(|builder: SubcktBuilder| {
+            let pins = [("a", "in"), ("b", "out")].map(|(n, p)| (n.to_owned(), p.to_owned()));
+            builder.build("buf", &pins)
+        })
+    }
+
+    #[test]
+    fn test_netlist_subckts() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::R, "1", &["a", "b"], "1k");
+        spawn_device(&mut world, SpDeviceType::D, "1", &["b", "a"], "DMOD");
+        let subckt = build_subckt(&mut world).unwrap();
+
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        for id in ["1", "2"] {
+            spawn_device(&mut world, SpDeviceType::X, id, &["n1", "n2"], "buf");
+        }
+        let mut q = world.query_filtered::<Entity, With<DevicePorts>>();
+        for e in q.iter(&world).collect::<Vec<_>>() {
+            world.entity_mut(e).insert(subckt.clone());
+        }
+        let netlist = netlist_of(&mut world);
+        // one definition for both instances, and the model used inside of it
+        assert_eq!(netlist.matches(".subckt buf in out").count(), 1);
+        assert!(netlist.contains("R1 in out 1k"));
+        assert_eq!(netlist.matches(".model DMOD").count(), 1);
+        assert!(netlist.contains("X1 n1 n2 buf"));
+        assert!(netlist.contains("X2 n1 n2 buf"));
+
+        // a definition containing instances of itself is rejected
+        assert!(build_subckt(&mut world).is_err());
+    }
+
+    #[test]
+    fn test_netlist_coupling() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        spawn_device(&mut world, SpDeviceType::K, "1", &[], "L1 L2 0.99");
+        spawn_device(&mut world, SpDeviceType::L, "1", &["a", "0"], "1m");
+        spawn_device(&mut world, SpDeviceType::L, "2", &["b", "0"], "4m");
+        let netlist = netlist_of(&mut world);
+        let lines = netlist.lines().map(str::trim_end).collect::<Vec<_>>();
+        assert_eq!(lines.last(), Some(&"K1 L1 L2 0.99"));
+        assert!(lines.contains(&"L1 a 0 1m"));
+    }
+
+    #[test]
+    fn test_netlist_empty() {
+        let mut world = World::new();
+        world.init_resource::<ModelLibrary>();
+        let netlist = netlist_of(&mut world);
+        // spice needs at least one device to not hang
+        assert_eq!(netlist.lines().count(), 2);
+    }
 }
@@ -39,26 +39,32 @@ pub fn create_preview_lineseg(
     src_pt: Vec3,
     dst_pt: Vec3,
 ) {
-    fn spawn_vertex(
-        commands: &mut Commands,
-        eres: &Res<ElementsRes>,
-        lineseg_entity: Entity,
-        vertex_entity: Entity,
-        pt: Vec3,
-    ) {
-        commands
-            .entity(vertex_entity)
-            .insert((VertexBundle::new(lineseg_entity, eres, pt), Preview));
+    for e in create_lineseg(commands, eres, src_pt, dst_pt) {
+        commands.entity(e).insert(Preview);
     }
+}
+
+/// creates a lineseg from src to dst, returns the segment and vertex entities
+pub fn create_lineseg(
+    commands: &mut Commands,
+    eres: &Res<ElementsRes>,
+    src_pt: Vec3,
+    dst_pt: Vec3,
+) -> [Entity; 3] {
     // vertex and segments have eachothers entity as reference
     // segment transform with scale zero since start and end are both at same point
     let src_entity = commands.spawn_empty().id();
     let dst_entity = commands.spawn_empty().id();
     let ls = LineSegBundle::new(eres, (src_entity, src_pt), (dst_entity, dst_pt));
-    let lineseg_entity = commands.spawn((ls, Preview)).id();
+    let lineseg_entity = commands.spawn(ls).id();
 
-    spawn_vertex(commands, eres, lineseg_entity, src_entity, src_pt);
-    spawn_vertex(commands, eres, lineseg_entity, dst_entity, dst_pt);
+    commands
+        .entity(src_entity)
+        .insert(VertexBundle::new(lineseg_entity, eres, src_pt));
+    commands
+        .entity(dst_entity)
+        .insert(VertexBundle::new(lineseg_entity, eres, dst_pt));
+    [lineseg_entity, src_entity, dst_entity]
 }
 
 pub struct NetsPlugin;
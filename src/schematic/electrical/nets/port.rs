@@ -20,6 +20,12 @@ pub struct Port {
     offset: IVec2,
 }
 impl Port {
+    pub fn new(parent_device: Entity, offset: IVec2) -> Self {
+        Self {
+            parent_device,
+            offset,
+        }
+    }
     pub fn get_parent(&self) -> Entity {
         self.parent_device
     }
@@ -70,10 +76,7 @@ impl PortBundle {
     pub fn new(deviceid: Entity, offset: IVec2, eres: &ElementsRes) -> Self {
         PortBundle {
             vertex: LineVertex::default(),
-            port: Port {
-                parent_device: deviceid,
-                offset,
-            },
+            port: Port::new(deviceid, offset),
             mat: MaterialMesh2dBundle {
                 mesh: bevy::sprite::Mesh2dHandle(eres.mesh_port.clone()), // TODO create a mesh for port
                 material: eres.mat_dflt.clone(),
@@ -0,0 +1,324 @@
+//! device operating point annotation
+//! an `op` run saves each device's current, power and, for semiconductors, small-signal values
+//! as ngspice `@device[param]` vectors. once done, these are shown in a label below the device's
+//! param label.
+
+use bevy::prelude::*;
+
+use super::{
+    label::SchematicLabelBundle,
+    simrun::SimFinished,
+    spid::{SchType, SpDeviceType, SpType},
+    units::format_eng,
+    SchematicElement, SpDeviceId,
+};
+
+/// whether device operating point labels are shown
+#[derive(Resource)]
+pub struct OpAnnotation {
+    pub show: bool,
+}
+
+impl Default for OpAnnotation {
+    fn default() -> Self {
+        Self { show: true }
+    }
+}
+
+/// operating point label of a device
+#[derive(Component)]
+pub struct OpLabel {
+    label: Entity,
+}
+
+/// parameters of `dtype` saved for the annotation, other than branch currents
+fn op_params(dtype: &SpDeviceType) -> &'static [&'static str] {
+    match dtype {
+        SpDeviceType::R => &["i", "p"],
+        SpDeviceType::C => &["i", "p"],
+        SpDeviceType::V | SpDeviceType::L => &["p"],
+        SpDeviceType::I => &["current", "p"],
+        SpDeviceType::D => &["id", "p", "gd"],
+        SpDeviceType::Q => &["ic", "ib", "p", "gm", "vbe", "vbc"],
+        SpDeviceType::M => &["id", "p", "gm", "vth", "vgs", "vds"],
+        SpDeviceType::F | SpDeviceType::G | SpDeviceType::B | SpDeviceType::S | SpDeviceType::W => {
+            &["i"]
+        }
+        SpDeviceType::E
+        | SpDeviceType::H
+        | SpDeviceType::K
+        | SpDeviceType::T
+        | SpDeviceType::Gnd
+        | SpDeviceType::X => &[],
+    }
+}
+
+/// `@device[param]` vector name, e.g. `@r1[i]`
+fn param_vector(name: &str, param: &str) -> String {
+    format!("@{}[{}]", name.to_lowercase(), param)
+}
+
+/// device `(type, spice name)` of a schematic element, if it is a device
+fn device_of<'a>(
+    se: &'a SchematicElement,
+    spdid: &SpDeviceId,
+) -> Option<(&'a SpDeviceType, String)> {
+    match se.get_schtype() {
+        SchType::Spice(SpType::Device(dtype)) => Some((dtype, se.spice_name(spdid)?)),
+        _ => None,
+    }
+}
+
+/// vectors to `.save` in an `op` run so that every device can be annotated
+pub fn op_save_vectors<'a>(
+    devices: impl Iterator<Item = (&'a SchematicElement, &'a SpDeviceId)>,
+) -> Vec<String> {
+    devices
+        .filter_map(|(se, spdid)| device_of(se, spdid))
+        .flat_map(|(dtype, name)| {
+            op_params(dtype)
+                .iter()
+                .map(move |param| param_vector(&name, param))
+        })
+        .collect()
+}
+
+/// label text for device `name` of type `dtype`, given a lookup of operating point vectors
+fn op_text(dtype: &SpDeviceType, name: &str, get: impl Fn(&str) -> Option<f64>) -> Option<String> {
+    let param = |p: &str| get(&param_vector(name, p));
+    let (values, region) = match dtype {
+        SpDeviceType::V | SpDeviceType::L => (
+            vec![
+                ("i", get(&format!("{}#branch", name.to_lowercase()))),
+                ("p", param("p")),
+            ],
+            None,
+        ),
+        SpDeviceType::E | SpDeviceType::H => (
+            vec![("i", get(&format!("{}#branch", name.to_lowercase())))],
+            None,
+        ),
+        SpDeviceType::F | SpDeviceType::G | SpDeviceType::B | SpDeviceType::S | SpDeviceType::W => {
+            (vec![("i", param("i"))], None)
+        }
+        SpDeviceType::I => (vec![("i", param("current")), ("p", param("p"))], None),
+        SpDeviceType::R | SpDeviceType::C => (vec![("i", param("i")), ("p", param("p"))], None),
+        SpDeviceType::D => (
+            vec![("id", param("id")), ("p", param("p")), ("gd", param("gd"))],
+            None,
+        ),
+        SpDeviceType::Q => (
+            vec![
+                ("ic", param("ic")),
+                ("ib", param("ib")),
+                ("p", param("p")),
+                ("gm", param("gm")),
+            ],
+            match (param("ic"), param("vbe"), param("vbc")) {
+                (Some(ic), Some(vbe), Some(vbc)) => Some(bjt_region(ic, vbe, vbc)),
+                _ => None,
+            },
+        ),
+        SpDeviceType::M => (
+            vec![
+                ("id", param("id")),
+                ("p", param("p")),
+                ("gm", param("gm")),
+                ("vth", param("vth")),
+            ],
+            match (param("id"), param("vgs"), param("vds"), param("vth")) {
+                (Some(id), Some(vgs), Some(vds), Some(vth)) => Some(mos_region(id, vgs, vds, vth)),
+                _ => None,
+            },
+        ),
+        SpDeviceType::Gnd | SpDeviceType::X | SpDeviceType::K | SpDeviceType::T => (vec![], None),
+    };
+    let lines = values
+        .into_iter()
+        .filter_map(|(label, value)| {
+            Some(format!("{}={}", label, format_eng(value?, unit_of(label))))
+        })
+        .chain(region.map(str::to_owned))
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// unit of an annotated value, by its label
+fn unit_of(label: &str) -> &'static str {
+    match label {
+        "p" => "W",
+        "gm" | "gd" => "S",
+        "vth" => "V",
+        _ => "A",
+    }
+}
+
+/// junction voltage above which a pn junction is taken to be conducting
+const VJ_ON: f64 = 0.5;
+
+/// region of a bjt, polarity taken from the sign of the collector current
+fn bjt_region(ic: f64, vbe: f64, vbc: f64) -> &'static str {
+    let pol = if ic < 0.0 { -1.0 } else { 1.0 };
+    match (pol * vbe > VJ_ON, pol * vbc > VJ_ON) {
+        (true, false) => "active",
+        (true, true) => "saturation",
+        (false, true) => "reverse",
+        (false, false) => "cutoff",
+    }
+}
+
+/// region of a mosfet, polarity taken from the sign of the drain current
+fn mos_region(id: f64, vgs: f64, vds: f64, vth: f64) -> &'static str {
+    let pol = if id < 0.0 { -1.0 } else { 1.0 };
+    let vov = pol * vgs - vth.abs();
+    if vov <= 0.0 {
+        "cutoff"
+    } else if pol * vds < vov {
+        "triode"
+    } else {
+        "saturation"
+    }
+}
+
+/// replace device operating point labels with the results of a finished `op`
+pub fn annotate_devices(
+    mut e_finished: EventReader<SimFinished>,
+    q_devices: Query<(Entity, &SchematicElement, &SpDeviceId)>,
+    q_labeled: Query<(Entity, &OpLabel)>,
+    annotation: Res<OpAnnotation>,
+    mut commands: Commands,
+) {
+    for ev in e_finished.read() {
+        let plot = ev.get_plot();
+        // annotations are stale as soon as any other analysis ran
+        for (e, l) in q_labeled.iter() {
+            commands.entity(l.label).despawn();
+            commands.entity(e).remove::<OpLabel>();
+        }
+        if plot.get_analysis() != "op" || !plot.is_single_point() || ev.get_plots().len() > 1 {
+            continue;
+        }
+        let get = |v: &str| plot.get(v).map(|v| v.real()[0]);
+        for (e, se, spdid) in q_devices.iter() {
+            let Some((dtype, name)) = device_of(se, spdid) else {
+                continue;
+            };
+            let Some(text) = op_text(dtype, &name, get) else {
+                continue;
+            };
+            let label = commands
+                .spawn(SchematicLabelBundle::new(e, IVec2::new(1, -1), text))
+                .insert(visibility(annotation.show))
+                .id();
+            commands.entity(e).insert(OpLabel { label });
+        }
+    }
+}
+
+fn visibility(show: bool) -> Visibility {
+    if show {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+/// apply the show/hide toggle
+fn toggle_labels(
+    annotation: Res<OpAnnotation>,
+    q_labeled: Query<&OpLabel>,
+    mut q_vis: Query<&mut Visibility>,
+) {
+    for l in q_labeled.iter() {
+        if let Ok(mut vis) = q_vis.get_mut(l.label) {
+            *vis = visibility(annotation.show);
+        }
+    }
+}
+
+pub struct OpPointPlugin;
+
+impl Plugin for OpPointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpAnnotation>();
+        app.add_systems(
+            Update,
+            toggle_labels.run_if(resource_changed::<OpAnnotation>),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_op_text() {
+        let vals = HashMap::from([
+            ("v1#branch", -1e-3),
+            ("@v1[p]", -3e-3),
+            ("@r1[i]", 1e-3),
+            ("@r1[p]", 1e-3),
+            ("@c1[i]", 0.0),
+            ("@c1[p]", 0.0),
+            ("l1#branch", 2e-3),
+            ("@l1[p]", 0.0),
+            ("@m1[id]", 2e-4),
+            ("@m1[gm]", 1e-3),
+            ("@m1[vth]", 0.7),
+            ("@m1[vgs]", 1.2),
+            ("@m1[vds]", 3.0),
+        ]);
+        let get = |v: &str| vals.get(v).copied();
+        assert_eq!(
+            op_text(&SpDeviceType::V, "V1", get).as_deref(),
+            Some("i=-1 mA\np=-3 mW")
+        );
+        assert_eq!(
+            op_text(&SpDeviceType::R, "R1", get).as_deref(),
+            Some("i=1 mA\np=1 mW")
+        );
+        assert_eq!(
+            op_text(&SpDeviceType::M, "M1", get).as_deref(),
+            Some("id=200 µA\ngm=1 mS\nvth=700 mV\nsaturation")
+        );
+        assert_eq!(
+            op_text(&SpDeviceType::C, "C1", get).as_deref(),
+            Some("i=0 A\np=0 W")
+        );
+        assert_eq!(
+            op_text(&SpDeviceType::L, "L1", get).as_deref(),
+            Some("i=2 mA\np=0 W")
+        );
+    }
+
+    #[test]
+    fn test_regions() {
+        assert_eq!(mos_region(0.0, 0.3, 1.0, 0.7), "cutoff");
+        assert_eq!(mos_region(1e-3, 2.0, 0.1, 0.7), "triode");
+        // pmos
+        assert_eq!(mos_region(-1e-3, -2.0, -3.0, -0.7), "saturation");
+        assert_eq!(bjt_region(1e-3, 0.7, -2.0), "active");
+        assert_eq!(bjt_region(-1e-3, -0.7, -0.7), "saturation");
+        assert_eq!(bjt_region(0.0, 0.0, -5.0), "cutoff");
+    }
+
+    #[test]
+    fn test_op_save_vectors() {
+        let r = SchematicElement {
+            schtype: SchType::Spice(SpType::Device(SpDeviceType::R)),
+        };
+        let id = SpDeviceId::new("1".to_owned());
+        assert_eq!(
+            op_save_vectors([(&r, &id)].into_iter()),
+            ["@r1[i]", "@r1[p]"]
+        );
+        // every value read for the label is saved, branch currents are saved anyway
+        let l = SchematicElement {
+            schtype: SchType::Spice(SpType::Device(SpDeviceType::L)),
+        };
+        assert_eq!(op_save_vectors([(&l, &id)].into_iter()), ["@l1[p]"]);
+    }
+}
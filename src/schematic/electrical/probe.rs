@@ -0,0 +1,163 @@
+//! probes: nets and devices whose voltage or current is plotted after each simulation
+//! a probe is a marker on the probed wire or device, so that it is kept with the schematic and
+//! follows renamed nets and devices. the vectors to plot are collected once per frame.
+
+use bevy::prelude::*;
+
+use super::{
+    spid::{SchType, SpDeviceType, SpType},
+    NetId, SchematicElement, SpDeviceId,
+};
+
+/// marks a wire or device as probed
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Probe;
+
+/// a probed vector: label shown in the plots and name of the simulation vector
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbeTrace {
+    /// e.g. `v(net_1)` or `i(r1)`
+    label: String,
+    /// e.g. `net_1`, `v1#branch` or `@r1[i]`
+    vector: String,
+}
+
+impl ProbeTrace {
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+    /// device currents other than branch currents are only kept by ngspice if saved explicitly
+    pub fn needs_save(&self) -> bool {
+        self.vector.starts_with('@')
+    }
+}
+
+/// vector probed by clicking an element: net voltage for wires, current for devices
+/// None for elements without a meaningful vector, e.g. ground or subcircuits
+pub fn probe_trace(
+    se: &SchematicElement,
+    spdid: Option<&SpDeviceId>,
+    netid: Option<&NetId>,
+) -> Option<ProbeTrace> {
+    match se.get_schtype() {
+        SchType::Spice(SpType::Net) => {
+            let net = netid?.get_id();
+            Some(ProbeTrace {
+                label: format!("v({})", net),
+                vector: net.to_owned(),
+            })
+        }
+        SchType::Spice(SpType::Device(dtype)) => {
+            let name = se.spice_name(spdid?)?.to_lowercase();
+            // ngspice keeps branch currents of voltage sources and inductors,
+            // other currents are device parameters
+            let vector = match dtype {
+                SpDeviceType::Gnd | SpDeviceType::X | SpDeviceType::K | SpDeviceType::T => {
+                    return None
+                }
+                SpDeviceType::V | SpDeviceType::L | SpDeviceType::E | SpDeviceType::H => {
+                    format!("{}#branch", name)
+                }
+                SpDeviceType::R
+                | SpDeviceType::C
+                | SpDeviceType::F
+                | SpDeviceType::G
+                | SpDeviceType::B
+                | SpDeviceType::S
+                | SpDeviceType::W => format!("@{}[i]", name),
+                SpDeviceType::I => format!("@{}[current]", name),
+                SpDeviceType::D | SpDeviceType::M => format!("@{}[id]", name),
+                SpDeviceType::Q => format!("@{}[ic]", name),
+            };
+            Some(ProbeTrace {
+                label: format!("i({})", name),
+                vector,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// all probed vectors, collected from the [`Probe`] markers every frame
+#[derive(Resource, Default)]
+pub struct Probes {
+    traces: Vec<ProbeTrace>,
+}
+
+impl Probes {
+    /// label of the probe on `vector`, if probed
+    pub fn label_of(&self, vector: &str) -> Option<&str> {
+        self.traces
+            .iter()
+            .find(|t| t.vector == vector)
+            .map(|t| t.label.as_str())
+    }
+    /// probed device currents, which have to be saved along with all default vectors
+    pub fn save_vectors(&self) -> Vec<String> {
+        self.traces
+            .iter()
+            .filter(|t| t.needs_save())
+            .map(|t| t.vector.clone())
+            .collect()
+    }
+}
+
+fn collect_probes(
+    q: Query<(&SchematicElement, Option<&SpDeviceId>, Option<&NetId>), With<Probe>>,
+    mut probes: ResMut<Probes>,
+) {
+    let mut traces = q
+        .iter()
+        .filter_map(|(se, spdid, netid)| probe_trace(se, spdid, netid))
+        .collect::<Vec<_>>();
+    traces.sort_by(|a, b| a.label.cmp(&b.label));
+    // several segments of one wire probe the same net
+    traces.dedup();
+    if probes.traces != traces {
+        probes.traces = traces;
+    }
+}
+
+pub struct ProbePlugin;
+
+impl Plugin for ProbePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Probe>();
+        app.init_resource::<Probes>();
+        app.add_systems(PostUpdate, collect_probes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_trace() {
+        let net = SchematicElement {
+            schtype: SchType::Spice(SpType::Net),
+        };
+        let trace = probe_trace(&net, None, Some(&NetId::new("net_3".to_owned()))).unwrap();
+        assert_eq!(trace.get_label(), "v(net_3)");
+        assert_eq!(trace.vector, "net_3");
+        assert!(!trace.needs_save());
+
+        let device = |dtype| SchematicElement {
+            schtype: SchType::Spice(SpType::Device(dtype)),
+        };
+        let id = SpDeviceId::new("1".to_owned());
+        let v = probe_trace(&device(SpDeviceType::V), Some(&id), None).unwrap();
+        assert_eq!(v.get_label(), "i(v1)");
+        assert_eq!(v.vector, "v1#branch");
+        let r = probe_trace(&device(SpDeviceType::R), Some(&id), None).unwrap();
+        assert_eq!(r.vector, "@r1[i]");
+        assert!(probe_trace(&device(SpDeviceType::Gnd), Some(&id), None).is_none());
+
+        let probes = Probes {
+            traces: vec![v, r, trace],
+        };
+        assert_eq!(probes.save_vectors(), ["@r1[i]"]);
+        assert_eq!(probes.label_of("v1#branch"), Some("i(v1)"));
+    }
+}
@@ -0,0 +1,407 @@
+//! ngspice / spice3 raw file reader
+//! loads results of simulations run elsewhere, e.g. batch ngspice on a server, into
+//! [`SimResults`] as if they had been run in the app. consecutive plots of the same analysis
+//! form one run, the way stepped runs are exported. nets of the schematic whose voltage is in
+//! the file are probed so that they show in the viewers.
+
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+
+use crate::schematic::ui::console::PrintConsoleLine;
+
+use super::{
+    results::{PlotInfo, SimPlot, SimResults, SimVector},
+    spid::{SchType, SpType},
+    NetId, Probe, SchematicElement,
+};
+
+/// request to load the raw file at path
+#[derive(Event)]
+pub struct LoadRawFile {
+    path: PathBuf,
+}
+
+impl LoadRawFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+/// header of one plot
+#[derive(Debug, Default)]
+struct RawHeader {
+    title: String,
+    date: String,
+    plotname: String,
+    complex: bool,
+    points: usize,
+    /// variable names, the first being the scale
+    names: Vec<String>,
+}
+
+/// parse all plots of an ascii or binary raw file, the two forms may be mixed
+pub fn parse_raw(data: &[u8]) -> Result<Vec<SimPlot>, String> {
+    let mut plots = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let (header, binary) = parse_header(data, &mut pos)?;
+        let Some(header) = header else {
+            break;
+        };
+        let values = if binary {
+            read_binary(data, &mut pos, &header)?
+        } else {
+            read_ascii(data, &mut pos, &header)?
+        };
+        plots.push(to_plot(header, values));
+    }
+    if plots.is_empty() {
+        return Err("no plots".to_owned());
+    }
+    Ok(plots)
+}
+
+/// next line starting at pos, without line end
+fn next_line<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    if *pos >= data.len() {
+        return None;
+    }
+    let end = data[*pos..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |i| *pos + i);
+    let line = &data[*pos..end];
+    *pos = end + 1;
+    Some(line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// read a header up to and including `Values:` or `Binary:`
+/// returns None at the end of the file, and whether the values are binary
+fn parse_header(data: &[u8], pos: &mut usize) -> Result<(Option<RawHeader>, bool), String> {
+    let mut header = RawHeader::default();
+    let mut started = false;
+    let mut nvars = 0;
+    while let Some(line) = next_line(data, pos) {
+        let line = String::from_utf8_lossy(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        started = true;
+        let (key, value) = line.split_once(':').unwrap_or((&line, ""));
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "title" => header.title = value.to_owned(),
+            "date" => header.date = value.to_owned(),
+            "plotname" => header.plotname = value.to_owned(),
+            "flags" => header.complex = value.split_whitespace().any(|f| f == "complex"),
+            "no. variables" => {
+                nvars = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of variables: {}", value))?
+            }
+            "no. points" => {
+                header.points = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of points: {}", value))?
+            }
+            "variables" => {
+                for _ in 0..nvars {
+                    let var = next_line(data, pos).ok_or("missing variables")?;
+                    let var = String::from_utf8_lossy(var);
+                    let name = var
+                        .split_whitespace()
+                        .nth(1)
+                        .ok_or_else(|| format!("invalid variable: {}", var.trim()))?;
+                    header.names.push(vector_name(name));
+                }
+            }
+            "values" => return Ok((Some(header), false)),
+            "binary" => return Ok((Some(header), true)),
+            // command, option, dimensions ...
+            _ => (),
+        }
+    }
+    if started {
+        Err("missing values".to_owned())
+    } else {
+        Ok((None, false))
+    }
+}
+
+/// (real, imag) per variable, imag empty for real plots
+type RawValues = Vec<(Vec<f64>, Vec<f64>)>;
+
+fn read_ascii(data: &[u8], pos: &mut usize, header: &RawHeader) -> Result<RawValues, String> {
+    let mut values = vec![(vec![], vec![]); header.names.len()];
+    let mut tokens = vec![];
+    // values take one line per variable, the first line of each point starting with its index
+    let needed = header.points * (header.names.len() + 1);
+    while tokens.len() < needed {
+        let line = next_line(data, pos).ok_or("missing values")?;
+        tokens.extend(
+            String::from_utf8_lossy(line)
+                .split_whitespace()
+                .map(str::to_owned),
+        );
+    }
+    let parse = |t: &str| {
+        t.parse::<f64>()
+            .map_err(|_| format!("invalid value: {}", t))
+    };
+    for point in tokens.chunks(header.names.len() + 1) {
+        for (v, token) in values.iter_mut().zip(point[1..].iter()) {
+            if header.complex {
+                let (re, im) = token.split_once(',').unwrap_or((token, "0"));
+                v.0.push(parse(re)?);
+                v.1.push(parse(im)?);
+            } else {
+                v.0.push(parse(token)?);
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn read_binary(data: &[u8], pos: &mut usize, header: &RawHeader) -> Result<RawValues, String> {
+    let mut values = vec![(vec![], vec![]); header.names.len()];
+    let width = if header.complex { 16 } else { 8 };
+    // sizes come from the file, and may be anything
+    let len = header
+        .points
+        .checked_mul(header.names.len())
+        .and_then(|n| n.checked_mul(width))
+        .ok_or("invalid binary size")?;
+    let end = pos.checked_add(len).ok_or("invalid binary size")?;
+    let bytes = data.get(*pos..end).ok_or("truncated binary values")?;
+    *pos = end;
+    let double = |b: &[u8]| f64::from_le_bytes(b.try_into().unwrap());
+    for (i, value) in bytes.chunks(width).enumerate() {
+        let v = &mut values[i % header.names.len()];
+        v.0.push(double(&value[..8]));
+        if header.complex {
+            v.1.push(double(&value[8..]));
+        }
+    }
+    Ok(values)
+}
+
+/// names as ngspice reports them in memory: `v(out)` is `out`, `i(v1)` is `v1#branch`
+fn vector_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    if let Some(net) = name.strip_prefix("v(").and_then(|n| n.strip_suffix(')')) {
+        if !net.contains(',') {
+            return net.to_owned();
+        }
+    }
+    if let Some(branch) = name.strip_prefix("i(").and_then(|n| n.strip_suffix(')')) {
+        return format!("{}#branch", branch);
+    }
+    name
+}
+
+/// analysis of a plot name, and the stepped values appended on export, e.g. `R1=1e3`
+fn analysis_of(plotname: &str) -> (String, Vec<(String, f64)>) {
+    let mut step = vec![];
+    let mut words = vec![];
+    for word in plotname.split_whitespace() {
+        match word.split_once('=').map(|(n, v)| (n, v.parse::<f64>())) {
+            Some((name, Ok(value))) => step.push((name.to_owned(), value)),
+            _ => words.push(word.to_lowercase()),
+        }
+    }
+    let analysis = match words.first().map(String::as_str) {
+        Some("operating") => "op",
+        Some("dc") => "dc",
+        Some("ac") => "ac",
+        Some("transient") => "tran",
+        _ => words.first().map_or("", String::as_str),
+    };
+    (analysis.to_owned(), step)
+}
+
+fn to_plot(header: RawHeader, values: RawValues) -> SimPlot {
+    let (analysis, step) = analysis_of(&header.plotname);
+    let vecs = header
+        .names
+        .iter()
+        .zip(values)
+        .map(|(name, (real, imag))| {
+            SimVector::new(name.clone(), real, header.complex.then_some(imag))
+        })
+        .collect::<Vec<_>>();
+    // an operating point has no scale
+    let scale = (analysis != "op" && !vecs.is_empty()).then_some(0);
+    let info = PlotInfo {
+        names: header.names,
+        title: header.title,
+        date: header.date,
+        plotname: header.plotname,
+    };
+    SimPlot::from_vectors(&analysis, scale, vecs)
+        .with_step(step)
+        .with_info(Some(info))
+}
+
+/// load raw files requested by [`LoadRawFile`] and probe the nets found in them
+fn load_raw(
+    mut e_load: EventReader<LoadRawFile>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+    mut results: ResMut<SimResults>,
+    q_wires: Query<(Entity, &SchematicElement, &NetId, Has<Probe>)>,
+    mut commands: Commands,
+) {
+    for ev in e_load.read() {
+        let plots = fs::read(&ev.path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| parse_raw(&data));
+        let plots = match plots {
+            Ok(plots) => plots,
+            Err(e) => {
+                e_console_rgstr.send(PrintConsoleLine::new(
+                    format!("load {}: {}", ev.path.display(), e),
+                    Color32::RED,
+                ));
+                continue;
+            }
+        };
+        let names = plots
+            .iter()
+            .flat_map(|p| p.columns().into_iter().map(|v| v.get_name().to_owned()))
+            .collect::<HashSet<_>>();
+        let wires = q_wires
+            .iter()
+            .filter(|(_, se, netid, _)| {
+                matches!(se.get_schtype(), SchType::Spice(SpType::Net))
+                    && names.contains(netid.get_id())
+            })
+            .collect::<Vec<_>>();
+        // one probed segment is enough for a net
+        let mut probed = wires
+            .iter()
+            .filter(|(.., has_probe)| *has_probe)
+            .map(|(_, _, netid, _)| netid.get_id())
+            .collect::<HashSet<_>>();
+        for (e, _, netid, _) in wires.iter() {
+            if probed.insert(netid.get_id()) {
+                commands.entity(*e).insert(Probe);
+            }
+        }
+        let nplots = plots.len();
+        let mut runs: Vec<Vec<SimPlot>> = vec![];
+        for plot in plots {
+            match runs.last_mut() {
+                Some(run) if run[0].get_analysis() == plot.get_analysis() => run.push(plot),
+                _ => runs.push(vec![plot]),
+            }
+        }
+        let analyses = runs
+            .iter()
+            .map(|r| r[0].get_analysis().to_owned())
+            .collect::<Vec<_>>();
+        for run in runs {
+            results.push(run);
+        }
+        e_console_rgstr.send(PrintConsoleLine::new(
+            format!(
+                "loaded {} plots ({}) from {}, probed {} nets",
+                nplots,
+                analyses.join(", "),
+                ev.path.display(),
+                probed.len()
+            ),
+            Color32::GRAY,
+        ));
+    }
+}
+
+pub struct RawFilePlugin;
+
+impl Plugin for RawFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LoadRawFile>();
+        app.add_systems(Update, load_raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "Title: rc
+Date: Thu Jan  1 00:00:00  2026
+Plotname: Transient Analysis
+Flags: real
+No. Variables: 3
+No. Points: 2
+Variables:
+\t0\ttime\ttime
+\t1\tv(out)\tvoltage
+\t2\ti(v1)\tcurrent
+Values:
+ 0\t0.000000000000000e+00
+\t1.000000000000000e+00
+\t-1.000000000000000e-03
+ 1\t1.000000000000000e-03
+\t5.000000000000000e-01
+\t-5.000000000000000e-04
+";
+
+    #[test]
+    fn test_parse_ascii() {
+        let plots = parse_raw(ASCII.as_bytes()).unwrap();
+        assert_eq!(plots.len(), 1);
+        let plot = &plots[0];
+        assert_eq!(plot.get_analysis(), "tran");
+        assert_eq!(plot.scale().unwrap().get_name(), "time");
+        assert_eq!(plot.get("out").unwrap().real(), [1.0, 0.5]);
+        assert_eq!(plot.get("v1#branch").unwrap().real(), [-1e-3, -5e-4]);
+        assert_eq!(plot.get_info().unwrap().title, "rc");
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        let mut data = b"Title: rc
+Plotname: AC Analysis R1=2e3
+Flags: complex
+No. Variables: 2
+No. Points: 1
+Variables:
+\t0\tfrequency\tfrequency
+\t1\tout\tvoltage
+Binary:
+"
+        .to_vec();
+        for v in [10.0f64, 0.0, 0.5, -0.5] {
+            data.extend(v.to_le_bytes());
+        }
+        let plots = parse_raw(&data).unwrap();
+        let plot = &plots[0];
+        assert_eq!(plot.get_analysis(), "ac");
+        assert_eq!(plot.get_step(), [("R1".to_owned(), 2e3)]);
+        assert_eq!(plot.get("out").unwrap().imag(), Some([-0.5].as_slice()));
+
+        data.truncate(data.len() - 8);
+        assert!(parse_raw(&data).is_err());
+
+        let huge = String::from_utf8_lossy(&data)
+            .replace("No. Points: 1", &format!("No. Points: {}", usize::MAX / 2));
+        assert_eq!(
+            parse_raw(huge.as_bytes()).err().as_deref(),
+            Some("invalid binary size")
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_export() {
+        let plots = parse_raw(ASCII.as_bytes()).unwrap();
+        let ascii = super::super::export::to_raw_ascii(&plots);
+        let binary = super::super::export::to_raw_binary(&plots);
+        for data in [ascii.as_bytes(), binary.as_slice()] {
+            let again = parse_raw(data).unwrap();
+            assert_eq!(again[0].get("out").unwrap().real(), [1.0, 0.5]);
+            assert_eq!(again[0].get_analysis(), "tran");
+        }
+    }
+}
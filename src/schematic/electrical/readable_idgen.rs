@@ -14,6 +14,8 @@ use std::collections::HashMap;
 
 use bevy::{prelude::*, reflect::Reflect, utils::hashbrown::HashSet};
 
+use super::spid::SpDeviceType;
+
 #[derive(Resource, Reflect, Default)]
 #[reflect(Resource)]
 pub struct IdTracker {
@@ -23,9 +25,19 @@ pub struct IdTracker {
     r: IdGen,
     l: IdGen,
     c: IdGen,
+    e: IdGen,
+    f: IdGen,
+    g: IdGen,
+    h: IdGen,
+    b: IdGen,
+    k: IdGen,
+    s: IdGen,
+    w: IdGen,
+    t: IdGen,
     d: IdGen,
     q: IdGen,
     m: IdGen,
+    x: IdGen,
 }
 
 impl IdTracker {
@@ -47,6 +59,33 @@ impl IdTracker {
     pub fn new_c_id(&mut self, prefix: &str) -> String {
         self.c.get_id(prefix)
     }
+    pub fn new_e_id(&mut self, prefix: &str) -> String {
+        self.e.get_id(prefix)
+    }
+    pub fn new_f_id(&mut self, prefix: &str) -> String {
+        self.f.get_id(prefix)
+    }
+    pub fn new_g_id(&mut self, prefix: &str) -> String {
+        self.g.get_id(prefix)
+    }
+    pub fn new_h_id(&mut self, prefix: &str) -> String {
+        self.h.get_id(prefix)
+    }
+    pub fn new_b_id(&mut self, prefix: &str) -> String {
+        self.b.get_id(prefix)
+    }
+    pub fn new_k_id(&mut self, prefix: &str) -> String {
+        self.k.get_id(prefix)
+    }
+    pub fn new_s_id(&mut self, prefix: &str) -> String {
+        self.s.get_id(prefix)
+    }
+    pub fn new_w_id(&mut self, prefix: &str) -> String {
+        self.w.get_id(prefix)
+    }
+    pub fn new_t_id(&mut self, prefix: &str) -> String {
+        self.t.get_id(prefix)
+    }
     pub fn new_d_id(&mut self, prefix: &str) -> String {
         self.d.get_id(prefix)
     }
@@ -56,6 +95,38 @@ impl IdTracker {
     pub fn new_m_id(&mut self, prefix: &str) -> String {
         self.m.get_id(prefix)
     }
+    pub fn new_x_id(&mut self, prefix: &str) -> String {
+        self.x.get_id(prefix)
+    }
+    /// register an existing net id, e.g. from an imported netlist
+    /// returns false if the id is already taken
+    pub fn register_net_id(&mut self, id: &str) -> bool {
+        self.nets.register(id)
+    }
+    /// register an existing device id (without its type prefix), e.g. from an imported netlist
+    /// returns false if the id is already taken
+    pub fn register_device_id(&mut self, dtype: &SpDeviceType, id: &str) -> bool {
+        match dtype {
+            SpDeviceType::Gnd | SpDeviceType::V => self.v.register(id),
+            SpDeviceType::I => self.i.register(id),
+            SpDeviceType::R => self.r.register(id),
+            SpDeviceType::L => self.l.register(id),
+            SpDeviceType::C => self.c.register(id),
+            SpDeviceType::E => self.e.register(id),
+            SpDeviceType::F => self.f.register(id),
+            SpDeviceType::G => self.g.register(id),
+            SpDeviceType::H => self.h.register(id),
+            SpDeviceType::B => self.b.register(id),
+            SpDeviceType::K => self.k.register(id),
+            SpDeviceType::S => self.s.register(id),
+            SpDeviceType::W => self.w.register(id),
+            SpDeviceType::T => self.t.register(id),
+            SpDeviceType::D => self.d.register(id),
+            SpDeviceType::Q => self.q.register(id),
+            SpDeviceType::M => self.m.register(id),
+            SpDeviceType::X => self.x.register(id),
+        }
+    }
 }
 
 /// one of these per recognized spice device prefix (r l c v i m q d etc.)
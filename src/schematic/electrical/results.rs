@@ -0,0 +1,260 @@
+//! simulation results store
+//! ngspice calls back with one [`PkVecvaluesall`] per simulation step, which are collected
+//! into a [`SimPlot`] once the analysis is done.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+use paprika::{PkVecinfoall, PkVecvaluesall};
+
+/// a named vector of a simulation result, e.g. `net_1` or `time`
+#[derive(Clone, Debug)]
+pub struct SimVector {
+    name: String,
+    real: Vec<f64>,
+    imag: Option<Vec<f64>>,
+}
+
+impl SimVector {
+    pub fn new(name: String, real: Vec<f64>, imag: Option<Vec<f64>>) -> Self {
+        Self { name, real, imag }
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn real(&self) -> &[f64] {
+        &self.real
+    }
+    pub fn imag(&self) -> Option<&[f64]> {
+        self.imag.as_deref()
+    }
+    pub fn len(&self) -> usize {
+        self.real.len()
+    }
+}
+
+/// description of a plot, sent by ngspice before the first step
+#[derive(Clone, Debug, Default)]
+pub struct PlotInfo {
+    /// circuit title, the first line of the netlist
+    pub title: String,
+    pub date: String,
+    /// type of the plot, e.g. `Transient Analysis`
+    pub plotname: String,
+    /// names of all vectors in ngspice vector order
+    pub names: Vec<String>,
+}
+
+impl PlotInfo {
+    pub fn from_vecinfo(info: &PkVecinfoall) -> Self {
+        let mut vecs = info.vecs.iter().collect::<Vec<_>>();
+        vecs.sort_by_key(|v| v.number);
+        Self {
+            title: info.title.clone(),
+            date: info.date.clone(),
+            plotname: info.stype.clone(),
+            names: vecs.into_iter().map(|v| v.name.clone()).collect(),
+        }
+    }
+}
+
+/// all vectors produced by a single analysis run
+#[derive(Clone, Debug)]
+pub struct SimPlot {
+    /// analysis that produced this plot, e.g. `tran`
+    analysis: String,
+    /// index into `vecs` of the scale vector (e.g. time), if any
+    scale: Option<usize>,
+    vecs: Vec<SimVector>,
+    /// values of the stepped devices that produced this plot, empty if not stepped
+    step: Vec<(String, f64)>,
+    /// description sent by the simulator, if any
+    info: Option<PlotInfo>,
+    /// dot command of the run that produced this plot, None if not known, e.g. if loaded
+    card: Option<String>,
+}
+
+impl SimPlot {
+    /// assemble a plot from the per-step values sent by ngspice
+    pub fn from_vecvals(analysis: &str, steps: Vec<PkVecvaluesall>) -> Self {
+        let mut vecs: Vec<SimVector> = vec![];
+        let mut scale = None;
+        for step in steps {
+            for (i, v) in step.vecsa.into_iter().enumerate() {
+                if vecs.len() <= i {
+                    if v.is_scale {
+                        scale = Some(i);
+                    }
+                    vecs.push(SimVector {
+                        name: v.name,
+                        real: vec![],
+                        imag: v.is_complex.then(Vec::new),
+                    });
+                }
+                let sv = &mut vecs[i];
+                sv.real.push(v.creal);
+                if let Some(imag) = sv.imag.as_mut() {
+                    imag.push(v.cimag);
+                }
+            }
+        }
+        Self {
+            analysis: analysis.to_owned(),
+            scale,
+            vecs,
+            step: vec![],
+            info: None,
+            card: None,
+        }
+    }
+    /// assemble a plot from whole vectors, `scale` being the index of the scale vector if any
+    pub fn from_vectors(analysis: &str, scale: Option<usize>, vecs: Vec<SimVector>) -> Self {
+        Self {
+            analysis: analysis.to_owned(),
+            scale,
+            vecs,
+            step: vec![],
+            info: None,
+            card: None,
+        }
+    }
+    pub fn with_info(mut self, info: Option<PlotInfo>) -> Self {
+        self.info = info;
+        self
+    }
+    pub fn get_info(&self) -> Option<&PlotInfo> {
+        self.info.as_ref()
+    }
+    /// all vectors including the scale, in the order described by the simulator if known,
+    /// otherwise scale first
+    pub fn columns(&self) -> Vec<&SimVector> {
+        match &self.info {
+            Some(info) if !info.names.is_empty() => {
+                info.names.iter().filter_map(|n| self.get(n)).collect()
+            }
+            _ => self.scale().into_iter().chain(self.vectors()).collect(),
+        }
+    }
+    /// tag with the dot command that produced it, e.g. `.dc V1 0 5 0.1 V2 0 1 0.5`
+    pub fn with_card(mut self, card: Option<String>) -> Self {
+        self.card = card;
+        self
+    }
+    pub fn get_card(&self) -> Option<&str> {
+        self.card.as_deref()
+    }
+    /// tag with the values of the stepped devices
+    pub fn with_step(mut self, step: Vec<(String, f64)>) -> Self {
+        self.step = step;
+        self
+    }
+    pub fn get_step(&self) -> &[(String, f64)] {
+        &self.step
+    }
+    pub fn get_analysis(&self) -> &str {
+        &self.analysis
+    }
+    /// the scale (x-axis) vector, e.g. time for transient
+    pub fn scale(&self) -> Option<&SimVector> {
+        self.scale.map(|i| &self.vecs[i])
+    }
+    /// all vectors other than the scale
+    pub fn vectors(&self) -> impl Iterator<Item = &SimVector> {
+        self.vecs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != self.scale)
+            .map(|(_, v)| v)
+    }
+    /// true if every vector holds exactly one value, e.g. an operating point
+    pub fn is_single_point(&self) -> bool {
+        !self.vecs.is_empty() && self.vecs.iter().all(|v| v.len() == 1)
+    }
+    /// index ranges over which the scale runs monotonically
+    /// a nested dc sweep restarts the inner sweep for each outer value, giving one range per curve
+    pub fn segments(&self) -> Vec<Range<usize>> {
+        let Some(scale) = self.scale() else {
+            return self
+                .vecs
+                .first()
+                .map(|v| vec![0..v.len()])
+                .unwrap_or_default();
+        };
+        let s = scale.real();
+        let mut segments = vec![];
+        let mut start = 0;
+        let mut dir = 0.0;
+        for i in 1..s.len() {
+            let d = s[i] - s[i - 1];
+            if dir == 0.0 {
+                if d != 0.0 {
+                    dir = d.signum();
+                }
+            } else if d * dir < 0.0 {
+                segments.push(start..i);
+                start = i;
+                dir = 0.0;
+            }
+        }
+        if start < s.len() {
+            segments.push(start..s.len());
+        }
+        segments
+    }
+    pub fn get(&self, name: &str) -> Option<&SimVector> {
+        self.vecs.iter().find(|v| v.name == name)
+    }
+}
+
+/// resource storing the results of completed analyses
+/// each entry holds all plots of one run, more than one if the run was stepped
+/// only the latest run of each analysis is kept, as only that one is shown
+#[derive(Resource, Default)]
+pub struct SimResults {
+    runs: Vec<Vec<SimPlot>>,
+}
+
+impl SimResults {
+    /// store the plots of a run, one per step in step order
+    /// replaces the previous run of the same analysis
+    pub fn push(&mut self, plots: Vec<SimPlot>) {
+        let Some(analysis) = plots.first().map(|p| p.analysis.clone()) else {
+            return;
+        };
+        self.runs.retain(|r| r[0].analysis != analysis);
+        self.runs.push(plots);
+    }
+    /// all plots of the most recent run of given analysis
+    pub fn latest_steps(&self, analysis: &str) -> &[SimPlot] {
+        self.runs
+            .iter()
+            .rev()
+            .find(|r| r[0].analysis == analysis)
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plot(analysis: &str, value: f64) -> SimPlot {
+        let v = SimVector::new("out".to_owned(), vec![value], None);
+        SimPlot::from_vectors(analysis, None, vec![v])
+    }
+
+    #[test]
+    fn test_keeps_latest_run() {
+        let mut results = SimResults::default();
+        results.push(vec![plot("op", 1.0)]);
+        results.push(vec![plot("ac", 2.0)]);
+        results.push(vec![plot("op", 3.0), plot("op", 4.0)]);
+        results.push(vec![]);
+        // the earlier op run is dropped, the ac run is kept
+        assert_eq!(results.runs.len(), 2);
+        let op = results.latest_steps("op");
+        assert_eq!(op.len(), 2);
+        assert_eq!(op[0].get("out").unwrap().real(), [3.0]);
+        assert_eq!(results.latest_steps("ac").len(), 1);
+    }
+}
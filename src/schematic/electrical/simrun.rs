@@ -0,0 +1,371 @@
+//! background simulation runs
+//! analyses are started with ngspice `bg_run`, which simulates in a separate thread so that
+//! the frame loop is not blocked. progress and completion are reported through the
+//! [`super::spmanager::SpManager`] callbacks, polled here once per frame.
+//! stepped runs are queued as one netlist per step, each sourced and run once the previous
+//! step finished.
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+use num::complex::Complex64;
+
+use crate::schematic::ui::console::PrintConsoleLine;
+
+use super::{
+    label::SchematicLabelBundle,
+    native,
+    nets::{Port, PortLabel},
+    oppoint,
+    results::{SimPlot, SimResults},
+    spmanager::SPRes,
+    stepping::StepValues,
+    units::{format_complex, format_eng, ComplexFormat},
+    NetId,
+};
+
+/// user controls over a running simulation
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimControl {
+    /// pause the background thread, keeping results so far
+    Halt,
+    /// continue a halted simulation
+    Resume,
+    /// stop the simulation and discard its results
+    Cancel,
+}
+
+/// sent once a background simulation completes, after its last step if stepped
+#[derive(Event)]
+pub struct SimFinished {
+    /// one plot per step, never empty
+    plots: Vec<SimPlot>,
+}
+
+impl SimFinished {
+    pub fn get_plot(&self) -> &SimPlot {
+        &self.plots[0]
+    }
+    pub fn get_plots(&self) -> &[SimPlot] {
+        &self.plots
+    }
+}
+
+/// state of the current background simulation, if any
+#[derive(Resource, Default)]
+pub struct SimRun {
+    /// analysis being run, None if idle
+    analysis: Option<String>,
+    /// dot command being run, if known, see [`SimPlot::get_card`]
+    card: Option<String>,
+    halted: bool,
+    /// percentage completed as last reported by ngspice
+    progress: f32,
+    /// steps yet to run, as the values and netlist file of each
+    pending: VecDeque<(StepValues, String)>,
+    /// values of the step being run
+    current: StepValues,
+    /// plots of the steps completed so far
+    done: Vec<SimPlot>,
+    /// total number of steps, 1 if not stepped
+    total: usize,
+}
+
+impl SimRun {
+    pub fn get_analysis(&self) -> Option<&str> {
+        self.analysis.as_deref()
+    }
+    pub fn is_running(&self) -> bool {
+        self.analysis.is_some()
+    }
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+    /// percentage completed over all steps
+    pub fn progress(&self) -> f32 {
+        (self.done.len() as f32 * 100.0 + self.progress) / self.total.max(1) as f32
+    }
+    /// 1-based number of the step being run and the total number of steps
+    pub fn step(&self) -> (usize, usize) {
+        (self.done.len() + 1, self.total)
+    }
+    /// start tracking a run of `analysis`, the caller is responsible for issuing `bg_run`
+    pub fn start(&mut self, analysis: &str) {
+        *self = Self {
+            analysis: Some(analysis.to_owned()),
+            total: 1,
+            ..Default::default()
+        };
+    }
+    /// start tracking a stepped run of `analysis`, given the values and netlist file of each step
+    /// the caller is responsible for running the first step, as returned by [`Self::next_step`]
+    pub fn start_steps(&mut self, analysis: &str, steps: Vec<(StepValues, String)>) {
+        *self = Self {
+            analysis: Some(analysis.to_owned()),
+            total: steps.len(),
+            pending: steps.into(),
+            ..Default::default()
+        };
+    }
+    /// set the dot command being run, after starting
+    pub fn set_card(&mut self, card: String) {
+        self.card = Some(card);
+    }
+    /// advance to the next step, returns its netlist file
+    pub fn next_step(&mut self) -> Option<String> {
+        let (values, path) = self.pending.pop_front()?;
+        self.current = values;
+        self.progress = 0.0;
+        Some(path)
+    }
+}
+
+pub struct SimRunPlugin;
+
+impl Plugin for SimRunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SimControl>();
+        app.add_event::<SimFinished>();
+        app.init_resource::<SimRun>();
+        app.init_resource::<ComplexFormat>();
+        app.add_systems(
+            PreUpdate,
+            (
+                sim_control,
+                poll_sim,
+                (
+                    apply_results,
+                    native::cross_check,
+                    oppoint::annotate_devices,
+                ),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// forward halt/resume/cancel requests to ngspice
+fn sim_control(
+    mut e_control: EventReader<SimControl>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+    mut simrun: ResMut<SimRun>,
+    spres: Res<SPRes>,
+) {
+    for ctrl in e_control.read() {
+        if !simrun.is_running() {
+            continue;
+        }
+        let cmd = match ctrl {
+            SimControl::Halt if !simrun.halted => {
+                simrun.halted = true;
+                "bg_halt"
+            }
+            SimControl::Resume if simrun.halted => {
+                simrun.halted = false;
+                // discard the finish reported when halting
+                spres.backend().take_finished();
+                "bg_resume"
+            }
+            SimControl::Cancel => {
+                if !simrun.halted {
+                    spres.command("bg_halt");
+                    e_console_rgstr
+                        .send(PrintConsoleLine::new("bg_halt".to_owned(), Color32::GRAY));
+                }
+                spres.backend().vecvals_drain();
+                *simrun = SimRun::default();
+                continue;
+            }
+            _ => continue,
+        };
+        spres.command(cmd);
+        e_console_rgstr.send(PrintConsoleLine::new(cmd.to_owned(), Color32::GRAY));
+    }
+}
+
+/// track progress and collect results of the background thread once it is done
+fn poll_sim(
+    mut simrun: ResMut<SimRun>,
+    spres: Res<SPRes>,
+    mut e_finished: EventWriter<SimFinished>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+) {
+    let Some(analysis) = simrun.analysis.clone() else {
+        // idle or cancelled, ignore any late finish notification
+        spres.backend().take_finished();
+        return;
+    };
+    if let Some((_, pct)) = spres.backend().progress() {
+        simrun.progress = pct;
+    }
+    if simrun.halted || !spres.backend().take_finished() {
+        return;
+    }
+    let step = std::mem::take(&mut simrun.current);
+    let plot = SimPlot::from_vecvals(&analysis, spres.backend().vecvals_drain())
+        .with_step(step)
+        .with_info(spres.backend().plot_info())
+        .with_card(simrun.card.clone());
+    simrun.done.push(plot);
+    if let Some(path) = simrun.next_step() {
+        for cmd in [format!("source {}", path), "bg_run".to_owned()] {
+            spres.command(&cmd);
+            e_console_rgstr.send(PrintConsoleLine::new(cmd, Color32::GRAY));
+        }
+        return;
+    }
+    let plots = std::mem::take(&mut simrun.done);
+    *simrun = SimRun::default();
+    e_finished.send(SimFinished { plots });
+}
+
+/// store finished results, labelling ports with operating point values
+fn apply_results(
+    mut e_finished: EventReader<SimFinished>,
+    q_ports: Query<(Entity, &NetId), With<Port>>,
+    q_labeled_ports: Query<(Entity, &PortLabel), With<Port>>,
+    mut commands: Commands,
+    mut sim_results: ResMut<SimResults>,
+    complex_format: Res<ComplexFormat>,
+) {
+    for ev in e_finished.read() {
+        let plot = ev.get_plot();
+        let stepped = ev.get_plots().len() > 1;
+        // clear all port labels
+        for (e, p) in q_labeled_ports.iter() {
+            commands.entity(p.get_label_entity()).despawn();
+            commands.entity(e).remove::<PortLabel>();
+        }
+        // operating points: display values as port labels, unless there is one per step
+        if plot.is_single_point() && !stepped {
+            let mut results = HashMap::<&str, String>::new();
+            for v in plot.vectors() {
+                if let Some(imag) = v.imag() {
+                    let cv = Complex64::new(v.real()[0], imag[0]);
+                    results.insert(v.get_name(), format_complex(cv, "V", *complex_format));
+                } else {
+                    results.insert(v.get_name(), format_eng(v.real()[0], "V"));
+                }
+            }
+            for (ent, netid) in q_ports.iter() {
+                let Some(val) = results.get(netid.get_id()) else {
+                    continue;
+                };
+                insert_new_label(ent, &mut commands, val.clone());
+            }
+        }
+        sim_results.push(ev.get_plots().to_vec());
+    }
+}
+
+fn insert_new_label(parent: Entity, commands: &mut Commands, val: String) {
+    let label_entity = commands
+        .spawn(SchematicLabelBundle::new(parent, IVec2::splat(0), val))
+        .id();
+    commands.entity(parent).insert(PortLabel::new(label_entity));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::schematic::electrical::backend::mock::MockBackend;
+
+    fn world_with(backend: Arc<MockBackend>) -> World {
+        let mut world = World::new();
+        world.insert_resource(SPRes::new(backend));
+        world.init_resource::<SimRun>();
+        world.init_resource::<SimResults>();
+        world.init_resource::<ComplexFormat>();
+        world.init_resource::<Events<SimFinished>>();
+        world.init_resource::<Events<SimControl>>();
+        world.init_resource::<Events<PrintConsoleLine>>();
+        world
+    }
+
+    #[test]
+    fn test_op_annotates_ports() {
+        let backend = Arc::new(MockBackend::with_results(&[&[
+            ("net_1", 3.0),
+            ("net_2", 1.5),
+        ]]));
+        let mut world = world_with(backend.clone());
+        let device = world.spawn_empty().id();
+        let p1 = world
+            .spawn((
+                Port::new(device, IVec2::ZERO),
+                NetId::new("net_1".to_owned()),
+            ))
+            .id();
+        let p2 = world
+            .spawn((Port::new(device, IVec2::X), NetId::new("net_3".to_owned())))
+            .id();
+
+        world.resource_mut::<SimRun>().start("op");
+        world.resource::<SPRes>().command("bg_run");
+        world.run_system_once(poll_sim);
+        world.run_system_once(apply_results);
+
+        assert!(!world.resource::<SimRun>().is_running());
+        let results = world.resource::<SimResults>();
+        let plot = &results.latest_steps("op")[0];
+        assert!(plot.is_single_point());
+        assert_eq!(plot.get("net_2").unwrap().real(), &[1.5]);
+        // only ports on nets present in the results are labelled
+        let label = world.get::<PortLabel>(p1).unwrap().get_label_entity();
+        assert_eq!(world.get::<Text>(label).unwrap().sections[0].value, "3 V");
+        assert!(world.get::<PortLabel>(p2).is_none());
+        assert_eq!(backend.commands(), ["bg_run"]);
+    }
+
+    #[test]
+    fn test_steps_run_in_sequence() {
+        let backend = Arc::new(MockBackend::with_results(&[&[("net_1", 1.0)]]));
+        let mut world = world_with(backend.clone());
+        let steps = [1e3, 2e3]
+            .iter()
+            .enumerate()
+            .map(|(k, r)| (vec![("R1".to_owned(), *r)], format!("step{}.cir", k)))
+            .collect();
+
+        let mut simrun = world.resource_mut::<SimRun>();
+        simrun.start_steps("op", steps);
+        simrun.set_card(".op".to_owned());
+        assert_eq!(simrun.next_step().as_deref(), Some("step0.cir"));
+        world.resource::<SPRes>().command("bg_run");
+        // first step done, second is started
+        world.run_system_once(poll_sim);
+        assert_eq!(world.resource::<SimRun>().step(), (2, 2));
+        world.run_system_once(poll_sim);
+        world.run_system_once(apply_results);
+
+        assert!(!world.resource::<SimRun>().is_running());
+        let steps = world.resource::<SimResults>().latest_steps("op");
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[1].get_step(), [("R1".to_owned(), 2e3)]);
+        // every step keeps the card it was run with
+        assert_eq!(steps[1].get_card(), Some(".op"));
+        assert_eq!(backend.commands(), ["bg_run", "source step1.cir", "bg_run"]);
+    }
+
+    #[test]
+    fn test_cancel_discards_results() {
+        let backend = Arc::new(MockBackend::with_results(&[&[("net_1", 1.0)]]));
+        let mut world = world_with(backend.clone());
+
+        world.resource_mut::<SimRun>().start("op");
+        world.resource::<SPRes>().command("bg_run");
+        world.send_event(SimControl::Cancel);
+        world.run_system_once(sim_control);
+        world.run_system_once(poll_sim);
+        world.run_system_once(apply_results);
+
+        assert!(!world.resource::<SimRun>().is_running());
+        assert!(world.resource::<SimResults>().latest_steps("op").is_empty());
+        assert_eq!(backend.commands(), ["bg_run", "bg_halt"]);
+    }
+}
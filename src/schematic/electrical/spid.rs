@@ -16,6 +16,9 @@ macro_rules! sptype_prefix {
 sptype_prefix!(
     R, L, C, // resistor, inductor, capacitor
     V, I, // independent voltage/current source
+    E, F, G, H, // voltage/current controlled voltage/current sources
+    B, // behavioral source
+    K, S, W, T, // coupled inductors, voltage/current controlled switch, transmission line
     D, Q, M, X // diode, bjt, mosfet, subcircuit
 );
 
@@ -23,7 +26,7 @@ sptype_prefix!(
 const NET: &str = "";
 
 /// Spice Device Types enumeration
-#[derive(Reflect, Debug, Clone)]
+#[derive(Reflect, Debug, Clone, PartialEq)]
 pub enum SpDeviceType {
     Gnd,
     V,
@@ -31,9 +34,29 @@ pub enum SpDeviceType {
     R,
     L,
     C,
+    /// voltage controlled voltage source
+    E,
+    /// current controlled current source
+    F,
+    /// voltage controlled current source
+    G,
+    /// current controlled voltage source
+    H,
+    /// behavioral source
+    B,
+    /// coupling between inductors, without ports of its own
+    K,
+    /// voltage controlled switch
+    S,
+    /// current controlled switch
+    W,
+    /// lossless transmission line
+    T,
     D,
     Q,
     M,
+    /// subcircuit instance
+    X,
 }
 
 impl SpDeviceType {
@@ -45,9 +68,59 @@ impl SpDeviceType {
             SpDeviceType::R => R,
             SpDeviceType::L => L,
             SpDeviceType::C => C,
+            SpDeviceType::E => E,
+            SpDeviceType::F => F,
+            SpDeviceType::G => G,
+            SpDeviceType::H => H,
+            SpDeviceType::B => B,
+            SpDeviceType::K => K,
+            SpDeviceType::S => S,
+            SpDeviceType::W => W,
+            SpDeviceType::T => T,
             SpDeviceType::D => D,
             SpDeviceType::Q => Q,
             SpDeviceType::M => M,
+            SpDeviceType::X => X,
+        }
+    }
+    /// device type of a spice prefix, e.g. `R`, or of `Gnd` for the ground symbol
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix.to_ascii_uppercase().as_str() {
+            "GND" => Some(SpDeviceType::Gnd),
+            V => Some(SpDeviceType::V),
+            I => Some(SpDeviceType::I),
+            R => Some(SpDeviceType::R),
+            L => Some(SpDeviceType::L),
+            C => Some(SpDeviceType::C),
+            E => Some(SpDeviceType::E),
+            F => Some(SpDeviceType::F),
+            G => Some(SpDeviceType::G),
+            H => Some(SpDeviceType::H),
+            B => Some(SpDeviceType::B),
+            K => Some(SpDeviceType::K),
+            S => Some(SpDeviceType::S),
+            W => Some(SpDeviceType::W),
+            T => Some(SpDeviceType::T),
+            D => Some(SpDeviceType::D),
+            Q => Some(SpDeviceType::Q),
+            M => Some(SpDeviceType::M),
+            X => Some(SpDeviceType::X),
+            _ => None,
+        }
+    }
+    /// number of ports, None for subcircuits which have those of their definition
+    pub fn port_count(&self) -> Option<usize> {
+        match self {
+            SpDeviceType::K => Some(0),
+            SpDeviceType::Gnd => Some(1),
+            SpDeviceType::Q => Some(3),
+            SpDeviceType::M
+            | SpDeviceType::E
+            | SpDeviceType::G
+            | SpDeviceType::S
+            | SpDeviceType::T => Some(4),
+            SpDeviceType::X => None,
+            _ => Some(2),
         }
     }
 }
@@ -65,6 +138,8 @@ pub enum SchType {
     Spice(SpType),
     Port,
     Label,
+    /// simulation directive text, see [`super::directive`]
+    Directive,
 }
 
 /// spice id to identify a unique device
@@ -1,24 +1,55 @@
-use super::super::ui::console::Color32;
+use super::super::ui::console::{Color32, PrintConsoleLine};
+use super::{
+    backend::SimulatorBackend,
+    libngspice::{self, Candidate},
+    native::NativeBackend,
+    results::PlotInfo,
+};
 use bevy::prelude::*;
 use paprika::*;
 use std::{
     collections::VecDeque,
-    sync::{Arc, Mutex, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 /// Spice Manager to facillitate interaction with NgSpice
 #[derive(Debug, Default)]
 pub struct SpManager {
     sharedres: Arc<RwLock<VecDeque<(String, Color32)>>>,
     vecvals: Mutex<Vec<PkVecvaluesall>>,
-    vecinfo: Option<PkVecinfoall>,
+    /// description of the plot being simulated, sent as the simulation starts
+    vecinfo: Mutex<Option<PlotInfo>>,
+    /// latest progress reported by ngspice, e.g. ("tran", 42.0)
+    progress: Mutex<Option<(String, f32)>>,
+    /// true while the ngspice background thread is running
+    bg_running: AtomicBool,
+    /// set when the background thread finishes, cleared when taken
+    bg_finished: AtomicBool,
 }
 
 impl SpManager {
     fn new() -> Self {
         SpManager::default()
     }
-    pub fn vecvals_pop(&self) -> Option<PkVecvaluesall> {
-        self.vecvals.try_lock().unwrap().pop()
+    /// takes all values sent by ngspice since last drained, one entry per simulation step
+    pub fn vecvals_drain(&self) -> Vec<PkVecvaluesall> {
+        self.vecvals.lock().unwrap().drain(..).collect()
+    }
+    /// latest analysis name and percentage completed reported by ngspice
+    pub fn progress(&self) -> Option<(String, f32)> {
+        self.progress.lock().unwrap().clone()
+    }
+    pub fn is_running(&self) -> bool {
+        self.bg_running.load(Ordering::SeqCst)
+    }
+    pub fn vecinfo(&self) -> Option<PlotInfo> {
+        self.vecinfo.lock().unwrap().clone()
+    }
+    /// true once after each time the background thread finishes
+    pub fn take_finished(&self) -> bool {
+        self.bg_finished.swap(false, Ordering::SeqCst)
     }
     pub fn drain(&self) -> impl IntoIterator<Item = (String, Color32)> {
         self.sharedres
@@ -46,79 +77,149 @@ impl paprika::PkSpiceManager for SpManager {
         (*arvs).push_back((msgs.to_owned(), color));
     }
     fn cb_send_stat(&mut self, msg: String, id: i32) {
+        // progress is reported as e.g. `tran: 42.3%`
+        if let Some((name, pct)) = msg
+            .trim()
+            .strip_suffix('%')
+            .and_then(|s| s.rsplit_once(':'))
+        {
+            if let Ok(pct) = pct.trim().parse::<f32>() {
+                *self.progress.lock().unwrap() = Some((name.trim().to_owned(), pct));
+                return;
+            }
+        }
         let mut arvs = self.sharedres.write().unwrap();
         (*arvs).push_back((msg, Color32::BLUE));
     }
     fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
     fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {
-        self.vecinfo = Some(pkvecinfoall);
+        *self.vecinfo.lock().unwrap() = Some(PlotInfo::from_vecinfo(&pkvecinfoall));
     }
     fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {
         // this is called every simulation step when running tran
-        self.vecvals.try_lock().unwrap().push(pkvecvaluesall);
+        self.vecvals.lock().unwrap().push(pkvecvaluesall);
+    }
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {
+        // called from the background thread as it starts and finishes
+        self.bg_running.store(!is_fin, Ordering::SeqCst);
+        if is_fin {
+            self.bg_finished.store(true, Ordering::SeqCst);
+        } else {
+            *self.progress.lock().unwrap() = None;
+        }
     }
-    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
 }
 
-#[derive(Resource)]
-pub struct SPRes {
+/// ngspice shared library as a simulator backend
+pub struct NgspiceBackend {
     /// spice manager
     spm: Arc<SpManager>,
     /// ngspice library
     lib: PkSpice<SpManager>,
 }
 
+impl SimulatorBackend for NgspiceBackend {
+    fn command(&self, cmd: &str) {
+        self.lib.command(cmd);
+    }
+    fn drain_output(&self) -> Vec<(String, Color32)> {
+        self.spm.drain().into_iter().collect()
+    }
+    fn vecvals_drain(&self) -> Vec<PkVecvaluesall> {
+        self.spm.vecvals_drain()
+    }
+    fn progress(&self) -> Option<(String, f32)> {
+        self.spm.progress()
+    }
+    fn is_running(&self) -> bool {
+        self.spm.is_running()
+    }
+    fn take_finished(&self) -> bool {
+        self.spm.take_finished()
+    }
+    fn plot_info(&self) -> Option<PlotInfo> {
+        self.spm.vecinfo()
+    }
+}
+
+#[derive(Resource)]
+pub struct SPRes {
+    backend: Arc<dyn SimulatorBackend>,
+    /// why libngspice could not be loaded, if it was not
+    ngspice_error: Option<String>,
+}
+
 impl SPRes {
-    pub fn get_spm(&self) -> &SpManager {
-        &self.spm
+    pub fn new(backend: Arc<dyn SimulatorBackend>) -> Self {
+        Self {
+            backend,
+            ngspice_error: None,
+        }
+    }
+    pub fn ngspice_error(&self) -> Option<&str> {
+        self.ngspice_error.as_deref()
+    }
+    pub fn backend(&self) -> &dyn SimulatorBackend {
+        self.backend.as_ref()
     }
     pub fn command(&self, cmdstr: &str) {
-        self.lib.command(cmdstr);
+        self.backend.command(cmdstr);
+    }
+}
+
+impl NgspiceBackend {
+    /// load libngspice from the first of [`libngspice::candidates`] which works
+    /// the error tells where the library was looked for and how to point to it
+    pub fn new() -> Result<Self, String> {
+        let mut lib = libngspice::candidates()
+            .iter()
+            .find_map(|candidate| {
+                match PkSpice::<SpManager>::new(candidate.path().as_os_str()) {
+                    Ok(lib) => {
+                        info!("loaded libngspice from {}", candidate.path().display());
+                        Some(lib)
+                    }
+                    // a path given by the user is expected to load, say why it did not
+                    Err(e) => {
+                        if let Candidate::Configured(path, origin) = candidate {
+                            warn!("could not load {} from {}: {}", path.display(), origin, e);
+                        }
+                        None
+                    }
+                }
+            })
+            .ok_or_else(|| format!("libngspice not found: {}", libngspice::help()))?;
+        let spm = Arc::new(SpManager::new());
+        lib.init(Some(spm.clone()));
+        Ok(NgspiceBackend { spm, lib })
     }
 }
 
 impl Default for SPRes {
     fn default() -> Self {
-        let spm = Arc::new(SpManager::new());
-        let mut lib;
-        #[cfg(target_family = "windows")]
-        {
-            lib = PkSpice::<SpManager>::new(std::ffi::OsStr::new("ngspice.dll")).unwrap();
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // retrieve libngspice.dylib from the following possible directories
-            let ret = std::process::Command::new("find")
-                .args(&["/usr/lib", "/usr/local/lib"])
-                .arg("-name")
-                .arg("*libngspice.dylib")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice.dylib. Make sure it is installed.");
-                    std::process::exit(1);
-                });
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
+        match NgspiceBackend::new() {
+            Ok(ngspice) => Self::new(Arc::new(ngspice)),
+            Err(e) => {
+                warn!("using native solver (linear op and ac only): {}", e);
+                Self {
+                    ngspice_error: Some(e),
+                    ..Self::new(Arc::new(NativeBackend::default()))
+                }
+            }
         }
-        #[cfg(target_os = "linux")]
-        {
-            // dynamically retrieves libngspice from system
-            let ret = std::process::Command::new("sh")
-                .arg("-c")
-                .arg("ldconfig -p | grep ngspice | awk '/.*libngspice.so$/{print $4}'")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice. Make sure it is installed.");
-                    std::process::exit(1);
-                });
+    }
+}
 
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
-        }
-        lib.init(Some(spm.clone()));
-        SPRes { spm, lib }
+/// tell the user once that simulation is limited to the native solver
+fn report_backend(spres: Res<SPRes>, mut e_console: EventWriter<PrintConsoleLine>) {
+    if let Some(e) = spres.ngspice_error() {
+        e_console.send(PrintConsoleLine::new(
+            format!(
+                "{}\nsimulation is limited to .op and .ac of linear circuits",
+                e
+            ),
+            Color32::YELLOW,
+        ));
     }
 }
 
@@ -127,5 +228,6 @@ pub struct SPManagerPlugin;
 impl Plugin for SPManagerPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SPRes>();
+        app.add_systems(Startup, report_backend);
     }
 }
@@ -0,0 +1,347 @@
+//! parametric sweeps and monte carlo runs
+//! the analysis is repeated once per step, each with its own netlist in which the value of the
+//! stepped devices is rewritten. every resulting [`super::SimPlot`] is tagged with the values
+//! that produced it, so that the viewers can overlay all runs.
+
+use bevy::prelude::*;
+
+use super::units::parse_spice_number;
+
+/// distribution of a device value around its nominal
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Distribution {
+    /// anywhere within the tolerance
+    #[default]
+    Uniform,
+    /// normal, with the tolerance at 3 sigma
+    Gaussian,
+}
+
+impl Distribution {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Gaussian => "gaussian",
+        }
+    }
+}
+
+/// relative tolerance of a device value, varied in monte carlo runs
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Tolerance {
+    /// e.g. 0.05 for 5%
+    pub tol: f64,
+    pub dist: Distribution,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            tol: 0.05,
+            dist: Distribution::Uniform,
+        }
+    }
+}
+
+/// how the next analysis is repeated
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StepMode {
+    /// single run
+    #[default]
+    Off,
+    /// linear sweep of the value of one device, e.g. `R1`
+    Param {
+        device: String,
+        start: f64,
+        stop: f64,
+        step: f64,
+    },
+    /// `runs` runs with every device with a [`Tolerance`] varied randomly
+    MonteCarlo { runs: u32, seed: u64 },
+}
+
+#[derive(Resource, Default)]
+pub struct StepConfig {
+    pub mode: StepMode,
+}
+
+/// most runs a step or monte carlo configuration may make, each writes and simulates a netlist
+pub const MAX_STEPS: usize = 1000;
+
+/// values of the stepped devices for one run, as (spice name, value)
+pub type StepValues = Vec<(String, f64)>;
+
+impl StepConfig {
+    /// values for each run, given (spice name, value, tolerance) of every device
+    /// the value is None if it is not a number, see [`super::DeviceParams::value`]
+    /// an empty list means no stepping
+    pub fn steps(
+        &self,
+        mut devices: impl Iterator<Item = (String, Option<f64>, Option<Tolerance>)>,
+    ) -> Result<Vec<StepValues>, String> {
+        match &self.mode {
+            StepMode::Off => Ok(vec![]),
+            StepMode::Param {
+                device,
+                start,
+                stop,
+                step,
+            } => {
+                let Some((name, value, _)) =
+                    devices.find(|(name, ..)| name.eq_ignore_ascii_case(device))
+                else {
+                    return Err(format!("step: no device named {}", device));
+                };
+                if value.is_none() {
+                    return Err(format!("step: value of {} is not a number", name));
+                }
+                if ![start, stop, step].iter().all(|v| v.is_finite()) {
+                    return Err(format!(
+                        "step: {} to {} in steps of {} is not a range",
+                        start, stop, step
+                    ));
+                }
+                if *step == 0.0 || (stop - start) * step < 0.0 {
+                    return Err(format!(
+                        "step: {} to {} never reached in steps of {}",
+                        start, stop, step
+                    ));
+                }
+                let n = ((stop - start) / step + 1e-9).floor() + 1.0;
+                if n > MAX_STEPS as f64 {
+                    return Err(format!(
+                        "step: {} to {} in steps of {} is {} runs, at most {} are allowed",
+                        start, stop, step, n, MAX_STEPS
+                    ));
+                }
+                let n = n as usize;
+                Ok((0..n)
+                    .map(|i| vec![(name.clone(), start + i as f64 * step)])
+                    .collect())
+            }
+            StepMode::MonteCarlo { runs, .. } if *runs as usize > MAX_STEPS => Err(format!(
+                "monte carlo: {} runs, at most {} are allowed",
+                runs, MAX_STEPS
+            )),
+            StepMode::MonteCarlo { runs, seed } => {
+                let varied = devices
+                    .filter_map(|(name, value, tol)| Some((name, value?, tol?)))
+                    .collect::<Vec<_>>();
+                if varied.is_empty() {
+                    return Err(
+                        "monte carlo: no device with a tolerance and numeric value".to_owned()
+                    );
+                }
+                let mut rng = Rng::new(*seed);
+                Ok((0..*runs)
+                    .map(|_| {
+                        varied
+                            .iter()
+                            .map(|(name, nominal, tol)| {
+                                let dev = match tol.dist {
+                                    Distribution::Uniform => rng.uniform() * 2.0 - 1.0,
+                                    Distribution::Gaussian => rng.gaussian() / 3.0,
+                                };
+                                (name.clone(), nominal * (1.0 + tol.tol * dev))
+                            })
+                            .collect()
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// the leading number of a device's params, e.g. 1e3 for `1k` or 0.0 for `0 ac 1`
+pub fn leading_value(params: &str) -> Option<f64> {
+    params
+        .split_whitespace()
+        .next()
+        .and_then(parse_spice_number)
+}
+
+/// params with the leading number replaced by `value`, None if it does not start with a number
+pub fn with_value(params: &str, value: f64) -> Option<String> {
+    let params = params.trim_start();
+    let first = params.split_whitespace().next()?;
+    parse_spice_number(first)?;
+    Some(format!("{:e}{}", value, &params[first.len()..]))
+}
+
+/// short label of a run for legends, e.g. `R1=1e3`
+pub fn step_label(index: usize, values: &[(String, f64)]) -> String {
+    match values {
+        [(name, value)] => format!("{}={:e}", name, value),
+        _ => format!("#{}", index + 1),
+    }
+}
+
+/// small deterministic generator (splitmix64), so that runs can be reproduced from their seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// uniform in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// standard normal, by box-muller
+    fn gaussian(&mut self) -> f64 {
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+}
+
+pub struct SteppingPlugin;
+
+impl Plugin for SteppingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StepConfig>();
+        app.register_type::<Tolerance>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::electrical::{DeviceParams, SpDeviceType};
+
+    #[test]
+    fn test_with_value() {
+        assert_eq!(with_value("1k", 2e3).as_deref(), Some("2e3"));
+        assert_eq!(with_value("0 AC 1", 1.5).as_deref(), Some("1.5e0 AC 1"));
+        assert_eq!(with_value("AC 1 SIN(3.3 1 2k 0 0)", 1.0), None);
+        assert_eq!(with_value("DMOD", 1.0), None);
+    }
+
+    #[test]
+    fn test_step_source() {
+        // the dc value is stepped, wherever it is written
+        let dtype = SpDeviceType::V;
+        let params = DeviceParams::parse(&dtype, "DC 5 AC 1 SIN(0 1 1k)");
+        assert_eq!(params.value(&dtype), Some(5.0));
+        let config = StepConfig {
+            mode: StepMode::Param {
+                device: "V1".to_owned(),
+                start: 1.0,
+                stop: 2.0,
+                step: 1.0,
+            },
+        };
+        let devices = [("V1".to_owned(), params.value(&dtype), None)];
+        let steps = config.steps(devices.into_iter()).unwrap();
+        let stepped = params.with_value(&dtype, steps[1][0].1).unwrap();
+        assert_eq!(stepped.spice_param(&dtype), "DC 2e0 AC 1 SIN(0 1 1k)");
+        // a source without a dc value cannot be stepped
+        let ac_only = DeviceParams::parse(&dtype, "AC 1");
+        assert_eq!(ac_only.value(&dtype), None);
+        let devices = [("V1".to_owned(), ac_only.value(&dtype), None)];
+        assert!(config.steps(devices.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_steps() {
+        let devices = || {
+            [
+                ("R1", "1k", Some(Tolerance::default())),
+                ("C1", "1n", None),
+                (
+                    "R2",
+                    "2k",
+                    Some(Tolerance {
+                        tol: 0.1,
+                        dist: Distribution::Gaussian,
+                    }),
+                ),
+            ]
+            .into_iter()
+            .map(|(name, params, tol)| (name.to_owned(), leading_value(params), tol))
+        };
+        let mut config = StepConfig::default();
+        assert!(config.steps(devices()).unwrap().is_empty());
+
+        config.mode = StepMode::Param {
+            device: "c1".to_owned(),
+            start: 1e-9,
+            stop: 3e-9,
+            step: 1e-9,
+        };
+        let steps = config.steps(devices()).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[2][0].0, "C1");
+        assert!((steps[2][0].1 - 3e-9).abs() < 1e-18);
+
+        config.mode = StepMode::MonteCarlo { runs: 50, seed: 7 };
+        let steps = config.steps(devices()).unwrap();
+        assert_eq!(steps.len(), 50);
+        // only devices with a tolerance are varied, within their tolerance
+        for run in steps.iter() {
+            assert_eq!(run.len(), 2);
+            assert!((run[0].1 - 1e3).abs() <= 50.0);
+        }
+        // reproducible from the seed
+        assert_eq!(steps, config.steps(devices()).unwrap());
+
+        config.mode = StepMode::Param {
+            device: "R9".to_owned(),
+            start: 1.0,
+            stop: 2.0,
+            step: 1.0,
+        };
+        assert!(config.steps(devices()).is_err());
+    }
+
+    #[test]
+    fn test_bad_steps() {
+        let devices = || [("R1".to_owned(), Some(1e3), Some(Tolerance::default()))].into_iter();
+        let mut config = StepConfig::default();
+        for (start, stop, step) in [
+            (1.0, 2.0, 0.0),
+            (1.0, 2.0, -1.0),
+            (2.0, 1.0, 1.0),
+            (1.0, 2.0, f64::NAN),
+            (1.0, f64::INFINITY, 1.0),
+            (f64::NEG_INFINITY, 1.0, 1.0),
+            // too many runs
+            (0.0, 1.0, 1e-9),
+        ] {
+            config.mode = StepMode::Param {
+                device: "R1".to_owned(),
+                start,
+                stop,
+                step,
+            };
+            assert!(
+                config.steps(devices()).is_err(),
+                "{} {} {}",
+                start,
+                stop,
+                step
+            );
+        }
+        // counting down is fine, up to the limit
+        config.mode = StepMode::Param {
+            device: "R1".to_owned(),
+            start: 1e3,
+            stop: 1.0,
+            step: -1.0,
+        };
+        assert_eq!(config.steps(devices()).unwrap().len(), MAX_STEPS);
+        config.mode = StepMode::MonteCarlo {
+            runs: MAX_STEPS as u32 + 1,
+            seed: 0,
+        };
+        assert!(config.steps(devices()).is_err());
+    }
+}
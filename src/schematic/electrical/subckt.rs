@@ -0,0 +1,280 @@
+//! hierarchical subcircuits
+//! a schematic, or a selection of it, is turned into a subcircuit definition with named pins.
+//! instances are `X` devices with a generated box symbol, and carry a copy of their definition
+//! so that they can be copied across sheets. the netlister emits each definition once as
+//! `.subckt name pins` ... `.ends`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::bevyon::StrokeTessellator;
+
+use super::{
+    devices::{spawn_preview_device_from_type, DeviceParams, DevicePorts, SubcktSymbols},
+    netlisting::device_line,
+    spid::SpDeviceType,
+    ElementsRes, NetId, SchematicElement, Selected, SpDeviceId,
+};
+
+/// subcircuit definition, stored on each instance
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct Subckt {
+    name: String,
+    pins: Vec<String>,
+    /// device lines and nested definitions
+    body: String,
+}
+
+impl Subckt {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_body(&self) -> &str {
+        &self.body
+    }
+    /// `.subckt` ... `.ends` block defining this subcircuit
+    pub fn definition(&self) -> String {
+        format!(
+            ".subckt {} {}\n{}.ends {}\n",
+            self.name,
+            self.pins.join(" "),
+            self.body,
+            self.name
+        )
+    }
+}
+
+/// request to place an instance of a subcircuit
+#[derive(Event)]
+pub struct SpawnSubckt {
+    subckt: Subckt,
+}
+
+impl SpawnSubckt {
+    pub fn new(subckt: Subckt) -> Self {
+        Self { subckt }
+    }
+    pub fn get_subckt(&self) -> &Subckt {
+        &self.subckt
+    }
+}
+
+/// builds subcircuit definitions from the selected devices, or from all devices if none are selected
+#[derive(SystemParam)]
+pub struct SubcktBuilder<'w, 's> {
+    q_devices: Query<
+        'w,
+        's,
+        (
+            &'static DevicePorts,
+            &'static DeviceParams,
+            &'static SchematicElement,
+            &'static SpDeviceId,
+            Option<&'static Subckt>,
+            Has<Selected>,
+        ),
+    >,
+    q_nid: Query<'w, 's, &'static NetId>,
+}
+
+impl<'w, 's> SubcktBuilder<'w, 's> {
+    /// true if only the selected devices go into the subcircuit
+    fn selection_only(&self) -> bool {
+        self.q_devices.iter().any(|(.., seld)| seld)
+    }
+
+    fn nets_of(&self, ports: &DevicePorts) -> Vec<String> {
+        ports
+            .get_ports()
+            .iter()
+            .filter_map(|p| self.q_nid.get(*p).ok())
+            .map(|nid| nid.get_id().to_owned())
+            .collect()
+    }
+
+    /// nets which may become pins, sorted by name, with whether they also connect outside of the subcircuit
+    pub fn candidate_pins(&self) -> Vec<(String, bool)> {
+        let selection_only = self.selection_only();
+        let mut nets = BTreeMap::<String, bool>::new();
+        for (ports, .., seld) in self.q_devices.iter() {
+            if seld || !selection_only {
+                for net in self.nets_of(ports) {
+                    nets.entry(net).or_insert(false);
+                }
+            }
+        }
+        for (ports, .., seld) in self.q_devices.iter() {
+            if selection_only && !seld {
+                for net in self.nets_of(ports) {
+                    if let Some(boundary) = nets.get_mut(&net) {
+                        *boundary = true;
+                    }
+                }
+            }
+        }
+        nets.into_iter().collect()
+    }
+
+    /// definition named `name`, with `pins` as (net, pin name) in pin order
+    pub fn build(&self, name: &str, pins: &[(String, String)]) -> Result<Subckt, String> {
+        let selection_only = self.selection_only();
+        let mut lines = vec![];
+        let mut nested = BTreeMap::<String, String>::new();
+        // couplings after the inductors they name, as in the netlist
+        let mut devices = self.q_devices.iter().collect::<Vec<_>>();
+        devices.sort_by_key(|(_, _, se, ..)| se.get_dtype() == Some(&SpDeviceType::K));
+        for (ports, params, se, spdid, subckt, seld) in devices {
+            if selection_only && !seld {
+                continue;
+            }
+            if let Some(subckt) = subckt {
+                nested.insert(subckt.name.clone(), subckt.definition());
+            }
+            lines.extend(device_line(ports, params, se, spdid, &self.q_nid));
+        }
+        let rename = pins
+            .iter()
+            .map(|(net, pin)| (net.as_str(), pin.as_str()))
+            .collect::<HashMap<_, _>>();
+        let subckt = subckt_from_lines(name, pins, &lines, nested.into_values(), &rename)?;
+        if subckt_names(&subckt.body).any(|n| n.eq_ignore_ascii_case(name)) {
+            return Err(format!("subcircuit {} would contain itself", name));
+        }
+        Ok(subckt)
+    }
+}
+
+/// names of the subcircuits defined or instantiated in `body`, at any depth
+fn subckt_names(body: &str) -> impl Iterator<Item = &str> {
+    body.lines().filter_map(|line| {
+        let mut tokens = line.split_whitespace();
+        let first = tokens.next()?;
+        if first.eq_ignore_ascii_case(".subckt") {
+            return tokens.next();
+        }
+        if !first.starts_with(['X', 'x']) {
+            return None;
+        }
+        // the last token before any params, e.g. `buf` in `X1 in out buf gain=2`
+        tokens
+            .take_while(|t| !t.contains('=') && !t.eq_ignore_ascii_case("params:"))
+            .last()
+    })
+}
+
+/// assemble a definition from device lines, renaming pin nets to pin names
+fn subckt_from_lines(
+    name: &str,
+    pins: &[(String, String)],
+    lines: &[String],
+    nested: impl Iterator<Item = String>,
+    rename: &HashMap<&str, &str>,
+) -> Result<Subckt, String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err("subcircuit name must be a single word".to_owned());
+    }
+    if pins.is_empty() {
+        return Err("subcircuit needs at least one pin".to_owned());
+    }
+    let pin_names = pins.iter().map(|(_, pin)| pin.clone()).collect::<Vec<_>>();
+    for (i, pin) in pin_names.iter().enumerate() {
+        if pin.is_empty() || pin.contains(char::is_whitespace) || pin == "0" {
+            return Err(format!("invalid pin name: '{}'", pin));
+        }
+        if pin_names[..i].contains(pin) {
+            return Err(format!("duplicate pin name: {}", pin));
+        }
+    }
+    let mut body = nested.collect::<String>();
+    for line in lines {
+        // device names are kept, nets which became pins take the pin name
+        let mut tokens = line.split(' ');
+        let dname = tokens.next().unwrap_or_default();
+        body.push_str(dname);
+        for token in tokens {
+            body.push(' ');
+            body.push_str(rename.get(token).copied().unwrap_or(token));
+        }
+        body.push('\n');
+    }
+    Ok(Subckt {
+        name: name.to_owned(),
+        pins: pin_names,
+        body,
+    })
+}
+
+/// spawn a subcircuit instance as preview, returns the device, port and label entities
+pub fn spawn_preview_subckt(
+    subckt: Subckt,
+    commands: &mut Commands,
+    eres: &ElementsRes,
+    symbols: &mut SubcktSymbols,
+    stroke_tess: &mut StrokeTessellator,
+    meshes: &mut Assets<Mesh>,
+) -> Box<[Entity]> {
+    let dtype = symbols
+        .get(subckt.pins.len(), stroke_tess, meshes)
+        .with_params(DeviceParams::Raw(subckt.name.clone()));
+    let entities = spawn_preview_device_from_type(dtype, commands, eres);
+    commands.entity(entities[0]).insert(subckt);
+    entities
+}
+
+pub struct SubcktPlugin;
+
+impl Plugin for SubcktPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnSubckt>();
+        app.register_type::<Subckt>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subckt_from_lines() {
+        let pins = [
+            ("net_1".to_owned(), "in".to_owned()),
+            ("net_3".to_owned(), "out".to_owned()),
+        ];
+        let rename = pins
+            .iter()
+            .map(|(net, pin)| (net.as_str(), pin.as_str()))
+            .collect::<HashMap<_, _>>();
+        let lines = [
+            "R1 net_1 net_2 1k ".to_owned(),
+            "Xnet_1 net_2 net_3 buf".to_owned(),
+        ];
+        let nested = [".subckt buf a b\nR1 a b 1\n.ends buf\n".to_owned()];
+        let subckt = subckt_from_lines("amp", &pins, &lines, nested.into_iter(), &rename).unwrap();
+        assert_eq!(subckt.pins, ["in", "out"]);
+        // device names are never renamed, even if they look like a net
+        assert_eq!(
+            subckt.definition(),
+            ".subckt amp in out\n.subckt buf a b\nR1 a b 1\n.ends buf\nR1 in net_2 1k \nXnet_1 net_2 out buf\n.ends amp\n"
+        );
+
+        let dup = [
+            ("net_1".to_owned(), "a".to_owned()),
+            ("net_2".to_owned(), "a".to_owned()),
+        ];
+        assert!(subckt_from_lines("amp", &dup, &lines, std::iter::empty(), &rename).is_err());
+        assert!(subckt_from_lines("my amp", &pins, &lines, std::iter::empty(), &rename).is_err());
+        assert!(subckt_from_lines("amp", &[], &lines, std::iter::empty(), &rename).is_err());
+    }
+
+    #[test]
+    fn test_subckt_names() {
+        let body = ".subckt\tamp in out\nR1 in out 1k\n.ends amp\n.SUBCKT buf\nX1 a b buf gain=2\nx2 a b amp params: k=1\n";
+        assert_eq!(
+            subckt_names(body).collect::<Vec<_>>(),
+            ["amp", "buf", "buf", "amp"]
+        );
+        assert_eq!(subckt_names("R1 a b 1k\n").count(), 0);
+    }
+}
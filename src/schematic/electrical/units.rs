@@ -0,0 +1,245 @@
+//! spice numbers and engineering notation
+//! spice values are a number followed by an optional scale suffix, e.g. `1k`, `4.7u` or `2meg`
+//! letters after the suffix are ignored, so that `10kohm` reads as 10e3.
+//! values are shown with an engineering prefix and unit, e.g. `4.7 kΩ`, in a form which
+//! [`parse_eng`] reads back: mega is written `Meg`, as `M` is milli to spice.
+
+use bevy::prelude::*;
+use num::complex::Complex64;
+
+/// scale suffixes recognised by spice, longest first so that `meg` and `mil` win over `m`
+const SUFFIXES: [(&str, f64); 10] = [
+    ("meg", 1e6),
+    ("mil", 25.4e-6),
+    ("t", 1e12),
+    ("g", 1e9),
+    ("k", 1e3),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+];
+
+/// parse a spice number such as `1k`, `-4.7e-3` or `2MEG`
+pub fn parse_spice_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let len = number_len(s);
+    if len == 0 {
+        return None;
+    }
+    let value = s[..len].parse::<f64>().ok()?;
+    let rest = s[len..].to_ascii_lowercase();
+    if !rest.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let scale = SUFFIXES
+        .iter()
+        .find(|(sfx, _)| rest.starts_with(sfx))
+        .map_or(1.0, |(_, scale)| *scale);
+    Some(value * scale)
+}
+
+/// prefixes used in formatting, by power of ten
+const PREFIXES: [(i32, &str); 10] = [
+    (12, "T"),
+    (9, "G"),
+    (6, "Meg"),
+    (3, "k"),
+    (0, ""),
+    (-3, "m"),
+    (-6, "µ"),
+    (-9, "n"),
+    (-12, "p"),
+    (-15, "f"),
+];
+
+/// `value` to 3 significant digits with an engineering prefix and `unit`, e.g. `4.7 kΩ`
+/// values out of the prefix range are written in scientific notation
+pub fn format_eng(value: f64, unit: &str) -> String {
+    let with_unit = |number: String, prefix: &str| match unit {
+        "" => format!("{}{}", number, prefix),
+        _ => format!("{} {}{}", number, prefix, unit),
+    };
+    if value == 0.0 || !value.is_finite() {
+        return with_unit(value.to_string(), "");
+    }
+    // round first, so that 999.96 is written 1k rather than 1000
+    let digits = value.abs().log10().floor() as i32;
+    let scale = 10f64.powi(2 - digits);
+    let rounded = (value * scale).round() / scale;
+    let exp = (rounded.abs().log10().floor() as i32).div_euclid(3) * 3;
+    let Some((_, prefix)) = PREFIXES.iter().find(|(e, _)| *e == exp) else {
+        return with_unit(format!("{:.2e}", value), "");
+    };
+    let mantissa = rounded / 10f64.powi(exp);
+    let decimals = match mantissa.abs() {
+        m if m >= 100.0 => 0,
+        m if m >= 10.0 => 1,
+        _ => 2,
+    };
+    let number = format!("{:.*}", decimals, mantissa);
+    let number = match number.contains('.') {
+        true => number.trim_end_matches('0').trim_end_matches('.'),
+        false => &number,
+    };
+    with_unit(number.to_owned(), prefix)
+}
+
+/// parse a value as written by [`format_eng`] or as a spice number, e.g. `4.7 kΩ`, `4.7k`,
+/// `10 µF` or `1MegHz`
+/// `unit` is matched case-sensitively, so that `1f` stays a femto and `1 F` is a farad
+pub fn parse_eng(s: &str, unit: &str) -> Option<f64> {
+    let s = s.trim();
+    let lower = s.to_ascii_lowercase();
+    let s = match (unit, s.strip_suffix(unit)) {
+        ("", _) => s,
+        (_, Some(stripped)) => stripped,
+        ("Ω", None) if lower.ends_with("ohm") => &s[..s.len() - 3],
+        _ => s,
+    };
+    let s = s.split_whitespace().collect::<String>().replace('µ', "u");
+    parse_spice_number(&s)
+}
+
+/// how complex values are shown
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub enum ComplexFormat {
+    /// magnitude and phase, e.g. `1.2 V ∠-45°`
+    #[default]
+    Polar,
+    /// real and imaginary parts, e.g. `850 mV - j850 mV`
+    Rectangular,
+}
+
+impl ComplexFormat {
+    pub const ALL: [ComplexFormat; 2] = [ComplexFormat::Polar, ComplexFormat::Rectangular];
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComplexFormat::Polar => "polar",
+            ComplexFormat::Rectangular => "rectangular",
+        }
+    }
+}
+
+/// complex value in engineering notation, see [`format_eng`]
+pub fn format_complex(value: Complex64, unit: &str, format: ComplexFormat) -> String {
+    match format {
+        ComplexFormat::Polar => format!(
+            "{} ∠{:.1}°",
+            format_eng(value.norm(), unit),
+            value.arg().to_degrees()
+        ),
+        ComplexFormat::Rectangular => format!(
+            "{} {} j{}",
+            format_eng(value.re, unit),
+            if value.im < 0.0 { '-' } else { '+' },
+            format_eng(value.im.abs(), unit)
+        ),
+    }
+}
+
+/// length of the leading float literal in `s`, 0 if there is none
+fn number_len(s: &str) -> usize {
+    let b = s.as_bytes();
+    let digits = |mut i: usize| {
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = 0;
+    if i < b.len() && (b[i] == b'+' || b[i] == b'-') {
+        i += 1;
+    }
+    let int_end = digits(i);
+    let mut end = int_end;
+    if end < b.len() && b[end] == b'.' {
+        end = digits(end + 1);
+    }
+    // must contain at least one digit
+    if end == i || (end == i + 1 && int_end == i) {
+        return 0;
+    }
+    // exponent, only if followed by digits
+    if end < b.len() && (b[end] == b'e' || b[end] == b'E') {
+        let mut j = end + 1;
+        if j < b.len() && (b[j] == b'+' || b[j] == b'-') {
+            j += 1;
+        }
+        let exp_end = digits(j);
+        if exp_end > j {
+            end = exp_end;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spice_number() {
+        assert_eq!(parse_spice_number("1"), Some(1.0));
+        assert_eq!(parse_spice_number("-2.5"), Some(-2.5));
+        assert_eq!(parse_spice_number("1k"), Some(1e3));
+        assert_eq!(parse_spice_number("2MEG"), Some(2e6));
+        assert_eq!(parse_spice_number("3m"), Some(3e-3));
+        assert_eq!(parse_spice_number("1e-3"), Some(1e-3));
+        assert_eq!(parse_spice_number("10kohm"), Some(10e3));
+        assert_eq!(parse_spice_number(".5u"), Some(0.5e-6));
+        assert_eq!(parse_spice_number("1mil"), Some(25.4e-6));
+        assert_eq!(parse_spice_number("5V"), Some(5.0));
+        assert_eq!(parse_spice_number("k"), None);
+        assert_eq!(parse_spice_number(""), None);
+        assert_eq!(parse_spice_number("1k2"), None);
+        assert_eq!(parse_spice_number("SIN(3.3"), None);
+    }
+
+    #[test]
+    fn test_format_eng() {
+        assert_eq!(format_eng(3.3, "V"), "3.3 V");
+        assert_eq!(format_eng(4700.0, "Ω"), "4.7 kΩ");
+        assert_eq!(format_eng(-1.2345e-3, "A"), "-1.23 mA");
+        assert_eq!(format_eng(1e6, "Hz"), "1 MegHz");
+        assert_eq!(format_eng(999.96, "V"), "1 kV");
+        assert_eq!(format_eng(220e-12, "F"), "220 pF");
+        assert_eq!(format_eng(0.0, "W"), "0 W");
+        assert_eq!(format_eng(47e3, ""), "47k");
+        assert_eq!(format_eng(1.5, ""), "1.5");
+        assert_eq!(format_eng(1e-18, "F"), "1.00e-18 F");
+    }
+
+    #[test]
+    fn test_parse_eng() {
+        assert_eq!(parse_eng("4.7 kΩ", "Ω"), Some(4.7e3));
+        assert_eq!(parse_eng("10kohm", "Ω"), Some(10e3));
+        assert_eq!(parse_eng("1 MegHz", "Hz"), Some(1e6));
+        assert_eq!(parse_eng("1mV", "V"), Some(1e-3));
+        assert_eq!(parse_eng("1 µF", "F"), Some(1e-6));
+        assert_eq!(parse_eng("1f", "F"), Some(1e-15));
+        assert_eq!(parse_eng("1 F", "F"), Some(1.0));
+        assert_eq!(parse_eng("2.5", ""), Some(2.5));
+        assert_eq!(parse_eng("V", "V"), None);
+        for v in [3.3, 4.7e3, 1e6, 220e-12, -1.23e-3] {
+            for unit in ["V", "Ω", "Hz", "F", ""] {
+                let back = parse_eng(&format_eng(v, unit), unit).unwrap();
+                assert!((back - v).abs() <= v.abs() * 1e-9, "{} {}", v, unit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_complex() {
+        let c = Complex64::new(1.0, -1.0);
+        assert_eq!(
+            format_complex(c, "V", ComplexFormat::Polar),
+            "1.41 V ∠-45.0°"
+        );
+        assert_eq!(
+            format_complex(c * 0.5, "V", ComplexFormat::Rectangular),
+            "500 mV - j500 mV"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use self::{
     camera::CameraPlugin, electrical::ElementsPlugin, guides::GuidesPlugin, infotext::InfoPlugin,
-    material::SchematicMaterial, tools::ToolsPlugin,
+    material::SchematicMaterial, symbol::SymbolEditorPlugin, tools::ToolsPlugin,
 };
 use bevy::{
     prelude::*,
@@ -10,11 +10,13 @@ use bevy_save::prelude::*;
 use electrical::SchematicElement;
 use ui::SchematicUiPlugin;
 
+pub mod batch;
 mod camera;
 mod electrical;
 mod guides;
 mod infotext;
 mod material;
+mod symbol;
 mod tools;
 mod ui;
 // Snapped marker component: system to goes around snapping transform of such entities
@@ -36,6 +38,12 @@ pub struct SchematicChanged;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
 pub struct SnapSet;
 
+/// [`SystemSet`] for systems which edit, checkpoint or simulate the schematic.
+/// Does not run while the schematic is stashed for the symbol editor.
+/// Configured in [`PreUpdate`] and [`Update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub struct SchematicSet;
+
 /// SystemSet for deserializing
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum EntityLoadSet {
@@ -60,40 +68,62 @@ impl Plugin for SchematicPlugin {
             GuidesPlugin,
             ElementsPlugin,
             ToolsPlugin,
+            SymbolEditorPlugin,
             SchematicUiPlugin,
         ));
         app.configure_sets(
             PostUpdate,
             SnapSet.before(bevy::transform::TransformSystem::TransformPropagate),
         );
+        app.configure_sets(
+            PreUpdate,
+            SchematicSet.run_if(in_state(symbol::EditorState::Schematic)),
+        );
+        app.configure_sets(
+            Update,
+            SchematicSet.run_if(in_state(symbol::EditorState::Schematic)),
+        );
         app.add_systems(Update, snap.in_set(SnapSet));
         app.add_systems(PostStartup, register_checkpoint); // so we can rollback to initial state
         app.add_systems(
             Update,
-            register_checkpoint.run_if(on_event::<SchematicChanged>()),
+            register_checkpoint
+                .run_if(on_event::<SchematicChanged>())
+                .in_set(SchematicSet),
         );
-        app.add_systems(PreUpdate, process_checkpoints.in_set(EntityLoadSet::Direct));
-        app.add_plugins((
-            // Bevy Save
-            SavePlugins,
-        ));
-        app.configure_sets(
+        // the symbol editor keeps its own undo
+        app.add_systems(
             PreUpdate,
-            (
-                EntityLoadSet::Direct,
-                EntityLoadSet::React
-                    .run_if(on_event::<LoadEvent>())
-                    .after(EntityLoadSet::Direct),
-                EntityLoadSet::Post
-                    .run_if(on_event::<LoadEvent>())
-                    .after(EntityLoadSet::React),
-            ),
+            process_checkpoints
+                .in_set(EntityLoadSet::Direct)
+                .in_set(SchematicSet),
         );
-        app.add_event::<SchematicChanged>();
-        app.add_event::<LoadEvent>();
+        add_save_load(app);
     }
 }
 
+/// saving, loading, and the events and sets reacting to a loaded schematic
+fn add_save_load(app: &mut App) {
+    app.add_plugins((
+        // Bevy Save
+        SavePlugins,
+    ));
+    app.configure_sets(
+        PreUpdate,
+        (
+            EntityLoadSet::Direct,
+            EntityLoadSet::React
+                .run_if(on_event::<LoadEvent>())
+                .after(EntityLoadSet::Direct),
+            EntityLoadSet::Post
+                .run_if(on_event::<LoadEvent>())
+                .after(EntityLoadSet::React),
+        ),
+    );
+    app.add_event::<SchematicChanged>();
+    app.add_event::<LoadEvent>();
+}
+
 /// this system snaps all applicable entities
 fn snap(mut e: Query<(&mut Transform, &Snap), Changed<GlobalTransform>>) {
     for (mut t, s) in e.iter_mut() {
@@ -0,0 +1,603 @@
+/*
+symbol editor: draws device symbols and saves them as device definitions, see [`DeviceDef`]
+the schematic is stashed on entering the editor and restored on leaving it. lines are drawn with
+the wire tool, arcs, circles, ports and the pick box by clicking with the tools of the editor.
+symbols are shown magnified by [`SCALE`], so the cursor grid is in quarter units of the symbol,
+while ports stay on whole units as in the schematic.
+*/
+
+use std::fs;
+
+use bevy::{
+    color::palettes::basic as basic_colors,
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_egui::egui::Color32;
+use bevy_save::prelude::*;
+
+use crate::bevyon::{EmptyMesh, StrokeTessellator};
+
+use super::{
+    electrical::{
+        definition_path, despawn_preview, path_mesh, DeviceDef, DeviceRegistry, ElementsRes,
+        PathCmd, Preview, SchematicElement, SpDeviceType,
+    },
+    guides::{NewSnappedCursorPos, SchematicCursor},
+    tools::SchematicToolState,
+    ui::console::PrintConsoleLine,
+    LoadEvent, SavePipeline,
+};
+
+/// which editor the app is in
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EditorState {
+    #[default]
+    Schematic,
+    Symbol,
+}
+
+/// cursor grid steps per symbol unit
+pub const SCALE: f32 = 4.0;
+/// radius of the marks showing ports
+const PORT_MARK: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolTool {
+    /// straight lines, drawn with the wire tool
+    Line,
+    /// click the center, the start and the end
+    Arc,
+    /// click the center and a point on the circle
+    Circle,
+    /// click to add a port, or to remove the one there
+    Port,
+    /// click two corners
+    Pick,
+}
+
+/// segment drawn by the wire tool in the symbol editor, in cursor grid coordinates
+#[derive(Event)]
+pub struct SymbolLine(pub IVec2, pub IVec2);
+
+/// save the symbol being edited, see [`definition_path`]
+#[derive(Event)]
+pub struct SaveSymbol;
+
+/// the symbol being edited
+#[derive(Resource)]
+pub struct SymbolEditor {
+    pub def: DeviceDef,
+    tool: Option<SymbolTool>,
+    /// points clicked so far with the current tool
+    clicks: Vec<Vec2>,
+}
+
+impl Default for SymbolEditor {
+    fn default() -> Self {
+        Self {
+            def: DeviceDef {
+                name: "symbol".to_owned(),
+                dtype: SpDeviceType::R,
+                key: None,
+                params: String::new(),
+                ports: vec![],
+                pick: [Vec2::new(-1.0, -2.0), Vec2::new(1.0, 2.0)],
+                path: vec![],
+            },
+            tool: None,
+            clicks: vec![],
+        }
+    }
+}
+
+/// where the pen is after drawing `path`
+fn end_point(path: &[PathCmd]) -> Option<Vec2> {
+    let mut start = None;
+    let mut pen = None;
+    for cmd in path.iter() {
+        pen = match *cmd {
+            PathCmd::Move(to) => {
+                start = Some(to);
+                Some(to)
+            }
+            PathCmd::Line(to) | PathCmd::Quad { to, .. } => Some(to),
+            PathCmd::Arc { center, sweep, .. } => {
+                pen.map(|p| center + Vec2::from_angle(sweep.to_radians()).rotate(p - center))
+            }
+            PathCmd::Circle { center, radius } => Some(center - Vec2::Y * radius),
+            PathCmd::Close => start,
+        };
+    }
+    pen
+}
+
+/// angle from `a` to `b` around `center`, in degrees, counterclockwise positive
+fn sweep(center: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let (a, b) = (a - center, b - center);
+    a.perp_dot(b).atan2(a.dot(b)).to_degrees()
+}
+
+/// outline of the box spanned by `a` and `b`
+fn rect(a: Vec2, b: Vec2) -> [PathCmd; 5] {
+    [
+        PathCmd::Move(a),
+        PathCmd::Line(Vec2::new(b.x, a.y)),
+        PathCmd::Line(b),
+        PathCmd::Line(Vec2::new(a.x, b.y)),
+        PathCmd::Close,
+    ]
+}
+
+impl SymbolEditor {
+    /// edit a copy of `def`
+    pub fn open(&mut self, def: DeviceDef) {
+        self.def = def;
+        self.clicks.clear();
+    }
+    pub fn tool(&self) -> Option<SymbolTool> {
+        self.tool
+    }
+    pub fn set_tool(&mut self, tool: Option<SymbolTool>) {
+        self.tool = tool;
+        self.clicks.clear();
+    }
+    /// remove the last drawn statement, with the move it started from
+    pub fn undo(&mut self) {
+        self.def.path.pop();
+        if let Some(PathCmd::Move(_)) = self.def.path.last() {
+            self.def.path.pop();
+        }
+    }
+    /// start a new subpath at `from`, unless the pen is there already
+    fn move_to(&mut self, from: Vec2) {
+        if end_point(&self.def.path) != Some(from) {
+            self.def.path.push(PathCmd::Move(from));
+        }
+    }
+    pub fn line(&mut self, from: Vec2, to: Vec2) {
+        if from != to {
+            self.move_to(from);
+            self.def.path.push(PathCmd::Line(to));
+        }
+    }
+    /// click at `p` with the current tool, in symbol coordinates
+    pub fn click(&mut self, p: Vec2) {
+        let Some(tool) = self.tool else {
+            return;
+        };
+        match (tool, &self.clicks[..]) {
+            (SymbolTool::Line, _) => {}
+            (SymbolTool::Circle, &[center]) => {
+                if p != center {
+                    self.def.path.push(PathCmd::Circle {
+                        center,
+                        radius: p.distance(center),
+                    });
+                }
+                self.clicks.clear();
+            }
+            (SymbolTool::Arc, &[center, start]) => {
+                let sweep = sweep(center, start, p);
+                if sweep != 0.0 {
+                    self.move_to(start);
+                    self.def.path.push(PathCmd::Arc {
+                        center,
+                        radius: start.distance(center),
+                        sweep,
+                    });
+                }
+                self.clicks.clear();
+            }
+            (SymbolTool::Port, _) => {
+                let offset = p.round().as_ivec2();
+                match self.def.ports.iter().position(|(o, _)| *o == offset) {
+                    Some(i) => {
+                        self.def.ports.remove(i);
+                    }
+                    None => self.def.ports.push((offset, String::new())),
+                }
+            }
+            (SymbolTool::Pick, &[corner]) => {
+                self.def.pick = [corner.min(p), corner.max(p)];
+                self.clicks.clear();
+            }
+            (SymbolTool::Arc, &[center]) if p == center => {}
+            _ => self.clicks.push(p),
+        }
+    }
+    /// the statement being drawn, to the cursor at `p`
+    fn pending(&self, p: Vec2) -> Vec<PathCmd> {
+        match (self.tool, &self.clicks[..]) {
+            (Some(SymbolTool::Circle), &[center]) => vec![PathCmd::Circle {
+                center,
+                radius: p.distance(center),
+            }],
+            (Some(SymbolTool::Arc), &[center]) => vec![PathCmd::Move(center), PathCmd::Line(p)],
+            (Some(SymbolTool::Arc), &[center, start]) => vec![
+                PathCmd::Move(start),
+                PathCmd::Arc {
+                    center,
+                    radius: start.distance(center),
+                    sweep: sweep(center, start, p),
+                },
+            ],
+            (Some(SymbolTool::Pick), &[corner]) => rect(corner, p).to_vec(),
+            _ => vec![],
+        }
+    }
+    /// marks of the ports and the pick box
+    fn marks(&self) -> Vec<PathCmd> {
+        let [a, b] = self.def.pick;
+        self.def
+            .ports
+            .iter()
+            .map(|(offset, _)| PathCmd::Circle {
+                center: offset.as_vec2(),
+                radius: PORT_MARK,
+            })
+            .chain(rect(a, b))
+            .collect()
+    }
+}
+
+/// the drawn symbol, and the marks of its ports and pick box
+#[derive(Component)]
+enum SymbolMesh {
+    Symbol,
+    Marks,
+}
+
+/// keeps the schematic while the symbol editor is open
+struct StashPipeline;
+
+impl Pipeline for StashPipeline {
+    type Backend = DefaultDebugBackend;
+    type Format = DefaultDebugFormat;
+
+    type Key<'a> = &'a str;
+
+    fn key(&self) -> Self::Key<'_> {
+        "out/stash"
+    }
+
+    fn capture(builder: SnapshotBuilder) -> Snapshot {
+        SavePipeline::capture(builder)
+    }
+
+    fn apply(world: &mut World, snapshot: &Snapshot) -> Result<(), bevy_save::Error> {
+        SavePipeline::apply(world, snapshot)
+    }
+}
+
+/// whether the schematic is stashed, and is to be restored on leaving the editor
+#[derive(Resource, Default)]
+struct Stashed(bool);
+
+/// report a failed stash or restore in the console
+fn stash_error(world: &mut World, msg: String) {
+    error!("{}", msg);
+    world.send_event(PrintConsoleLine::new(msg, Color32::RED));
+}
+
+/// stash the schematic and show the symbol
+/// the schematic is left as is if it cannot be stashed, and the editor is not entered
+fn enter(world: &mut World) {
+    if let Err(e) = world.save(StashPipeline) {
+        stash_error(world, format!("could not stash the schematic: {:?}", e));
+        world
+            .resource_mut::<NextState<EditorState>>()
+            .set(EditorState::Schematic);
+        return;
+    }
+    world.resource_mut::<Stashed>().0 = true;
+    let elements = world
+        .query_filtered::<Entity, With<SchematicElement>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    for e in elements {
+        if let Some(e) = world.get_entity_mut(e) {
+            e.despawn_recursive();
+        }
+    }
+    let mat = world.resource::<ElementsRes>().mat_dflt.clone();
+    for symbol_mesh in [SymbolMesh::Symbol, SymbolMesh::Marks] {
+        let empty = world.resource::<EmptyMesh>().0.clone();
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(empty);
+        world.spawn((
+            symbol_mesh,
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(mesh),
+                material: mat.clone(),
+                transform: Transform::from_scale(Vec3::splat(SCALE)),
+                ..default()
+            },
+        ));
+    }
+    world.resource_mut::<SymbolEditor>().set_changed();
+    world
+        .resource_mut::<NextState<SchematicToolState>>()
+        .set(SchematicToolState::Idle);
+}
+
+/// hide the symbol and restore the schematic
+fn exit(world: &mut World) {
+    world.resource_mut::<SymbolEditor>().set_tool(None);
+    world
+        .resource_mut::<NextState<SchematicToolState>>()
+        .set(SchematicToolState::Idle);
+    // entering failed, the schematic was never taken away
+    if !std::mem::take(&mut world.resource_mut::<Stashed>().0) {
+        return;
+    }
+    let entities = world
+        .query_filtered::<Entity, Or<(With<SymbolMesh>, With<SchematicElement>)>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    for e in entities {
+        if let Some(e) = world.get_entity_mut(e) {
+            e.despawn_recursive();
+        }
+    }
+    if let Err(e) = world.load(StashPipeline) {
+        stash_error(world, format!("could not restore the schematic: {:?}", e));
+        return;
+    }
+    world.send_event(LoadEvent);
+}
+
+const LINE_TOOL_KEY: KeyCode = KeyCode::KeyW;
+const ARC_TOOL_KEY: KeyCode = KeyCode::KeyA;
+const CIRCLE_TOOL_KEY: KeyCode = KeyCode::KeyO;
+const PORT_TOOL_KEY: KeyCode = KeyCode::KeyP;
+const PICK_TOOL_KEY: KeyCode = KeyCode::KeyB;
+/// select the tools of the symbol editor, the line tool being the wire tool
+fn tools_select(
+    mut evt_keys: EventReader<KeyboardInput>,
+    mut editor: ResMut<SymbolEditor>,
+    mut toolst_next: ResMut<NextState<SchematicToolState>>,
+) {
+    let tool = evt_keys
+        .read()
+        .filter(|ki| ki.state == ButtonState::Released)
+        .find_map(|ki| match ki.key_code {
+            LINE_TOOL_KEY => Some(SymbolTool::Line),
+            ARC_TOOL_KEY => Some(SymbolTool::Arc),
+            CIRCLE_TOOL_KEY => Some(SymbolTool::Circle),
+            PORT_TOOL_KEY => Some(SymbolTool::Port),
+            PICK_TOOL_KEY => Some(SymbolTool::Pick),
+            _ => None,
+        });
+    if let Some(tool) = tool {
+        editor.set_tool(Some(tool));
+        toolst_next.set(match tool {
+            SymbolTool::Line => SchematicToolState::Wiring,
+            _ => SchematicToolState::Idle,
+        });
+    }
+}
+
+/// clicks for the tools other than lines
+fn main(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    qc: Query<&SchematicCursor>,
+    mut editor: ResMut<SymbolEditor>,
+) {
+    if keys.just_pressed(KeyCode::KeyZ) && keys.pressed(KeyCode::ControlLeft) {
+        editor.undo();
+    }
+    if keys.just_released(KeyCode::Escape) {
+        let tool = match editor.clicks.is_empty() {
+            true => None,
+            false => editor.tool,
+        };
+        editor.set_tool(tool);
+    }
+    let Some(coords) = &qc.single().coords else {
+        return;
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        editor.click(coords.get_snapped_coords_float() / SCALE);
+    }
+}
+
+/// lines drawn by the wire tool
+fn add_lines(mut e_line: EventReader<SymbolLine>, mut editor: ResMut<SymbolEditor>) {
+    for SymbolLine(from, to) in e_line.read() {
+        editor.line(from.as_vec2() / SCALE, to.as_vec2() / SCALE);
+    }
+}
+
+/// the wire tool was left, e.g. on escape or for another tool
+fn wire_tool_exit(
+    mut commands: Commands,
+    mut editor: ResMut<SymbolEditor>,
+    eqsp: Query<Entity, (With<SchematicElement>, With<Preview>)>,
+) {
+    despawn_preview(&mut commands, &eqsp);
+    if editor.tool == Some(SymbolTool::Line) {
+        editor.set_tool(None);
+    }
+}
+
+/// redraw the symbol as it is edited, and the statement being drawn as the cursor moves
+fn redraw(
+    editor: Res<SymbolEditor>,
+    mut e_newsc: EventReader<NewSnappedCursorPos>,
+    qc: Query<&SchematicCursor>,
+    q_meshes: Query<(&Mesh2dHandle, &SymbolMesh)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+) {
+    if e_newsc.read().last().is_none() && !editor.is_changed() {
+        return;
+    }
+    let pending = qc
+        .single()
+        .coords
+        .as_ref()
+        .map(|coords| editor.pending(coords.get_snapped_coords_float() / SCALE))
+        .unwrap_or_default();
+    for (handle, symbol_mesh) in q_meshes.iter() {
+        let mesh = match symbol_mesh {
+            SymbolMesh::Symbol => {
+                let path = [editor.def.path.clone(), pending.clone()].concat();
+                path_mesh(&path, basic_colors::GREEN.into(), &mut stroke_tess)
+            }
+            SymbolMesh::Marks => path_mesh(
+                &editor.marks(),
+                basic_colors::YELLOW.into(),
+                &mut stroke_tess,
+            ),
+        };
+        meshes.insert(&handle.0, mesh);
+    }
+}
+
+/// validate the symbol, write its definition and make it placeable
+fn save(
+    editor: Res<SymbolEditor>,
+    mut registry: ResMut<DeviceRegistry>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut e_console: EventWriter<PrintConsoleLine>,
+) {
+    let name = editor.def.name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        e_console.send(PrintConsoleLine::new(
+            "symbol: names may only hold letters, digits, - and _".to_owned(),
+            Color32::RED,
+        ));
+        return;
+    }
+    let text = editor.def.to_text();
+    let def = match DeviceDef::parse(name, &text) {
+        Ok(def) => def,
+        Err(e) => {
+            e_console.send(PrintConsoleLine::new(format!("symbol {}", e), Color32::RED));
+            return;
+        }
+    };
+    let path = definition_path(name);
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, text));
+    let line = match written {
+        Ok(()) => {
+            registry.define(def, &mut stroke_tess, &mut meshes);
+            PrintConsoleLine::new(
+                format!("saved symbol {} to {}", name, path.display()),
+                Color32::GREEN,
+            )
+        }
+        Err(e) => PrintConsoleLine::new(format!("symbol {}: {}", path.display(), e), Color32::RED),
+    };
+    e_console.send(line);
+}
+
+pub struct SymbolEditorPlugin;
+
+impl Plugin for SymbolEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<EditorState>();
+        app.init_resource::<SymbolEditor>();
+        app.init_resource::<Stashed>();
+        app.add_event::<SymbolLine>();
+        app.add_event::<SaveSymbol>();
+        app.add_systems(OnEnter(EditorState::Symbol), enter);
+        app.add_systems(OnExit(EditorState::Symbol), exit);
+        app.add_systems(
+            OnExit(SchematicToolState::Wiring),
+            wire_tool_exit.run_if(in_state(EditorState::Symbol)),
+        );
+        app.add_systems(
+            PreUpdate,
+            (
+                tools_select,
+                main.run_if(in_state(SchematicToolState::Idle)),
+                add_lines,
+            )
+                .run_if(in_state(EditorState::Symbol)),
+        );
+        app.add_systems(
+            Update,
+            (redraw, save.run_if(on_event::<SaveSymbol>())).run_if(in_state(EditorState::Symbol)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tools() {
+        let mut editor = SymbolEditor::default();
+        editor.line(Vec2::new(0.0, 3.0), Vec2::new(0.0, 1.0));
+        editor.line(Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0));
+        assert_eq!(
+            editor.def.path,
+            [
+                PathCmd::Move(Vec2::new(0.0, 3.0)),
+                PathCmd::Line(Vec2::new(0.0, 1.0)),
+                PathCmd::Line(Vec2::new(1.0, 1.0)),
+            ]
+        );
+
+        editor.set_tool(Some(SymbolTool::Arc));
+        editor.click(Vec2::ZERO);
+        editor.click(Vec2::new(1.0, 0.0));
+        editor.click(Vec2::new(0.0, -1.0));
+        assert_eq!(
+            editor.def.path[3..],
+            [
+                PathCmd::Move(Vec2::new(1.0, 0.0)),
+                PathCmd::Arc {
+                    center: Vec2::ZERO,
+                    radius: 1.0,
+                    sweep: -90.0
+                },
+            ]
+        );
+        assert!(
+            end_point(&editor.def.path)
+                .unwrap()
+                .distance(Vec2::new(0.0, -1.0))
+                < 1e-6
+        );
+        editor.undo();
+        assert_eq!(editor.def.path.len(), 3);
+
+        editor.set_tool(Some(SymbolTool::Circle));
+        editor.click(Vec2::ZERO);
+        editor.click(Vec2::new(0.0, 1.5));
+        assert_eq!(
+            editor.def.path.last(),
+            Some(&PathCmd::Circle {
+                center: Vec2::ZERO,
+                radius: 1.5
+            })
+        );
+
+        editor.set_tool(Some(SymbolTool::Port));
+        editor.click(Vec2::new(0.25, 2.75));
+        editor.click(Vec2::new(0.0, -3.0));
+        editor.click(Vec2::new(0.0, -3.0));
+        assert_eq!(editor.def.ports, [(IVec2::new(0, 3), String::new())]);
+
+        editor.set_tool(Some(SymbolTool::Pick));
+        editor.click(Vec2::new(1.0, 2.0));
+        editor.click(Vec2::new(-1.0, -2.0));
+        assert_eq!(
+            editor.def.pick,
+            [Vec2::new(-1.0, -2.0), Vec2::new(1.0, 2.0)]
+        );
+    }
+}
@@ -1,8 +1,21 @@
-use bevy::prelude::*;
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
 
-use crate::schematic::{
-    electrical::{spawn_preview_device_from_type, DefaultDevices, ElementsRes},
-    guides::SchematicCursor,
+use crate::{
+    bevyon::StrokeTessellator,
+    schematic::{
+        electrical::{
+            spawn_preview_device_from_type, spawn_preview_directive, spawn_preview_subckt,
+            DeviceRegistry, ElementsRes, SpawnDevice, SpawnSubckt, SubcktSymbols,
+        },
+        guides::SchematicCursor,
+        SchematicSet,
+    },
 };
 
 use super::{transform::TransformType, SchematicToolState};
@@ -13,48 +26,100 @@ impl Plugin for DeviceSpawnToolPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             PreUpdate,
-            main.run_if(in_state(SchematicToolState::DeviceSpawn)),
+            (
+                main.run_if(in_state(SchematicToolState::DeviceSpawn)),
+                spawn_subckt.run_if(not(in_state(SchematicToolState::Transform))),
+                spawn_device.run_if(not(in_state(SchematicToolState::Transform))),
+            )
+                .in_set(SchematicSet),
         );
     }
 }
 
+/// place the device whose definition is keyed by the typed character, see [`DeviceRegistry::by_key`]
 fn main(
-    keys: Res<ButtonInput<KeyCode>>,
+    mut e_key: EventReader<KeyboardInput>,
     mut ntool_st: ResMut<NextState<SchematicToolState>>,
     mut ntransform_st: ResMut<NextState<TransformType>>,
-    dd: Res<DefaultDevices>,
+    registry: Res<DeviceRegistry>,
     mut commands: Commands,
     eres: Res<ElementsRes>,
     cursor: Query<Entity, With<SchematicCursor>>,
 ) {
-    if keys.just_pressed(KeyCode::KeyG)
-        || keys.just_pressed(KeyCode::KeyV)
-        || keys.just_pressed(KeyCode::KeyI)
-        || keys.just_pressed(KeyCode::KeyR)
-        || keys.just_pressed(KeyCode::KeyL)
-        || keys.just_pressed(KeyCode::KeyC)
-    {
-        let device_entity = spawn_preview_device_from_type(
-            if keys.just_pressed(KeyCode::KeyV) {
-                dd.voltage_source()
-            } else if keys.just_pressed(KeyCode::KeyI) {
-                dd.current_source()
-            } else if keys.just_pressed(KeyCode::KeyR) {
-                dd.resistor()
-            } else if keys.just_pressed(KeyCode::KeyL) {
-                dd.inductor()
-            } else if keys.just_pressed(KeyCode::KeyC) {
-                dd.capacitor()
-            } else {
-                dd.gnd()
-            },
-            &mut commands,
-            &eres,
-        );
+    let Some(key) = e_key
+        .read()
+        .filter(|ev| ev.state == ButtonState::Pressed)
+        .filter_map(|ev| match &ev.logical_key {
+            Key::Character(c) => Some(c.clone()),
+            _ => None,
+        })
+        .last()
+    else {
+        return;
+    };
+    if let Some(device) = registry.by_key(&key) {
+        let device_entity = spawn_preview_device_from_type(device, &mut commands, &eres);
         commands
             .entity(cursor.single())
             .push_children(&device_entity);
         ntool_st.set(SchematicToolState::Transform);
         ntransform_st.set(TransformType::Copy);
+    } else if key == "." {
+        // simulation directive, text is edited in the params editor
+        let entities = spawn_preview_directive(".param".to_owned(), &mut commands, &eres);
+        commands.entity(cursor.single()).push_children(&entities);
+        ntool_st.set(SchematicToolState::Transform);
+        ntransform_st.set(TransformType::Copy);
     }
 }
+
+/// place a device picked from the device list
+fn spawn_device(
+    mut e_spawn: EventReader<SpawnDevice>,
+    mut ntool_st: ResMut<NextState<SchematicToolState>>,
+    mut ntransform_st: ResMut<NextState<TransformType>>,
+    mut commands: Commands,
+    eres: Res<ElementsRes>,
+    registry: Res<DeviceRegistry>,
+    cursor: Query<Entity, With<SchematicCursor>>,
+) {
+    let Some(device) = e_spawn
+        .read()
+        .last()
+        .and_then(|ev| registry.get(ev.get_name()))
+    else {
+        return;
+    };
+    let entities = spawn_preview_device_from_type(device, &mut commands, &eres);
+    commands.entity(cursor.single()).push_children(&entities);
+    ntool_st.set(SchematicToolState::Transform);
+    ntransform_st.set(TransformType::Copy);
+}
+
+/// place a subcircuit instance created from the subcircuit editor
+fn spawn_subckt(
+    mut e_spawn: EventReader<SpawnSubckt>,
+    mut ntool_st: ResMut<NextState<SchematicToolState>>,
+    mut ntransform_st: ResMut<NextState<TransformType>>,
+    mut commands: Commands,
+    eres: Res<ElementsRes>,
+    mut symbols: ResMut<SubcktSymbols>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cursor: Query<Entity, With<SchematicCursor>>,
+) {
+    let Some(ev) = e_spawn.read().last() else {
+        return;
+    };
+    let entities = spawn_preview_subckt(
+        ev.get_subckt().clone(),
+        &mut commands,
+        &eres,
+        &mut symbols,
+        &mut stroke_tess,
+        &mut meshes,
+    );
+    commands.entity(cursor.single()).push_children(&entities);
+    ntool_st.set(SchematicToolState::Transform);
+    ntransform_st.set(TransformType::Copy);
+}
@@ -2,6 +2,7 @@ use bevy::{prelude::*, sprite::Mesh2dHandle, text::TextLayoutInfo};
 use bevy_save::prelude::*;
 
 mod devicespawn;
+mod probe;
 mod sel;
 mod transform;
 mod wire;
@@ -18,11 +19,12 @@ pub use sel::{NewPickingCollider, PickingCollider, SelectEvt};
 pub enum SchematicToolState {
     #[default]
     Idle, // also select
-    Wiring,    // for drawing wires
-    Transform, // moving elements around,
+    Wiring,      // for drawing wires
+    Transform,   // moving elements around,
     DeviceSpawn, // for spawning a new device,
-               // Label,   // wire/net labeling
-               // Comment, // plain text comment with basic formatting options
+    Probe,       // for probing net voltages and device currents
+                 // Label,   // wire/net labeling
+                 // Comment, // plain text comment with basic formatting options
 }
 
 #[derive(Event)]
@@ -37,6 +39,7 @@ impl Plugin for ToolsPlugin {
             sel::SelToolPlugin,
             transform::TransformToolPlugin,
             devicespawn::DeviceSpawnToolPlugin,
+            probe::ProbeToolPlugin,
         ));
         app.init_state::<SchematicToolState>();
         app.add_event::<MergeLoadEvent>();
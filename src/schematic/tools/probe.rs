@@ -0,0 +1,68 @@
+/*
+tool for probing net voltages and device currents
+clicking a probed element again removes its probe
+*/
+
+use bevy::prelude::*;
+use bevy_egui::egui::Color32;
+
+use crate::schematic::{
+    electrical::{probe_trace, NetId, Picked, Probe, SchematicElement, SpDeviceId},
+    guides::NewSnappedCursorPos,
+    ui::console::PrintConsoleLine,
+};
+
+use super::{NewPickingCollider, SchematicToolState};
+
+pub struct ProbeToolPlugin;
+
+impl Plugin for ProbeToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, main.run_if(in_state(SchematicToolState::Probe)));
+    }
+}
+
+fn main(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut next_schematictoolstate: ResMut<NextState<SchematicToolState>>,
+    mut e_newsc: EventReader<NewSnappedCursorPos>,
+    mut e_newpc: EventWriter<NewPickingCollider>,
+    q_picked: Query<
+        (
+            Entity,
+            &SchematicElement,
+            Option<&SpDeviceId>,
+            Option<&NetId>,
+            Has<Probe>,
+        ),
+        With<Picked>,
+    >,
+    mut commands: Commands,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+) {
+    if keys.just_released(KeyCode::Escape) {
+        next_schematictoolstate.set(SchematicToolState::Idle);
+        return;
+    }
+    // pick whatever is under the cursor
+    if let Some(NewSnappedCursorPos(Some(coords))) = e_newsc.read().last() {
+        e_newpc.send(NewPickingCollider::point(coords.get_snapped_coords_float()));
+    }
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    for (e, se, spdid, netid, probed) in q_picked.iter() {
+        let Some(trace) = probe_trace(se, spdid, netid) else {
+            continue;
+        };
+        let msg = if probed {
+            commands.entity(e).remove::<Probe>();
+            format!("probe removed: {}", trace.get_label())
+        } else {
+            commands.entity(e).insert(Probe);
+            format!("probe added: {}", trace.get_label())
+        };
+        e_console_rgstr.send(PrintConsoleLine::new(msg, Color32::GRAY));
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
         guides::{NewSnappedCursorPos, SchematicCursor},
         material::SchematicMaterial,
         tools::ToolsPreviewPipeline,
-        EntityLoadSet, LoadEvent, SchematicChanged,
+        EntityLoadSet, LoadEvent, SchematicChanged, SchematicSet,
     },
 };
 use bevy::{
@@ -98,6 +98,7 @@ impl Plugin for SelToolPlugin {
             (
                 tools_select
                     .in_set(EntityLoadSet::Direct)
+                    .in_set(SchematicSet)
                     .run_if(in_state(SchematicToolState::Idle)),
                 (save_load, post_serde)
                     .chain()
@@ -170,6 +171,7 @@ const WIRE_TOOL_KEY: KeyCode = KeyCode::KeyW;
 const DEVICE_SPAWN_TOOL_KEY: KeyCode = KeyCode::KeyD;
 const MOVE_KEY: KeyCode = KeyCode::KeyM;
 const COPY_KEY: KeyCode = KeyCode::KeyC;
+const PROBE_TOOL_KEY: KeyCode = KeyCode::KeyP;
 fn tools_select(
     commands: Commands,
     mut evt_keys: EventReader<KeyboardInput>,
@@ -209,6 +211,12 @@ fn tools_select(
     {
         debug!("selecting device spawn tool");
         toolst_next.set(SchematicToolState::DeviceSpawn);
+    } else if evt_keys
+        .iter()
+        .any(|ki| ki.key_code == PROBE_TOOL_KEY && ki.state == ButtonState::Released)
+    {
+        debug!("selecting probe tool");
+        toolst_next.set(SchematicToolState::Probe);
     }
     // } else if evt_keys
     //     .iter()
@@ -3,7 +3,7 @@ use std::f32::consts::PI;
 use bevy::prelude::*;
 
 use crate::schematic::{
-    electrical::{self, NetId, Preview, SchematicElement, Selected, SpDeviceId},
+    electrical::{self, NetId, Preview, Probe, SchematicElement, Selected, SpDeviceId},
     guides::SchematicCursor,
     SchematicChanged,
 };
@@ -67,9 +67,13 @@ fn main(
     if buttons.just_pressed(MouseButton::Left) {
         match st.get() {
             TransformType::Copy => {
-                // delete spid component on new copies
+                // delete spid component on new copies, copies are not probed
                 for c in children.iter() {
-                    commands.entity(*c).remove::<SpDeviceId>().remove::<NetId>();
+                    commands
+                        .entity(*c)
+                        .remove::<SpDeviceId>()
+                        .remove::<NetId>()
+                        .remove::<Probe>();
                 }
             }
             TransformType::Move => {
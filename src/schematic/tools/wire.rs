@@ -7,6 +7,7 @@ use bevy::prelude::*;
 use crate::schematic::{
     electrical::{self, ElementsRes, Preview, SchematicElement},
     guides::{NewSnappedCursorPos, SchematicCursor},
+    symbol::{EditorState, SymbolLine},
     SchematicChanged,
 };
 
@@ -46,6 +47,8 @@ fn main(
     eres: Res<ElementsRes>,
     eqsp: Query<Entity, (With<SchematicElement>, With<Preview>)>,
     mut notify_changed: EventWriter<SchematicChanged>,
+    editorstate: Res<State<EditorState>>,
+    mut e_symbol_line: EventWriter<SymbolLine>,
 ) {
     // system should be set to run if schematic tool state is wiring
     // main purpose is to manage WireToolState
@@ -57,6 +60,8 @@ fn main(
         next_schematictoolstate.set(SchematicToolState::Idle);
         return;
     }
+    // in the symbol editor, lines go straight and are kept by the editor rather than as nets
+    let symbol = *editorstate.get() == EditorState::Symbol;
     // let now_curpos = coords.get_snapped_coords();
     match wiretoolstate.get() {
         WireToolState::Ready => {
@@ -65,14 +70,18 @@ fn main(
             }
         }
         WireToolState::Drawing(src) => {
-            if buttons.just_pressed(MouseButton::Left) {
+            if buttons.just_pressed(MouseButton::Left) && symbol {
+                electrical::despawn_preview(&mut commands, &eqsp);
+                e_symbol_line.send(SymbolLine(*src, coords.get_snapped_coords()));
+                next_wiretoolstate.set(WireToolState::Drawing(coords.get_snapped_coords()));
+            } else if buttons.just_pressed(MouseButton::Left) {
                 electrical::persist_preview(&mut commands, &eqsp);
                 next_wiretoolstate.set(WireToolState::Drawing(coords.get_snapped_coords()));
                 notify_changed.send(SchematicChanged);
             } else if let Some(NewSnappedCursorPos(Some(c))) = e_newsc.read().last() {
                 debug!("despawning preview - new curpos");
                 electrical::despawn_preview(&mut commands, &eqsp);
-                compute_preview(&mut commands, eres, *src, c.get_snapped_coords());
+                compute_preview(&mut commands, eres, *src, c.get_snapped_coords(), symbol);
             }
         }
     }
@@ -91,8 +100,17 @@ fn route(
 }
 
 /// this system computes the preview entities and adds them to world with preview
-fn compute_preview(mut commands: &mut Commands, eres: Res<ElementsRes>, src: IVec2, dst: IVec2) {
-    let path = route(src, dst, None);
+fn compute_preview(
+    mut commands: &mut Commands,
+    eres: Res<ElementsRes>,
+    src: IVec2,
+    dst: IVec2,
+    straight: bool,
+) {
+    let path = match straight {
+        true => vec![src, dst],
+        false => route(src, dst, None),
+    };
 
     // filter redundant nodes - necessary to avoid solder dots where crossing another net segment
     let mut simple_path = Vec::with_capacity(path.len());
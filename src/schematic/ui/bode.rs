@@ -0,0 +1,122 @@
+//! bode plot: magnitude (dB) and phase (degrees) of the latest ac sweep over log frequency
+//! all steps of a stepped run are overlaid
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::electrical::{step_label, Probes, SimPlot, SimResults, SimVector};
+
+use super::plot::{is_shown, trace_color, trace_name, trace_selector, Plot, PlotView, Trace};
+
+#[derive(Resource, Default)]
+pub struct BodeViewer {
+    /// names of the vectors to plot
+    selected: BTreeSet<String>,
+    /// shared between magnitude and phase plots so they stay aligned
+    view: PlotView,
+}
+
+pub fn bode_ui(
+    mut egui_context: EguiContexts,
+    results: Res<SimResults>,
+    probes: Res<Probes>,
+    mut viewer: ResMut<BodeViewer>,
+) {
+    let steps = results.latest_steps("ac");
+    let Some(plot) = steps.first().filter(|p| !p.is_single_point()) else {
+        return;
+    };
+    let viewer = &mut *viewer;
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("bode plot")
+        .default_size([600.0, 400.0])
+        .show(ctx, |ui| {
+            trace_selector(
+                ui,
+                plot.vectors()
+                    .filter(|v| v.imag().is_some())
+                    .map(|v| v.get_name()),
+                &mut viewer.selected,
+                &probes,
+            );
+            if ui.button("fit").clicked() {
+                viewer.view.fit();
+            }
+            let (mag, phase) = bode_traces(steps, &viewer.selected, &probes);
+            let height = (ui.available_height() / 2.0).max(120.0);
+            Plot::new(&mut viewer.view)
+                .log_x(true)
+                .height(height)
+                .labels("", "magnitude (dB)")
+                .show(ui, &mag);
+            Plot::new(&mut viewer.view)
+                .log_x(true)
+                .height(height)
+                .labels("frequency (Hz)", "phase (deg)")
+                .show(ui, &phase);
+        });
+}
+
+/// magnitude and phase traces for each shown complex vector of each step
+fn bode_traces(
+    steps: &[SimPlot],
+    selected: &BTreeSet<String>,
+    probes: &Probes,
+) -> (Vec<Trace>, Vec<Trace>) {
+    steps
+        .iter()
+        .enumerate()
+        .filter_map(|(k, plot)| Some((k, plot, plot.scale()?)))
+        .flat_map(|(k, plot, freq)| {
+            plot.vectors()
+                .filter(|v| v.imag().is_some() && is_shown(v.get_name(), selected, probes))
+                .map(move |v| (k, plot, freq, v))
+        })
+        .enumerate()
+        .map(|(i, (k, plot, freq, v))| {
+            let (mag, phase) = bode_points(freq.real(), v);
+            let name = match steps.len() {
+                1 => trace_name(v.get_name(), probes).to_owned(),
+                _ => format!(
+                    "{} {}",
+                    trace_name(v.get_name(), probes),
+                    step_label(k, plot.get_step())
+                ),
+            };
+            (
+                Trace {
+                    name: name.clone(),
+                    points: mag,
+                    color: trace_color(i),
+                },
+                Trace {
+                    name,
+                    points: phase,
+                    color: trace_color(i),
+                },
+            )
+        })
+        .unzip()
+}
+
+/// dB magnitude and unwrapped phase in degrees against frequency
+fn bode_points(freq: &[f64], v: &SimVector) -> (Vec<[f64; 2]>, Vec<[f64; 2]>) {
+    let imag = v.imag().unwrap_or(&[]);
+    let mut mag = Vec::with_capacity(freq.len());
+    let mut phase = Vec::with_capacity(freq.len());
+    let mut prev: Option<f64> = None;
+    for ((f, re), im) in freq.iter().zip(v.real()).zip(imag) {
+        let c = num::complex::Complex::new(*re, *im);
+        mag.push([*f, 20.0 * c.norm().log10()]);
+        // unwrap so that phase does not jump by 360 degrees between adjacent points
+        let mut deg = c.arg().to_degrees();
+        if let Some(p) = prev {
+            deg -= 360.0 * ((deg - p) / 360.0).round();
+        }
+        prev = Some(deg);
+        phase.push([*f, deg]);
+    }
+    (mag, phase)
+}
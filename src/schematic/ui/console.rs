@@ -47,8 +47,8 @@ impl Plugin for ConsolePlugin {
 }
 
 fn write_to_console(mut console_line: EventWriter<PrintConsoleLine>, sres: Res<SPRes>) {
-    for s in sres.get_spm().drain() {
-        console_line.send(PrintConsoleLine::new(s.0, s.1));
+    for s in sres.backend().drain_output() {
+        console_line.send(PrintConsoleLine::simulator(s.0, s.1));
     }
 }
 
@@ -72,12 +72,26 @@ pub struct PrintConsoleLine {
     pub line: String,
     /// color to display line with
     pub color: Color32,
+    /// true if the line is output of the simulator, rather than of the app
+    pub from_simulator: bool,
 }
 
 impl PrintConsoleLine {
     /// Creates a new console line to print.
     pub const fn new(line: String, color: Color32) -> Self {
-        Self { line, color }
+        Self {
+            line,
+            color,
+            from_simulator: false,
+        }
+    }
+    /// Creates a console line of simulator output.
+    pub const fn simulator(line: String, color: Color32) -> Self {
+        Self {
+            line,
+            color,
+            from_simulator: true,
+        }
     }
 }
 
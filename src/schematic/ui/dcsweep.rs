@@ -0,0 +1,93 @@
+//! dc sweep viewer: plots the vectors of the latest dc analysis against the swept source
+//! a nested sweep is shown as a family of curves, one per value of the outer source
+//! all steps of a stepped run are overlaid
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::electrical::{step_label, Probes, SimDc, SimResults};
+
+use super::plot::{is_shown, trace_color, trace_name, trace_selector, Plot, PlotView, Trace};
+
+#[derive(Resource, Default)]
+pub struct DcSweepViewer {
+    /// names of the vectors to plot
+    selected: BTreeSet<String>,
+    view: PlotView,
+}
+
+pub fn dcsweep_ui(
+    mut egui_context: EguiContexts,
+    results: Res<SimResults>,
+    probes: Res<Probes>,
+    mut viewer: ResMut<DcSweepViewer>,
+) {
+    let steps = results.latest_steps("dc");
+    let Some(plot) = steps.first().filter(|p| !p.is_single_point()) else {
+        return;
+    };
+    let Some(scale) = plot.scale() else {
+        return;
+    };
+    // outer sweep values used to tag each curve of the family, as run rather than as set now
+    let outer = plot
+        .get_card()
+        .and_then(SimDc::parse)
+        .and_then(|dc| dc.nested)
+        .map(|n| (n.src.clone(), n.values()));
+    let viewer = &mut *viewer;
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("dc sweep")
+        .default_size([600.0, 300.0])
+        .show(ctx, |ui| {
+            trace_selector(
+                ui,
+                plot.vectors().map(|v| v.get_name()),
+                &mut viewer.selected,
+                &probes,
+            );
+            if ui.button("fit").clicked() {
+                viewer.view.fit();
+            }
+            let mut traces = vec![];
+            for (s, plot) in steps.iter().enumerate() {
+                let Some(scale) = plot.scale() else {
+                    continue;
+                };
+                let segments = plot.segments();
+                for v in plot
+                    .vectors()
+                    .filter(|v| is_shown(v.get_name(), &viewer.selected, &probes))
+                {
+                    let vname = trace_name(v.get_name(), &probes);
+                    for (k, seg) in segments.iter().enumerate() {
+                        let mut name = match &outer {
+                            Some((src, vals)) if segments.len() > 1 => match vals.get(k) {
+                                Some(val) => format!("{} {}={:e}", vname, src, val),
+                                None => format!("{} #{}", vname, k),
+                            },
+                            _ => vname.to_owned(),
+                        };
+                        if steps.len() > 1 {
+                            name = format!("{} {}", name, step_label(s, plot.get_step()));
+                        }
+                        traces.push(Trace {
+                            name,
+                            points: scale.real()[seg.clone()]
+                                .iter()
+                                .zip(&v.real()[seg.clone()])
+                                .map(|(x, y)| [*x, *y])
+                                .collect(),
+                            color: trace_color(traces.len()),
+                        });
+                    }
+                }
+            }
+            Plot::new(&mut viewer.view)
+                .height(ui.available_height().max(150.0))
+                .labels(scale.get_name(), "")
+                .show(ui, &traces);
+        });
+}
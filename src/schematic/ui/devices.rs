@@ -0,0 +1,42 @@
+//! device list: the definitions of the device registry, and where more are read from
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Color32},
+    EguiContexts,
+};
+
+use crate::schematic::electrical::{device_dirs, DeviceRegistry, SpawnDevice};
+
+pub fn devices_ui(
+    mut egui_context: EguiContexts,
+    registry: Res<DeviceRegistry>,
+    mut e_spawn: EventWriter<SpawnDevice>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("devices")
+        .default_open(false)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("devices").striped(true).show(ui, |ui| {
+                    for def in registry.definitions() {
+                        ui.monospace(&def.name);
+                        ui.label(format!("{:?}", def.dtype));
+                        ui.label(def.key.as_deref().unwrap_or_default());
+                        if ui.button("place").clicked() {
+                            e_spawn.send(SpawnDevice::new(def.name.clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+            for e in registry.errors() {
+                ui.colored_label(Color32::YELLOW, e);
+            }
+            ui.separator();
+            ui.label("definitions are read at startup from");
+            for dir in device_dirs() {
+                ui.monospace(dir.display().to_string());
+            }
+        });
+}
@@ -0,0 +1,43 @@
+//! errors and warnings of the latest run, with links to the elements they mention
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::{camera::CenterOn, electrical::Diagnostics};
+
+pub fn diagnostics_ui(
+    mut egui_context: EguiContexts,
+    diagnostics: Res<Diagnostics>,
+    q_transform: Query<&GlobalTransform>,
+    mut e_center: EventWriter<CenterOn>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new(format!("diagnostics ({})", diagnostics.entries().len()))
+        .id(egui::Id::new("diagnostics"))
+        .default_open(false)
+        .show(ctx, |ui| {
+            if diagnostics.entries().is_empty() {
+                ui.label("no errors or warnings");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (i, d) in diagnostics.entries().iter().enumerate() {
+                    ui.colored_label(d.severity.color(), &d.message);
+                    ui.horizontal_wrapped(|ui| {
+                        for (j, (name, e)) in d.links.iter().enumerate() {
+                            // nets are linked once per segment, list each net once
+                            if d.links[..j].iter().any(|(n, _)| n == name) {
+                                continue;
+                            }
+                            let Ok(gt) = q_transform.get(*e) else {
+                                continue;
+                            };
+                            if ui.push_id((i, j), |ui| ui.link(name)).inner.clicked() {
+                                e_center.send(CenterOn(gt.translation().truncate()));
+                            }
+                        }
+                    });
+                    ui.separator();
+                }
+            });
+        });
+}
@@ -0,0 +1,44 @@
+//! electrical rules check findings, with links to the elements concerned
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::{camera::CenterOn, electrical::Erc};
+
+pub fn erc_ui(
+    mut egui_context: EguiContexts,
+    mut erc: ResMut<Erc>,
+    q_transform: Query<&GlobalTransform>,
+    mut e_center: EventWriter<CenterOn>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new(format!("erc ({})", erc.findings().len()))
+        .id(egui::Id::new("erc"))
+        .default_open(false)
+        .show(ctx, |ui| {
+            let mut block = erc.block_on_errors;
+            ui.checkbox(&mut block, "block simulation on errors");
+            // only write on change, the resource is checked before each run
+            if block != erc.block_on_errors {
+                erc.block_on_errors = block;
+            }
+            if erc.findings().is_empty() {
+                ui.label("no findings");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (i, f) in erc.findings().iter().enumerate() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.colored_label(f.rule.severity().color(), &f.message);
+                        for (j, e) in f.entities.iter().enumerate() {
+                            let Ok(gt) = q_transform.get(*e) else {
+                                continue;
+                            };
+                            if ui.push_id((i, j), |ui| ui.link("show")).inner.clicked() {
+                                e_center.send(CenterOn(gt.translation().truncate()));
+                            }
+                        }
+                    });
+                }
+            });
+        });
+}
@@ -0,0 +1,92 @@
+//! export of the latest results of an analysis as csv or ngspice raw file
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Color32},
+    EguiContexts,
+};
+
+use crate::schematic::electrical::{export, ExportFormat, SimResults};
+
+use super::console::PrintConsoleLine;
+
+const ANALYSES: [&str; 4] = ["op", "dc", "ac", "tran"];
+
+#[derive(Resource)]
+pub struct ResultsExport {
+    analysis: &'static str,
+    format: ExportFormat,
+    path: String,
+}
+
+impl Default for ResultsExport {
+    fn default() -> Self {
+        Self {
+            analysis: "tran",
+            format: ExportFormat::Csv,
+            path: "out/tran.csv".to_owned(),
+        }
+    }
+}
+
+impl ResultsExport {
+    /// default path for the current analysis and format
+    fn default_path(&self) -> String {
+        format!("out/{}.{}", self.analysis, self.format.extension())
+    }
+}
+
+pub fn export_ui(
+    mut egui_context: EguiContexts,
+    mut state: ResMut<ResultsExport>,
+    results: Res<SimResults>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+) {
+    let state = &mut *state;
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("export results")
+        .default_open(false)
+        .show(ctx, |ui| {
+            // the path follows analysis and format until edited
+            let was_default = state.path == state.default_path();
+            ui.horizontal(|ui| {
+                for analysis in ANALYSES {
+                    let available = !results.latest_steps(analysis).is_empty();
+                    ui.add_enabled_ui(available, |ui| {
+                        ui.radio_value(&mut state.analysis, analysis, analysis);
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                for format in ExportFormat::ALL {
+                    ui.radio_value(&mut state.format, format, format.name());
+                }
+            });
+            if was_default {
+                state.path = state.default_path();
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.path).font(egui::TextStyle::Monospace),
+                );
+                if ui.button("export").clicked() {
+                    let plots = results.latest_steps(state.analysis);
+                    let (msg, color) = match export(plots, state.format, Path::new(&state.path)) {
+                        Ok(()) => (
+                            format!(
+                                "exported {} ({} plots) to {}",
+                                state.analysis,
+                                plots.len(),
+                                state.path
+                            ),
+                            Color32::GRAY,
+                        ),
+                        Err(e) => (format!("export {}: {}", state.analysis, e), Color32::RED),
+                    };
+                    e_console_rgstr.send(PrintConsoleLine::new(msg, color));
+                }
+            });
+        });
+}
@@ -10,10 +10,25 @@ use bevy_egui::{
     EguiContexts,
 };
 
-use super::electrical::SimAcHz;
+use super::electrical::{
+    format_eng, parse_eng, AcSweep, ComplexFormat, DcSweep, ImportNetlist, LoadRawFile,
+    OpAnnotation, SPRes, SchematicElement, SimAc, SimControl, SimDc, SimRun, SimTran, SpDeviceId,
+    StepConfig, StepMode, MAX_STEPS,
+};
 
+mod bode;
 pub mod console;
+mod dcsweep;
+mod devices;
+mod diagnostics;
+mod erc;
+mod export;
+mod model_browser;
 mod params_editor;
+mod plot;
+mod subckt;
+mod symbol;
+mod waveform;
 
 #[derive(Resource, Default, Deref, DerefMut)]
 struct UiHasFocus(bool);
@@ -37,11 +52,28 @@ impl Plugin for SchematicUiPlugin {
             PreUpdate,
             (
                 params_editor::params_ui.in_set(UiSet::Ui),
-                ac_sim_config.in_set(UiSet::Ui),
+                sim_config.in_set(UiSet::Ui),
+                sim_progress.in_set(UiSet::Ui),
+                import_ui.in_set(UiSet::Ui),
+                model_browser::model_browser_ui.in_set(UiSet::Ui),
+                devices::devices_ui.in_set(UiSet::Ui),
+                symbol::symbol_ui.in_set(UiSet::Ui),
+                subckt::subckt_ui.in_set(UiSet::Ui),
+                export::export_ui.in_set(UiSet::Ui),
+                diagnostics::diagnostics_ui.in_set(UiSet::Ui),
+                erc::erc_ui.in_set(UiSet::Ui),
+                waveform::waveform_ui.in_set(UiSet::Ui),
+                bode::bode_ui.in_set(UiSet::Ui),
+                dcsweep::dcsweep_ui.in_set(UiSet::Ui),
                 consume_input_events.in_set(UiSet::PostUi),
             ),
         );
         app.init_resource::<UiHasFocus>();
+        app.init_resource::<waveform::WaveformViewer>();
+        app.init_resource::<bode::BodeViewer>();
+        app.init_resource::<dcsweep::DcSweepViewer>();
+        app.init_resource::<subckt::SubcktEditor>();
+        app.init_resource::<export::ResultsExport>();
         app.configure_sets(Update, (UiSet::Ui, UiSet::PostUi.after(UiSet::Ui)));
     }
 }
@@ -79,12 +111,266 @@ fn consume_input_events(
     }
 }
 
-fn ac_sim_config(mut egui_context: EguiContexts, mut sim_achz: ResMut<SimAcHz>) {
+fn sim_config(
+    mut egui_context: EguiContexts,
+    mut sim_ac: ResMut<SimAc>,
+    mut sim_tran: ResMut<SimTran>,
+    mut sim_dc: ResMut<SimDc>,
+    mut step_config: ResMut<StepConfig>,
+    mut op_annotation: ResMut<OpAnnotation>,
+    q_devices: Query<(&SchematicElement, &SpDeviceId)>,
+    spres: Res<SPRes>,
+    mut complex_format: ResMut<ComplexFormat>,
+) {
+    // independent sources available for dc sweeps
+    let mut sources = q_devices
+        .iter()
+        .filter(|(se, _)| se.is_source())
+        .filter_map(|(se, spdid)| se.spice_name(spdid))
+        .collect::<Vec<String>>();
+    sources.sort();
+    // any device may be stepped
+    let mut devices = q_devices
+        .iter()
+        .filter_map(|(se, spdid)| se.spice_name(spdid))
+        .collect::<Vec<String>>();
+    devices.sort();
     let ctx = egui_context.ctx_mut();
-    egui::Window::new("ac sim config").show(ctx, |ui| {
+    egui::Window::new("sim config").show(ctx, |ui| {
+        if let Some(e) = spres.ngspice_error() {
+            ui.colored_label(egui::Color32::YELLOW, e);
+            ui.label("tran and dc need ngspice, op and ac use the native solver");
+            ui.separator();
+        }
+        ui.label("op (space)");
+        // only touch the resource on change, labels are updated when it changes
+        let mut show = op_annotation.show;
+        if ui.checkbox(&mut show, "device operating points").changed() {
+            op_annotation.show = show;
+        }
         ui.horizontal(|ui| {
-            ui.label("Stroke:");
-            ui.add(egui::DragValue::new(&mut **sim_achz));
-        })
+            ui.label("complex values");
+            let mut format = *complex_format;
+            for f in ComplexFormat::ALL {
+                ui.selectable_value(&mut format, f, f.name());
+            }
+            if format != *complex_format {
+                *complex_format = format;
+            }
+        });
+        ui.separator();
+        ui.label("ac (ctrl + space)");
+        let sim_ac = &mut *sim_ac;
+        egui::Grid::new("ac").show(ui, |ui| {
+            ui.label("sweep");
+            egui::ComboBox::from_id_source("ac sweep")
+                .selected_text(sim_ac.sweep.keyword())
+                .show_ui(ui, |ui| {
+                    for sweep in [AcSweep::Dec, AcSweep::Oct, AcSweep::Lin] {
+                        ui.selectable_value(&mut sim_ac.sweep, sweep, sweep.keyword());
+                    }
+                });
+            ui.end_row();
+            ui.label("points");
+            ui.add(egui::DragValue::new(&mut sim_ac.points).range(1..=u32::MAX));
+            ui.end_row();
+            for (label, val) in [("fstart", &mut sim_ac.fstart), ("fstop", &mut sim_ac.fstop)] {
+                ui.label(label);
+                ui.add(eng_drag_value(val, "Hz"));
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.label("tran (shift + space)");
+        let sim_tran = &mut *sim_tran;
+        egui::Grid::new("tran").show(ui, |ui| {
+            for (label, val) in [
+                ("tstep", &mut sim_tran.tstep),
+                ("tstop", &mut sim_tran.tstop),
+                ("tstart", &mut sim_tran.tstart),
+                ("tmax", &mut sim_tran.tmax),
+            ] {
+                ui.label(label);
+                ui.add(eng_drag_value(val, "s"));
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.label("dc (alt + space)");
+        let sim_dc = &mut *sim_dc;
+        dc_sweep_grid(ui, "dc", &mut sim_dc.sweep, &sources);
+        let mut nested = sim_dc.nested.is_some();
+        if ui.checkbox(&mut nested, "nested sweep").changed() {
+            sim_dc.nested = nested.then(DcSweep::default);
+        }
+        if let Some(sweep) = sim_dc.nested.as_mut() {
+            dc_sweep_grid(ui, "dc nested", sweep, &sources);
+        }
+        ui.separator();
+        ui.label("step (repeats any analysis)");
+        step_grid(ui, &mut step_config.mode, &devices);
+    });
+}
+
+/// parametric sweep or monte carlo settings
+fn step_grid(ui: &mut egui::Ui, mode: &mut StepMode, devices: &[String]) {
+    let names = ["off", "param", "monte carlo"];
+    let mut selected = match mode {
+        StepMode::Off => 0,
+        StepMode::Param { .. } => 1,
+        StepMode::MonteCarlo { .. } => 2,
+    };
+    egui::ComboBox::from_id_source("step mode")
+        .selected_text(names[selected])
+        .show_index(ui, &mut selected, names.len(), |i| names[i]);
+    match (selected, &mode) {
+        (0, StepMode::Off) | (1, StepMode::Param { .. }) | (2, StepMode::MonteCarlo { .. }) => {}
+        (1, _) => {
+            *mode = StepMode::Param {
+                device: devices.first().cloned().unwrap_or_default(),
+                start: 1e3,
+                stop: 10e3,
+                step: 1e3,
+            }
+        }
+        (2, _) => *mode = StepMode::MonteCarlo { runs: 20, seed: 1 },
+        _ => *mode = StepMode::Off,
+    }
+    egui::Grid::new("step").show(ui, |ui| match mode {
+        StepMode::Off => {}
+        StepMode::Param {
+            device,
+            start,
+            stop,
+            step,
+        } => {
+            ui.label("device");
+            egui::ComboBox::from_id_source("step device")
+                .selected_text(device.as_str())
+                .show_ui(ui, |ui| {
+                    for d in devices {
+                        ui.selectable_value(device, d.clone(), d);
+                    }
+                });
+            ui.end_row();
+            for (label, val) in [("start", start), ("stop", stop), ("step", step)] {
+                ui.label(label);
+                ui.add(eng_drag_value(val, ""));
+                ui.end_row();
+            }
+        }
+        StepMode::MonteCarlo { runs, seed } => {
+            ui.label("runs");
+            ui.add(egui::DragValue::new(runs).range(1..=MAX_STEPS as u32));
+            ui.end_row();
+            ui.label("seed");
+            ui.add(egui::DragValue::new(seed));
+            ui.end_row();
+        }
     });
 }
+
+/// progress of the running simulation with halt/resume/cancel controls
+fn sim_progress(
+    mut egui_context: EguiContexts,
+    simrun: Res<SimRun>,
+    mut e_control: EventWriter<SimControl>,
+) {
+    let Some(analysis) = simrun.get_analysis() else {
+        return;
+    };
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("simulation").show(ctx, |ui| {
+        match simrun.step() {
+            (_, 1) => ui.label(format!("running {}", analysis)),
+            (k, n) => ui.label(format!("running {} (step {}/{})", analysis, k, n)),
+        };
+        ui.add(egui::ProgressBar::new(simrun.progress() / 100.0).show_percentage());
+        ui.horizontal(|ui| {
+            if simrun.is_halted() {
+                if ui.button("resume").clicked() {
+                    e_control.send(SimControl::Resume);
+                }
+            } else if ui.button("halt").clicked() {
+                e_control.send(SimControl::Halt);
+            }
+            if ui.button("cancel").clicked() {
+                e_control.send(SimControl::Cancel);
+            }
+        });
+    });
+}
+
+/// import a spice netlist as schematic, or load results from a raw file
+fn import_ui(
+    mut egui_context: EguiContexts,
+    mut path: Local<String>,
+    mut raw_path: Local<String>,
+    mut e_import: EventWriter<ImportNetlist>,
+    mut e_load: EventWriter<LoadRawFile>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("import netlist")
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut *path)
+                        .hint_text("path/to/netlist.cir")
+                        .font(egui::TextStyle::Monospace),
+                );
+                if ui.button("import").clicked() && !path.trim().is_empty() {
+                    e_import.send(ImportNetlist::new(path.trim().into()));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut *raw_path)
+                        .hint_text("path/to/results.raw")
+                        .font(egui::TextStyle::Monospace),
+                );
+                if ui.button("load results").clicked() && !raw_path.trim().is_empty() {
+                    e_load.send(LoadRawFile::new(raw_path.trim().into()));
+                }
+            });
+        });
+}
+
+/// source picker and range for one dc sweep
+fn dc_sweep_grid(ui: &mut egui::Ui, id: &str, sweep: &mut DcSweep, sources: &[String]) {
+    let unit = match sweep.src.chars().next().map(|c| c.to_ascii_uppercase()) {
+        Some('I') => "A",
+        _ => "V",
+    };
+    egui::Grid::new(id).show(ui, |ui| {
+        ui.label("source");
+        egui::ComboBox::from_id_source(id)
+            .selected_text(sweep.src.as_str())
+            .show_ui(ui, |ui| {
+                for src in sources {
+                    ui.selectable_value(&mut sweep.src, src.clone(), src);
+                }
+            });
+        ui.end_row();
+        for (label, val) in [
+            ("start", &mut sweep.start),
+            ("stop", &mut sweep.stop),
+            ("step", &mut sweep.step),
+        ] {
+            ui.label(label);
+            ui.add(eng_drag_value(val, unit));
+            ui.end_row();
+        }
+    });
+}
+
+/// drag value shown and entered in engineering notation, e.g. `10 kHz` or `10k`
+fn eng_drag_value<'a, Num: egui::emath::Numeric>(
+    value: &'a mut Num,
+    unit: &'static str,
+) -> egui::DragValue<'a> {
+    egui::DragValue::new(value)
+        .speed(0.0)
+        .custom_formatter(move |v, _| format_eng(v, unit))
+        .custom_parser(move |s| parse_eng(s, unit))
+}
@@ -0,0 +1,79 @@
+//! model browser: loads model and library files, and assigns models to the selected devices
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Color32},
+    EguiContexts,
+};
+
+use crate::schematic::electrical::{
+    DeviceParams, ModelLibrary, ModelOrigin, SchematicElement, Selected,
+};
+
+use super::console::PrintConsoleLine;
+
+pub fn model_browser_ui(
+    mut egui_context: EguiContexts,
+    mut library: ResMut<ModelLibrary>,
+    mut path: Local<String>,
+    mut q_selected: Query<(&SchematicElement, &mut DeviceParams), With<Selected>>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("model library")
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut *path)
+                        .hint_text("path/to/models.lib")
+                        .font(egui::TextStyle::Monospace),
+                );
+                if ui.button("load").clicked() && !path.trim().is_empty() {
+                    let line = match library.load_file(Path::new(path.trim())) {
+                        Ok(n) => PrintConsoleLine::new(
+                            format!("loaded {} models from {}", n, path.trim()),
+                            Color32::GREEN,
+                        ),
+                        Err(e) => {
+                            PrintConsoleLine::new(format!("model library: {}", e), Color32::RED)
+                        }
+                    };
+                    e_console_rgstr.send(line);
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("models").striped(true).show(ui, |ui| {
+                    for model in library.models() {
+                        ui.monospace(model.get_name())
+                            .on_hover_text_at_pointer(model.get_card());
+                        ui.label(model.get_kind());
+                        ui.label(match model.get_origin() {
+                            ModelOrigin::Card => "card".to_owned(),
+                            ModelOrigin::Include(path) => path.display().to_string(),
+                            ModelOrigin::LibSection(path, section) => {
+                                format!("{} ({})", path.display(), section)
+                            }
+                        });
+                        // assign to every selected device which may use this model
+                        let mut fitting = q_selected
+                            .iter_mut()
+                            .filter(|(se, _)| se.get_dtype().is_some_and(|t| model.fits(t)))
+                            .peekable();
+                        if ui
+                            .add_enabled(fitting.peek().is_some(), egui::Button::new("assign"))
+                            .clicked()
+                        {
+                            for (_, mut params) in fitting {
+                                params.assign_model(model.get_name());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
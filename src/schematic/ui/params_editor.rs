@@ -4,16 +4,53 @@ use bevy_egui::{
     EguiContexts,
 };
 
-use crate::schematic::electrical::{DeviceParams, Selected};
+use crate::schematic::{
+    electrical::{
+        format_eng, param_schema, parse_eng, DeviceParams, Directive, Distribution, ParamKind,
+        ParamSpec, Preview, SchematicElement, Selected, SpDeviceId, SpDeviceType, Tolerance,
+    },
+    SchematicChanged,
+};
 
-pub fn params_ui(mut egui_context: EguiContexts, mut qs: Query<&mut DeviceParams, With<Selected>>) {
+pub fn params_ui(
+    mut egui_context: EguiContexts,
+    mut qs: Query<
+        (
+            Entity,
+            &SchematicElement,
+            &mut DeviceParams,
+            Option<&mut Tolerance>,
+        ),
+        With<Selected>,
+    >,
+    mut qd: Query<&mut Directive, With<Selected>>,
+    q_devices: Query<(&SchematicElement, &SpDeviceId), Without<Preview>>,
+    mut commands: Commands,
+    mut e_changed: EventWriter<SchematicChanged>,
+) {
     let ctx = egui_context.ctx_mut();
     egui::Window::new("params editor").show(ctx, |ui| {
-        let mut temp = qs.get_single_mut();
-        let Ok(param) = temp.as_deref_mut() else {
+        // directives are edited as multiline spice text
+        // on a copy, so that the directive and its label only change when edited
+        if let Ok(mut directive) = qd.get_single_mut() {
+            let mut text = directive.get_text().to_owned();
+            let response = ui.add(
+                TextEdit::multiline(&mut text)
+                    .desired_width(f32::INFINITY)
+                    .lock_focus(true)
+                    .font(egui::TextStyle::Monospace),
+            );
+            if response.changed() {
+                directive.set_text(text);
+                e_changed.send(SchematicChanged);
+            }
+            return;
+        }
+        let Ok((entity, se, mut param, tolerance)) = qs.get_single_mut() else {
             return;
         };
-        match param {
+        let schema = se.get_dtype().map_or(&[][..], param_schema);
+        match &mut *param {
             DeviceParams::Raw(ref mut s) => {
                 ui.add(
                     TextEdit::singleline(s)
@@ -21,8 +58,135 @@ pub fn params_ui(mut egui_context: EguiContexts, mut qs: Query<&mut DeviceParams
                         .lock_focus(true)
                         .font(egui::TextStyle::Monospace),
                 );
+                // e.g. from schematics saved before params had fields
+                if let Some(fields) = se
+                    .get_dtype()
+                    .map(|dtype| DeviceParams::parse(dtype, s))
+                    .filter(|p| matches!(p, DeviceParams::Fields(_)))
+                {
+                    if ui.button("edit as fields").clicked() {
+                        *param = fields;
+                    }
+                }
+            }
+            DeviceParams::Fields(fields) => {
+                egui::Grid::new("param fields").show(ui, |ui| {
+                    for field in fields.iter_mut() {
+                        let Some(spec) = schema.iter().find(|s| s.name == field.name) else {
+                            continue;
+                        };
+                        ui.label(spec.label);
+                        let id = (entity, spec.name);
+                        let checked = match spec.kind {
+                            ParamKind::Reference(dtype) => {
+                                reference_pick(ui, id, spec, dtype, &q_devices, &mut field.value)
+                            }
+                            _ => field_edit(ui, id, spec, &mut field.value),
+                        };
+                        // numbers are shown with their prefix, e.g. 4.7 kΩ for 4700
+                        let number = match spec.kind {
+                            ParamKind::Reference(_) => None,
+                            _ => parse_eng(&field.value, ""),
+                        };
+                        match number {
+                            Some(v) => ui.label(format_eng(v, spec.unit)),
+                            None => ui.label(spec.unit),
+                        };
+                        if let Err(e) = checked {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(dtype) = se.get_dtype() {
+                    let text = param.spice_param(dtype);
+                    ui.label(egui::RichText::new(text).monospace().weak());
+                }
             }
-            DeviceParams::Float(_f) => todo!(), // not supported yet
         }
+        // tolerance used by monte carlo runs
+        ui.horizontal(|ui| match tolerance {
+            Some(mut tolerance) => {
+                let tolerance = &mut *tolerance;
+                ui.label("tolerance");
+                ui.add(
+                    egui::DragValue::new(&mut tolerance.tol)
+                        .speed(0.001)
+                        .range(0.0..=1.0)
+                        .custom_formatter(|v, _| format!("{:.1}%", v * 100.0))
+                        .custom_parser(|s| {
+                            let pct = s.trim().trim_end_matches('%').parse::<f64>().ok()?;
+                            Some(pct / 100.0)
+                        }),
+                );
+                for dist in [Distribution::Uniform, Distribution::Gaussian] {
+                    ui.selectable_value(&mut tolerance.dist, dist, dist.name());
+                }
+                if ui.button("remove").clicked() {
+                    commands.entity(entity).remove::<Tolerance>();
+                }
+            }
+            None => {
+                if ui.button("add tolerance").clicked() {
+                    commands.entity(entity).insert(Tolerance::default());
+                }
+            }
+        });
     });
 }
+
+/// edit a parameter field, only taking valid values
+/// while the field has focus the text being typed is kept in egui memory, so that it may be
+/// invalid on the way, e.g. `1e` before `1e-3`
+fn field_edit(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    spec: &ParamSpec,
+    value: &mut String,
+) -> Result<(), String> {
+    let id = egui::Id::new(id);
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| value.clone());
+    let response = ui.add(
+        TextEdit::singleline(&mut text)
+            .id(id.with("edit"))
+            .desired_width(120.0)
+            .font(egui::TextStyle::Monospace),
+    );
+    let checked = spec.check(&text);
+    if checked.is_ok() && text != *value {
+        *value = text.clone();
+    }
+    match response.has_focus() {
+        true => ui.data_mut(|d| d.insert_temp(id, text)),
+        false => ui.data_mut(|d| d.remove::<String>(id)),
+    }
+    checked
+}
+
+/// pick another device of type `dtype` by its id, e.g. the controlling source of a cccs
+fn reference_pick(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    spec: &ParamSpec,
+    dtype: &SpDeviceType,
+    q_devices: &Query<(&SchematicElement, &SpDeviceId), Without<Preview>>,
+    value: &mut String,
+) -> Result<(), String> {
+    let mut ids = q_devices
+        .iter()
+        .filter(|(se, _)| se.get_dtype() == Some(dtype))
+        .map(|(_, spdid)| spdid.get_id().to_owned())
+        .collect::<Vec<_>>();
+    ids.sort();
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{}{}", dtype.prefix(), value))
+        .show_ui(ui, |ui| {
+            for id in ids {
+                let text = format!("{}{}", dtype.prefix(), id);
+                ui.selectable_value(value, id, text);
+            }
+        });
+    spec.check(value)
+}
@@ -0,0 +1,359 @@
+//! minimal line plot widget drawn with the egui painter
+//! supports zooming (scroll) and panning (drag) along the x axis, double click to fit
+
+use std::collections::BTreeSet;
+
+use bevy_egui::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Shape, Stroke, Ui};
+
+use crate::schematic::electrical::Probes;
+
+/// colors cycled through for successive traces
+const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(0x1f, 0x77, 0xb4),
+    Color32::from_rgb(0xff, 0x7f, 0x0e),
+    Color32::from_rgb(0x2c, 0xa0, 0x2c),
+    Color32::from_rgb(0xd6, 0x27, 0x28),
+    Color32::from_rgb(0x94, 0x67, 0xbd),
+    Color32::from_rgb(0x8c, 0x56, 0x4b),
+    Color32::from_rgb(0xe3, 0x77, 0xc2),
+    Color32::from_rgb(0x17, 0xbe, 0xcf),
+];
+
+/// space reserved around the plot area for tick labels
+const MARGIN_LEFT: f32 = 60.0;
+const MARGIN_RIGHT: f32 = 10.0;
+const MARGIN_TOP: f32 = 10.0;
+const MARGIN_BOTTOM: f32 = 20.0;
+
+/// target number of ticks per axis
+const TICKS: usize = 6;
+
+pub fn trace_color(i: usize) -> Color32 {
+    PALETTE[i % PALETTE.len()]
+}
+
+/// row of checkboxes to pick which of `names` are plotted
+/// probed vectors are always plotted and not listed
+pub fn trace_selector<'a>(
+    ui: &mut Ui,
+    names: impl Iterator<Item = &'a str>,
+    selected: &mut BTreeSet<String>,
+    probes: &Probes,
+) {
+    ui.horizontal_wrapped(|ui| {
+        for name in names.filter(|n| probes.label_of(n).is_none()) {
+            let mut shown = selected.contains(name);
+            if ui.checkbox(&mut shown, name).changed() {
+                if shown {
+                    selected.insert(name.to_owned());
+                } else {
+                    selected.remove(name);
+                }
+            }
+        }
+    });
+}
+
+/// true if vector `name` is plotted, either picked in the trace selector or probed
+pub fn is_shown(name: &str, selected: &BTreeSet<String>, probes: &Probes) -> bool {
+    selected.contains(name) || probes.label_of(name).is_some()
+}
+
+/// legend name of vector `name`, the probe label if probed
+pub fn trace_name<'a>(name: &'a str, probes: &'a Probes) -> &'a str {
+    probes.label_of(name).unwrap_or(name)
+}
+
+/// a named series of (x, y) points
+pub struct Trace {
+    pub name: String,
+    pub points: Vec<[f64; 2]>,
+    pub color: Color32,
+}
+
+/// visible x range of a plot, kept by the owner of the plot between frames
+#[derive(Default, Clone)]
+pub struct PlotView {
+    /// visible x range in axis coordinates (log10 if log axis), fit to data if None
+    x: Option<(f64, f64)>,
+}
+
+impl PlotView {
+    /// zoom to fit all data on next draw
+    pub fn fit(&mut self) {
+        self.x = None;
+    }
+}
+
+pub struct Plot<'a> {
+    view: &'a mut PlotView,
+    log_x: bool,
+    height: f32,
+    x_label: &'a str,
+    y_label: &'a str,
+}
+
+impl<'a> Plot<'a> {
+    pub fn new(view: &'a mut PlotView) -> Self {
+        Self {
+            view,
+            log_x: false,
+            height: 200.0,
+            x_label: "",
+            y_label: "",
+        }
+    }
+    pub fn log_x(mut self, log_x: bool) -> Self {
+        self.log_x = log_x;
+        self
+    }
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+    pub fn labels(mut self, x_label: &'a str, y_label: &'a str) -> Self {
+        self.x_label = x_label;
+        self.y_label = y_label;
+        self
+    }
+
+    /// maps data x to axis coordinates
+    fn axis_x(&self, x: f64) -> f64 {
+        if self.log_x {
+            x.log10()
+        } else {
+            x
+        }
+    }
+
+    /// maps axis coordinates back to data x
+    fn data_x(&self, u: f64) -> f64 {
+        if self.log_x {
+            10f64.powf(u)
+        } else {
+            u
+        }
+    }
+
+    pub fn show(self, ui: &mut Ui, traces: &[Trace]) -> egui::Response {
+        let size = egui::vec2(ui.available_width(), self.height);
+        let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+        let frame = response.rect;
+        let plot_rect = Rect::from_min_max(
+            frame.min + egui::vec2(MARGIN_LEFT, MARGIN_TOP),
+            frame.max - egui::vec2(MARGIN_RIGHT, MARGIN_BOTTOM),
+        );
+        let text_color = ui.visuals().text_color();
+        let grid_color = ui.visuals().weak_text_color().gamma_multiply(0.3);
+        let font = FontId::monospace(10.0);
+        painter.rect_stroke(plot_rect, 0.0, Stroke::new(1.0, grid_color));
+
+        // data extents along x
+        let mut xbounds = (f64::INFINITY, f64::NEG_INFINITY);
+        for p in traces.iter().flat_map(|t| t.points.iter()) {
+            let u = self.axis_x(p[0]);
+            if u.is_finite() {
+                xbounds = (xbounds.0.min(u), xbounds.1.max(u));
+            }
+        }
+        if xbounds.0 > xbounds.1 {
+            painter.text(
+                plot_rect.center(),
+                Align2::CENTER_CENTER,
+                "no data",
+                font,
+                text_color,
+            );
+            return response;
+        }
+
+        // pan and zoom along x
+        let (mut x0, mut x1) = self.view.x.unwrap_or(xbounds);
+        if x1 <= x0 {
+            x0 -= 0.5;
+            x1 += 0.5;
+        }
+        let units_per_px = (x1 - x0) / plot_rect.width() as f64;
+        if response.double_clicked() {
+            self.view.fit();
+            (x0, x1) = xbounds;
+        } else if response.dragged() {
+            let dx = response.drag_delta().x as f64 * units_per_px;
+            (x0, x1) = (x0 - dx, x1 - dx);
+            self.view.x = Some((x0, x1));
+        }
+        if let Some(hover) = response.hover_pos() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                let pivot = x0 + (hover.x - plot_rect.left()) as f64 * units_per_px;
+                let factor = (-scroll as f64 * 0.002).exp();
+                (x0, x1) = (pivot + (x0 - pivot) * factor, pivot + (x1 - pivot) * factor);
+                self.view.x = Some((x0, x1));
+            }
+        }
+
+        // fit y to the visible portion of the data
+        let mut ybounds = (f64::INFINITY, f64::NEG_INFINITY);
+        for p in traces.iter().flat_map(|t| t.points.iter()) {
+            let u = self.axis_x(p[0]);
+            if u >= x0 && u <= x1 && p[1].is_finite() {
+                ybounds = (ybounds.0.min(p[1]), ybounds.1.max(p[1]));
+            }
+        }
+        let (mut y0, mut y1) = if ybounds.0 <= ybounds.1 {
+            ybounds
+        } else {
+            (-1.0, 1.0)
+        };
+        let pad = ((y1 - y0) * 0.05)
+            .max(y0.abs().max(y1.abs()) * 1e-3)
+            .max(1e-15);
+        y0 -= pad;
+        y1 += pad;
+
+        let to_screen = |u: f64, y: f64| {
+            Pos2::new(
+                plot_rect.left() + ((u - x0) / (x1 - x0)) as f32 * plot_rect.width(),
+                plot_rect.bottom() - ((y - y0) / (y1 - y0)) as f32 * plot_rect.height(),
+            )
+        };
+
+        // grid and tick labels
+        let xticks = if self.log_x && x1 - x0 >= 2.0 {
+            (x0.ceil() as i32..=x1.floor() as i32)
+                .map(|d| d as f64)
+                .collect()
+        } else {
+            ticks(x0, x1)
+        };
+        for u in xticks {
+            let x = to_screen(u, y0).x;
+            painter.line_segment(
+                [
+                    Pos2::new(x, plot_rect.top()),
+                    Pos2::new(x, plot_rect.bottom()),
+                ],
+                Stroke::new(1.0, grid_color),
+            );
+            painter.text(
+                Pos2::new(x, plot_rect.bottom() + 2.0),
+                Align2::CENTER_TOP,
+                fmt_tick(self.data_x(u)),
+                font.clone(),
+                text_color,
+            );
+        }
+        for y in ticks(y0, y1) {
+            let py = to_screen(x0, y).y;
+            painter.line_segment(
+                [
+                    Pos2::new(plot_rect.left(), py),
+                    Pos2::new(plot_rect.right(), py),
+                ],
+                Stroke::new(1.0, grid_color),
+            );
+            painter.text(
+                Pos2::new(plot_rect.left() - 4.0, py),
+                Align2::RIGHT_CENTER,
+                fmt_tick(y),
+                font.clone(),
+                text_color,
+            );
+        }
+        painter.text(
+            plot_rect.right_bottom() + egui::vec2(0.0, 2.0),
+            Align2::RIGHT_TOP,
+            self.x_label,
+            font.clone(),
+            text_color,
+        );
+        painter.text(
+            frame.left_top(),
+            Align2::LEFT_TOP,
+            self.y_label,
+            font.clone(),
+            text_color,
+        );
+
+        // traces
+        let clipped = painter.with_clip_rect(plot_rect);
+        for trace in traces {
+            let pts = trace
+                .points
+                .iter()
+                .map(|p| (self.axis_x(p[0]), p[1]))
+                .filter(|(u, y)| u.is_finite() && y.is_finite())
+                .map(|(u, y)| to_screen(u, y))
+                .collect::<Vec<Pos2>>();
+            if pts.len() == 1 {
+                clipped.circle_filled(pts[0], 2.0, trace.color);
+            } else {
+                clipped.add(Shape::line(pts, Stroke::new(1.5, trace.color)));
+            }
+        }
+
+        // legend
+        for (i, trace) in traces.iter().enumerate() {
+            clipped.text(
+                plot_rect.left_top() + egui::vec2(4.0, 4.0 + 12.0 * i as f32),
+                Align2::LEFT_TOP,
+                &trace.name,
+                font.clone(),
+                trace.color,
+            );
+        }
+
+        // cursor readout
+        if let Some(hover) = response.hover_pos().filter(|p| plot_rect.contains(*p)) {
+            clipped.line_segment(
+                [
+                    Pos2::new(hover.x, plot_rect.top()),
+                    Pos2::new(hover.x, plot_rect.bottom()),
+                ],
+                Stroke::new(1.0, text_color.gamma_multiply(0.5)),
+            );
+            let u = x0 + (hover.x - plot_rect.left()) as f64 * (x1 - x0) / plot_rect.width() as f64;
+            let y = y1 - (hover.y - plot_rect.top()) as f64 * (y1 - y0) / plot_rect.height() as f64;
+            clipped.text(
+                plot_rect.right_top() + egui::vec2(-4.0, 4.0),
+                Align2::RIGHT_TOP,
+                format!("x: {}, y: {}", fmt_tick(self.data_x(u)), fmt_tick(y)),
+                font,
+                text_color,
+            );
+        }
+        response
+    }
+}
+
+/// evenly spaced ticks at 1, 2, 5 multiples of a power of ten
+fn ticks(lo: f64, hi: f64) -> Vec<f64> {
+    let span = hi - lo;
+    if !(span > 0.0 && span.is_finite()) {
+        return vec![];
+    }
+    let raw = span / TICKS as f64;
+    let mag = 10f64.powf(raw.log10().floor());
+    let step = mag
+        * match raw / mag {
+            n if n < 1.5 => 1.0,
+            n if n < 3.0 => 2.0,
+            n if n < 7.0 => 5.0,
+            _ => 10.0,
+        };
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+fn fmt_tick(v: f64) -> String {
+    let a = v.abs();
+    if a == 0.0 || (1e-2..1e4).contains(&a) {
+        format!("{:.3}", v)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        format!("{:.2e}", v)
+    }
+}
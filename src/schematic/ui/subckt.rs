@@ -0,0 +1,73 @@
+//! subcircuit editor: turns the selection, or the whole schematic, into a subcircuit block
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Color32},
+    EguiContexts,
+};
+
+use crate::schematic::electrical::{SpawnSubckt, SubcktBuilder};
+
+use super::console::PrintConsoleLine;
+
+#[derive(Resource, Default)]
+pub struct SubcktEditor {
+    name: String,
+    /// per net: whether it is a pin, and the pin name
+    pins: HashMap<String, (bool, String)>,
+}
+
+pub fn subckt_ui(
+    mut egui_context: EguiContexts,
+    mut editor: ResMut<SubcktEditor>,
+    builder: SubcktBuilder,
+    mut e_spawn: EventWriter<SpawnSubckt>,
+    mut e_console_rgstr: EventWriter<PrintConsoleLine>,
+) {
+    let editor = &mut *editor;
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("subcircuit")
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("name");
+                ui.text_edit_singleline(&mut editor.name);
+            });
+            ui.label("pins (from selection, or all devices if none selected)");
+            let candidates = builder.candidate_pins();
+            egui::Grid::new("subckt pins").show(ui, |ui| {
+                for (net, boundary) in candidates.iter() {
+                    // nets leaving the selection are pins by default
+                    let (is_pin, pin) = editor
+                        .pins
+                        .entry(net.clone())
+                        .or_insert_with(|| (*boundary, net.clone()));
+                    ui.checkbox(is_pin, net.as_str());
+                    ui.add_enabled(*is_pin, egui::TextEdit::singleline(pin));
+                    ui.end_row();
+                }
+            });
+            if ui.button("create").clicked() {
+                let pins = candidates
+                    .iter()
+                    .filter_map(|(net, _)| match editor.pins.get(net) {
+                        Some((true, pin)) => Some((net.clone(), pin.trim().to_owned())),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                match builder.build(editor.name.trim(), &pins) {
+                    Ok(subckt) => {
+                        e_spawn.send(SpawnSubckt::new(subckt));
+                    }
+                    Err(e) => {
+                        e_console_rgstr.send(PrintConsoleLine::new(
+                            format!("subcircuit: {}", e),
+                            Color32::RED,
+                        ));
+                    }
+                }
+            }
+        });
+}
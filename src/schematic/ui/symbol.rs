@@ -0,0 +1,154 @@
+//! symbol editor window: enters and leaves the editor, and edits what is not drawn
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::{
+    electrical::{DeviceRegistry, SpDeviceType},
+    symbol::{EditorState, SaveSymbol, SymbolEditor, SymbolTool},
+    tools::SchematicToolState,
+};
+
+/// device types a symbol may be made for, subcircuits have generated symbols
+const TYPES: [&str; 18] = [
+    "Gnd", "V", "I", "R", "L", "C", "E", "F", "G", "H", "B", "K", "S", "W", "T", "D", "Q", "M",
+];
+
+const TOOLS: [(SymbolTool, &str); 5] = [
+    (SymbolTool::Line, "line (W)"),
+    (SymbolTool::Arc, "arc (A)"),
+    (SymbolTool::Circle, "circle (O)"),
+    (SymbolTool::Port, "port (P)"),
+    (SymbolTool::Pick, "pick box (B)"),
+];
+
+pub fn symbol_ui(
+    mut egui_context: EguiContexts,
+    editorstate: Res<State<EditorState>>,
+    mut editorstate_next: ResMut<NextState<EditorState>>,
+    mut toolst_next: ResMut<NextState<SchematicToolState>>,
+    mut editor: ResMut<SymbolEditor>,
+    registry: Res<DeviceRegistry>,
+    mut e_save: EventWriter<SaveSymbol>,
+) {
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("symbol editor")
+        .default_open(false)
+        .show(ctx, |ui| {
+            if *editorstate.get() == EditorState::Schematic {
+                if ui.button("edit symbols").clicked() {
+                    editorstate_next.set(EditorState::Symbol);
+                }
+                return;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("back to schematic").clicked() {
+                    editorstate_next.set(EditorState::Schematic);
+                }
+                egui::ComboBox::from_id_source("symbol open")
+                    .selected_text("open")
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(false, "new").clicked() {
+                            editor.open(SymbolEditor::default().def);
+                        }
+                        for def in registry.definitions() {
+                            if ui.selectable_label(false, &def.name).clicked() {
+                                editor.open(def.clone());
+                            }
+                        }
+                    });
+            });
+            ui.separator();
+            // only written when edited, so that an unchanged editor is not redrawn
+            let def = &mut editor.bypass_change_detection().def;
+            let mut changed = false;
+            egui::Grid::new("symbol def").show(ui, |ui| {
+                ui.label("name");
+                changed |= ui.text_edit_singleline(&mut def.name).changed();
+                ui.end_row();
+                ui.label("type");
+                egui::ComboBox::from_id_source("symbol type")
+                    .selected_text(format!("{:?}", def.dtype))
+                    .show_ui(ui, |ui| {
+                        for dtype in TYPES.iter().filter_map(|t| SpDeviceType::from_prefix(t)) {
+                            let text = format!("{:?}", dtype);
+                            changed |= ui.selectable_value(&mut def.dtype, dtype, text).changed();
+                        }
+                    });
+                ui.end_row();
+                ui.label("key");
+                let mut key = def.key.clone().unwrap_or_default();
+                if ui
+                    .add(egui::TextEdit::singleline(&mut key).char_limit(1))
+                    .changed()
+                {
+                    def.key = Some(key.trim().to_owned()).filter(|k| !k.is_empty());
+                    changed = true;
+                }
+                ui.end_row();
+                ui.label("params");
+                changed |= ui.text_edit_singleline(&mut def.params).changed();
+                ui.end_row();
+            });
+            ui.label(format!(
+                "ports ({} of {}), in spice order",
+                def.ports.len(),
+                def.dtype.port_count().unwrap_or_default()
+            ));
+            let mut remove = None;
+            egui::Grid::new("symbol ports")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, (offset, pin)) in def.ports.iter_mut().enumerate() {
+                        ui.monospace(format!("{} {}", offset.x, offset.y));
+                        if ui
+                            .add(egui::TextEdit::singleline(pin).hint_text("pin name"))
+                            .changed()
+                        {
+                            // names are a single word of the definition
+                            *pin = pin.split_whitespace().collect::<Vec<_>>().join("_");
+                            changed = true;
+                        }
+                        if ui.small_button("remove").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(i) = remove {
+                def.ports.remove(i);
+                changed = true;
+            }
+            let [p0, p1] = def.pick;
+            ui.label(format!("pick box {} {} to {} {}", p0.x, p0.y, p1.x, p1.y));
+            if changed {
+                editor.set_changed();
+            }
+            ui.separator();
+            ui.horizontal_wrapped(|ui| {
+                for (tool, text) in TOOLS {
+                    if ui
+                        .selectable_label(editor.tool() == Some(tool), text)
+                        .clicked()
+                    {
+                        editor.set_tool(Some(tool));
+                        toolst_next.set(match tool {
+                            SymbolTool::Line => SchematicToolState::Wiring,
+                            _ => SchematicToolState::Idle,
+                        });
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("undo").clicked() {
+                    editor.undo();
+                }
+                if ui.button("clear").clicked() {
+                    editor.def.path.clear();
+                }
+                if ui.button("save").clicked() {
+                    e_save.send(SaveSymbol);
+                }
+            });
+        });
+}
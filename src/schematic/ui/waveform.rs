@@ -0,0 +1,84 @@
+//! waveform viewer: plots the vectors of the latest transient analysis over time
+//! all steps of a stepped run are overlaid
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::schematic::electrical::{step_label, Probes, SimResults};
+
+use super::plot::{is_shown, trace_color, trace_name, trace_selector, Plot, PlotView, Trace};
+
+#[derive(Resource, Default)]
+pub struct WaveformViewer {
+    /// names of the vectors to plot
+    selected: BTreeSet<String>,
+    view: PlotView,
+}
+
+pub fn waveform_ui(
+    mut egui_context: EguiContexts,
+    results: Res<SimResults>,
+    probes: Res<Probes>,
+    mut viewer: ResMut<WaveformViewer>,
+) {
+    let steps = results.latest_steps("tran");
+    let Some(plot) = steps.first() else {
+        return;
+    };
+    let viewer = &mut *viewer;
+    let ctx = egui_context.ctx_mut();
+    egui::Window::new("waveform viewer")
+        .default_size([600.0, 300.0])
+        .show(ctx, |ui| {
+            trace_selector(
+                ui,
+                plot.vectors().map(|v| v.get_name()),
+                &mut viewer.selected,
+                &probes,
+            );
+            if ui.button("fit").clicked() {
+                viewer.view.fit();
+            }
+            let traces = steps
+                .iter()
+                .enumerate()
+                .flat_map(|(k, plot)| {
+                    plot.vectors()
+                        .filter(|v| is_shown(v.get_name(), &viewer.selected, &probes))
+                        .map(move |v| (k, plot, v))
+                })
+                .enumerate()
+                .map(|(i, (k, plot, v))| Trace {
+                    name: match steps.len() {
+                        1 => trace_name(v.get_name(), &probes).to_owned(),
+                        _ => format!(
+                            "{} {}",
+                            trace_name(v.get_name(), &probes),
+                            step_label(k, plot.get_step())
+                        ),
+                    },
+                    points: match plot.scale() {
+                        Some(t) => t
+                            .real()
+                            .iter()
+                            .zip(v.real())
+                            .map(|(t, y)| [*t, *y])
+                            .collect(),
+                        None => v
+                            .real()
+                            .iter()
+                            .enumerate()
+                            .map(|(i, y)| [i as f64, *y])
+                            .collect(),
+                    },
+                    color: trace_color(i),
+                })
+                .collect::<Vec<Trace>>();
+            Plot::new(&mut viewer.view)
+                .height(ui.available_height().max(150.0))
+                .labels("time (s)", "")
+                .show(ui, &traces);
+        });
+}